@@ -0,0 +1,22 @@
+// Generates `EXPECTED_SCHEMA_VERSION` from the migrations embedded by `sqlx::migrate!`, which doesn't make cargo
+// rebuild the crate when they change either
+use std::env;
+use std::fs;
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+    let latest = fs::read_dir("migrations")
+        .expect("could not read the migrations directory")
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            if !name.ends_with(".sql") {
+                return None;
+            }
+            name.split('_').next()?.parse::<i64>().ok()
+        })
+        .max()
+        .unwrap_or(0);
+    let out = Path::new(&env::var("OUT_DIR").unwrap()).join("schema_version.rs");
+    fs::write(out, latest.to_string()).expect("could not write the schema version");
+}
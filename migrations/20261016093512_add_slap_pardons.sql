@@ -0,0 +1,5 @@
+-- soft pardons: pardoned slaps are kept but flagged
+alter table slaps
+    add column pardoned_by bigint,
+    add column pardoned_at timestamptz,
+    add constraint pardon_complete check ((pardoned_by is null) = (pardoned_at is null))
@@ -0,0 +1,12 @@
+-- slap creation date, used for retention
+alter table slaps
+    add column created_at timestamptz;
+
+-- existing slaps were issued when their sentence was sent, snowflakes hold the milliseconds since the discord
+-- epoch (2015-01-01) in their upper 42 bits
+update slaps
+    set created_at = to_timestamp(((sentence >> 22) + 1420070400000) / 1000.0);
+
+alter table slaps
+    alter column created_at set default now(),
+    alter column created_at set not null;
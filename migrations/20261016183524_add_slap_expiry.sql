@@ -0,0 +1,3 @@
+-- temporary slaps: expired ones are deleted by `GuildSlapRecord::expunge_expired_slaps`
+alter table slaps
+    add column expires_at timestamptz
@@ -0,0 +1,3 @@
+-- guild-wide default expiry of slaps, null means slaps don't expire unless told otherwise
+alter table guilds
+    add column default_slap_expiry_days integer check (default_slap_expiry_days > 0)
@@ -0,0 +1,3 @@
+-- private annotations of moderators, unlike `reason` they are never shown to the offender
+alter table slaps
+    add column notes text
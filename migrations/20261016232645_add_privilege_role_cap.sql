@@ -0,0 +1,18 @@
+-- cap on the number of roles of each privilege, the default matches `DEFAULT_MAX_ROLES_PER_PRIVILEGE`
+alter table guilds
+    add column max_roles_per_privilege integer not null default 250 check (max_roles_per_privilege > 0);
+
+-- guilds already over the cap keep their roles
+update guilds
+    set max_roles_per_privilege = greatest(
+        max_roles_per_privilege,
+        cardinality(priv_admin),
+        cardinality(priv_manager),
+        cardinality(priv_event)
+    );
+
+-- backstop for the checks made by the crate, the names are mapped to `GuildConfigError::TooManyRoles`
+alter table guilds
+    add constraint guilds_priv_admin_cap check (cardinality(priv_admin) <= max_roles_per_privilege),
+    add constraint guilds_priv_manager_cap check (cardinality(priv_manager) <= max_roles_per_privilege),
+    add constraint guilds_priv_event_cap check (cardinality(priv_event) <= max_roles_per_privilege);
@@ -0,0 +1,3 @@
+-- number of slaps after which a member is escalated, null means escalation is disabled
+alter table guilds
+    add column slap_threshold integer check (slap_threshold > 0)
@@ -0,0 +1,304 @@
+//! Synchronous API, requires the `blocking` feature
+//!
+//! For applications that don't run an async runtime, such as scripts and CLI tools. A [`BlockingPool`] owns a
+//! [connection pool] along with a small runtime driving it. [`GuildConfig`] and [`GuildSlapRecord`] mirror the
+//! methods of their [`crate::guild`] and [`crate::slap`] counterparts, blocking until the query completes.
+//! Methods returning a stream return a [`Vec`] instead.
+//!
+//! ```no_run
+//! # fn example() -> Result<(), db_adapter::AdapterError> {
+//! use db_adapter::blocking::BlockingPool;
+//!
+//! let db = BlockingPool::from_env()?;
+//! let slaps = db.slaps(4200.into()).len()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Async contexts
+//! This API must not be used from within an async runtime: blocking there would stall every task of the
+//! runtime. Every call checks it first and returns [`AdapterError::BlockingInAsyncContext`] instead of
+//! blocking. Dropping the last clone of a [`BlockingPool`] from within an async runtime panics, like dropping
+//! any tokio runtime does.
+//!
+//! [connection pool]: sqlx::postgres::PgPool
+
+use crate::connection::ConnectionConfig;
+use crate::guild::{
+    self, EffectivePrivileges, GuildConfigBuilder, GuildConfigData, MessageType, Privilege,
+    PrivilegeDiff, PrivilegeSet, PrivilegeSnapshot,
+};
+use crate::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use crate::metrics::PoolMetrics;
+use crate::slap::{
+    self, ActivityBucket, Enforcer, MemberSlapRecord, MonthlySlapSummary, SlapReport,
+};
+use crate::{AdapterError, PgPool};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::TryStreamExt;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::runtime::{self, Handle, Runtime};
+
+type Result<Return> = std::result::Result<Return, AdapterError>;
+
+/// A [connection pool] along with the runtime driving it
+///
+/// Cloning it is cheap, clones share the same pool and runtime.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+#[derive(Debug, Clone)]
+pub struct BlockingPool {
+    runtime: Arc<Runtime>,
+    pool: PgPool,
+}
+
+impl BlockingPool {
+    /// Connects to the database described by the environment
+    ///
+    /// See [`crate::try_establish_connection`].
+    pub fn from_env() -> Result<Self> {
+        Self::new(crate::try_establish_connection())
+    }
+
+    /// Connects to the database at `url`
+    ///
+    /// See [`crate::establish_connection_with`].
+    pub fn connect(url: &str) -> Result<Self> {
+        Self::new(crate::establish_connection_with(url))
+    }
+
+    /// Connects to the database at `url` with the settings of `config`
+    pub fn connect_with(config: &ConnectionConfig, url: &str) -> Result<Self> {
+        Self::new(config.connect(url))
+    }
+
+    // the pool must be created by the runtime that drives it since its connections are bound to its reactor
+    fn new<F: Future<Output = Result<PgPool>>>(connect: F) -> Result<Self> {
+        ensure_blocking_allowed()?;
+        let runtime = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let pool = runtime.block_on(connect)?;
+        Ok(BlockingPool {
+            runtime: Arc::new(runtime),
+            pool,
+        })
+    }
+
+    /// Configuration of the guild `id`
+    pub fn guild(&self, id: GuildId) -> GuildConfig {
+        GuildConfig {
+            inner: guild::GuildConfig(id),
+            db: self.clone(),
+        }
+    }
+
+    /// Slap record of the guild `id`
+    pub fn slaps(&self, id: GuildId) -> GuildSlapRecord {
+        GuildSlapRecord {
+            inner: slap::GuildSlapRecord(id),
+            db: self.clone(),
+        }
+    }
+
+    /// Blocking version of [`guild::GuildConfig::new`]
+    pub fn create_guild(&self, builder: GuildConfigBuilder<'_>) -> Result<GuildConfig> {
+        let inner = self.run(guild::GuildConfig::new(&self.pool, builder))?;
+        Ok(GuildConfig {
+            inner,
+            db: self.clone(),
+        })
+    }
+
+    /// Current metrics of the pool, see [`crate::metrics::metrics`]
+    pub fn metrics(&self) -> PoolMetrics {
+        crate::metrics::metrics(&self.pool)
+    }
+
+    /// Closes the pool, waiting for the connections to be returned to it
+    pub fn close(&self) -> Result<()> {
+        ensure_blocking_allowed()?;
+        self.runtime.block_on(self.pool.close());
+        Ok(())
+    }
+
+    fn run<T, F: Future<Output = Result<T>>>(&self, future: F) -> Result<T> {
+        ensure_blocking_allowed()?;
+        self.runtime.block_on(future)
+    }
+}
+
+fn ensure_blocking_allowed() -> Result<()> {
+    match Handle::try_current() {
+        Ok(_) => Err(AdapterError::BlockingInAsyncContext),
+        Err(_) => Ok(()),
+    }
+}
+
+// Defines methods forwarding to the async method of the same name on `self.inner`, with the pool as `conn`
+macro_rules! blocking {
+    ($path:literal; $($(#[$attr:meta])* fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            #[doc = concat!("Blocking version of [`", $path, "::", stringify!($name), "`]")]
+            $(#[$attr])*
+            pub fn $name(&self $(, $arg: $ty)*) -> Result<$ret> {
+                self.db.run(self.inner.$name(&self.db.pool $(, $arg)*))
+            }
+        )*
+    };
+}
+
+/// Blocking version of [`guild::GuildConfig`]
+///
+/// Obtained from [`BlockingPool::guild`] or [`BlockingPool::create_guild`].
+#[derive(Debug)]
+pub struct GuildConfig {
+    inner: guild::GuildConfig,
+    db: BlockingPool,
+}
+
+impl GuildConfig {
+    /// ID of the guild
+    pub fn id(&self) -> GuildId {
+        self.inner.0
+    }
+
+    /// Blocking version of [`guild::GuildConfig::require_exists`]
+    pub fn require_exists(self) -> Result<Self> {
+        let GuildConfig { inner, db } = self;
+        let inner = db.run(inner.require_exists(&db.pool))?;
+        Ok(GuildConfig { inner, db })
+    }
+
+    blocking! {
+        "guild::GuildConfig";
+        fn exists(&self) -> bool;
+        fn get_welcome_message(&self) -> Option<String>;
+        fn get_goodbye_message(&self) -> Option<String>;
+        fn set_welcome_message(&self, msg: Option<&str>) -> ();
+        fn set_goodbye_message(&self, msg: Option<&str>) -> ();
+        fn get_message_raw(&self, msg_ty: MessageType) -> Option<String>;
+        fn set_message_raw(&self, msg_ty: MessageType, msg: Option<&str>) -> ();
+        fn get_advertise(&self) -> bool;
+        fn set_advertise(&self, policy: bool) -> ();
+        fn get_admin_chan(&self) -> Option<ChannelId>;
+        fn set_admin_chan(&self, chan: Option<ChannelId>) -> ();
+        fn get_default_slap_expiry_days(&self) -> Option<u32>;
+        fn set_default_slap_expiry_days(&self, days: Option<u32>) -> ();
+        fn get_slap_threshold(&self) -> Option<u32>;
+        fn set_slap_threshold(&self, threshold: Option<u32>) -> ();
+        fn get_max_roles_per_privilege(&self) -> u32;
+        fn set_max_roles_per_privilege(&self, max: u32) -> ();
+        fn get_roles_with(&self, privilege: Privilege) -> Vec<RoleId>;
+        fn get_privilege_snapshot(&self) -> PrivilegeSnapshot;
+        fn get_full_privilege_audit(&self) -> String;
+        fn diff_privileges(&self, new_snapshot: &PrivilegeSnapshot) -> PrivilegeDiff;
+        fn roles_with_any_privilege(&self) -> Vec<RoleId>;
+        fn roles_matching_privilege_set(&self, required: PrivilegeSet) -> Vec<RoleId>;
+        fn privilege_count(&self, privilege: Privilege) -> usize;
+        fn set_all_privileges(&self, admin: &[RoleId], manager: &[RoleId], event: &[RoleId]) -> ();
+        fn import_from_snapshot(&self, snapshot: &GuildConfigData) -> ();
+        fn grant_privilege(&self, id: RoleId, privilege: Privilege) -> ();
+        fn deny_privilege(&self, id: RoleId, privilege: Privilege) -> ();
+        fn transfer_privilege(&self, from: RoleId, to: RoleId, privilege: Privilege) -> ();
+        fn copy_privileges_from(&self, source: RoleId, dest: RoleId) -> ();
+        fn have_privilege(&self, roles: &[RoleId], privilege: Privilege) -> bool;
+        fn any_has_privilege(&self, roles: &[RoleId], privilege: Privilege) -> bool;
+        fn has_privilege(&self, role: RoleId, privilege: Privilege) -> bool;
+        fn has_privileges(&self, role: RoleId, privileges: &[Privilege]) -> bool;
+        fn privilege_set_for(&self, role: RoleId) -> PrivilegeSet;
+        fn get_privileges_for(&self, role: RoleId) -> Vec<Privilege>;
+        fn highest_privilege_of(&self, roles: &[RoleId]) -> EffectivePrivileges;
+    }
+}
+
+/// Blocking version of [`slap::GuildSlapRecord`]
+///
+/// Obtained from [`BlockingPool::slaps`].
+#[derive(Debug)]
+pub struct GuildSlapRecord {
+    inner: slap::GuildSlapRecord,
+    db: BlockingPool,
+}
+
+// mirrors `slap::GuildSlapRecord`, which has no `is_empty` either
+#[allow(clippy::len_without_is_empty)]
+impl GuildSlapRecord {
+    /// ID of the guild
+    pub fn id(&self) -> GuildId {
+        self.inner.0
+    }
+
+    /// Blocking version of [`slap::GuildSlapRecord::slaps`], collected in a [`Vec`]
+    pub fn slaps(&self) -> Result<Vec<SlapReport>> {
+        self.db.run(self.inner.slaps(&self.db.pool).try_collect())
+    }
+
+    /// Blocking version of [`slap::GuildSlapRecord::offenders`], collected in a [`Vec`]
+    pub fn offenders(&self) -> Result<Vec<MemberSlapRecord>> {
+        self.db
+            .run(self.inner.offenders(&self.db.pool).try_collect())
+    }
+
+    /// Blocking version of [`slap::GuildSlapRecord::offenders_sorted_by_slap_count`], collected in a [`Vec`]
+    pub fn offenders_sorted_by_slap_count(&self) -> Result<Vec<(MemberSlapRecord, i64)>> {
+        self.db.run(
+            self.inner
+                .offenders_sorted_by_slap_count(&self.db.pool)
+                .try_collect(),
+        )
+    }
+
+    /// Blocking version of [`slap::GuildSlapRecord::active_members_with_slaps`], collected in a [`Vec`]
+    pub fn active_members_with_slaps(&self, since: DateTime<Utc>) -> Result<Vec<MemberSlapRecord>> {
+        self.db.run(
+            self.inner
+                .active_members_with_slaps(&self.db.pool, since)
+                .try_collect(),
+        )
+    }
+
+    blocking! {
+        "slap::GuildSlapRecord";
+        fn new_slap(
+            &self,
+            sentence: MessageId,
+            offender: UserId,
+            enforcer: Enforcer,
+            reason: Option<&str>
+        ) -> SlapReport;
+        fn len(&self) -> usize;
+        fn recent(&self, n: u32) -> Vec<SlapReport>;
+        fn latest(&self) -> Option<SlapReport>;
+        fn oldest(&self) -> Option<SlapReport>;
+        fn slaps_on_date(&self, date: NaiveDate) -> Vec<SlapReport>;
+        fn offender_count(&self) -> usize;
+        fn active_offender_count(&self) -> usize;
+        fn rolling_7day_count(&self) -> usize;
+        fn rolling_30day_count(&self) -> usize;
+        fn slap_rate(&self, days: u32) -> f64;
+        fn monthly_summary(&self, year: i32, month: u32) -> MonthlySlapSummary;
+        fn activity(
+            &self,
+            from: DateTime<Utc>,
+            to: DateTime<Utc>,
+            bucket: ActivityBucket
+        ) -> Vec<(DateTime<Utc>, u64)>;
+        fn slap_heatmap_by_weekday(&self) -> [usize; 7];
+        fn prune_older_than(&self, cutoff: DateTime<Utc>) -> u64;
+        fn expunge_expired_slaps(&self) -> usize;
+        #[cfg(feature = "net")]
+        fn export_as_json(&self) -> String;
+        #[cfg(feature = "net")]
+        fn import_from_json(&self, json: &str) -> usize;
+        #[cfg(feature = "csv")]
+        fn export_csv(&self) -> String;
+    }
+
+    /// Blocking version of [`slap::GuildSlapRecord::write_csv_to`]
+    #[cfg(feature = "csv")]
+    pub fn write_csv_to<W: tokio::io::AsyncWrite + Unpin>(&self, writer: W) -> Result<()> {
+        self.db.run(self.inner.write_csv_to(&self.db.pool, writer))
+    }
+}
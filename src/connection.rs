@@ -0,0 +1,595 @@
+//! Settings of the [connection pool]
+//!
+//! [`ConnectionConfig`] holds the settings used to create the [connection pool]. Its default values are the
+//! ones [`crate::establish_connection`] has always used so only the settings that matter to you need to be
+//! changed, either with a [`ConnectionConfigBuilder`] or through environment variables (see
+//! [`ConnectionConfig::from_env`]).
+//!
+//! Where to connect is described by a [`ConnectionInfo`], either a URL or discrete settings such as the ones
+//! orchestrators inject as separate secrets (see [`ConnectionInfo::from_env`]).
+//!
+//! TLS is configured through [`ConnectionConfig::ssl_mode`] and [`ConnectionConfig::ssl_root_cert`]. Client
+//! certificates aren't supported by the version of sqlx in use.
+//!
+//! Setups with a read replica can use [`DbPools`] to send read-only queries to the replica.
+//!
+//! [connection pool]: sqlx::postgres::PgPool
+
+use crate::AdapterError;
+pub use log::LevelFilter;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions, PgSslMode};
+use sqlx::{ConnectOptions, PgPool};
+use std::env;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// Environment variable overriding [`ConnectionConfig::max_connections`]
+pub const MAX_CONNECTIONS_VAR: &str = "BOTANIST_DB_MAX_CONNECTIONS";
+/// Environment variable overriding [`ConnectionConfig::min_connections`]
+pub const MIN_CONNECTIONS_VAR: &str = "BOTANIST_DB_MIN_CONNECTIONS";
+/// Environment variable overriding [`ConnectionConfig::acquire_timeout`], in seconds
+pub const ACQUIRE_TIMEOUT_VAR: &str = "BOTANIST_DB_ACQUIRE_TIMEOUT";
+/// Environment variable overriding [`ConnectionConfig::idle_timeout`], in seconds
+///
+/// `none` disables the timeout.
+pub const IDLE_TIMEOUT_VAR: &str = "BOTANIST_DB_IDLE_TIMEOUT";
+/// Environment variable overriding [`ConnectionConfig::statement_log_level`]
+///
+/// Accepts the names of [`LevelFilter`]'s variants, case insensitive.
+pub const STATEMENT_LOG_LEVEL_VAR: &str = "BOTANIST_DB_STATEMENT_LOG_LEVEL";
+/// Environment variable overriding [`ConnectionConfig::ssl_mode`]
+///
+/// Accepts the names libpq uses for `sslmode`, such as `require` or `verify-full`, case insensitive.
+pub const SSL_MODE_VAR: &str = "BOTANIST_DB_SSLMODE";
+/// Environment variable overriding [`ConnectionConfig::ssl_root_cert`] with the path of a PEM file
+pub const SSL_ROOT_CERT_VAR: &str = "BOTANIST_DB_SSLROOTCERT";
+
+/// Environment variable holding the host of the database, see [`ConnectionInfo::from_env`]
+pub const HOST_VAR: &str = "PGHOST";
+/// Environment variable holding the port of the database, see [`ConnectionInfo::from_env`]
+pub const PORT_VAR: &str = "PGPORT";
+/// Environment variable holding the user to connect as, see [`ConnectionInfo::from_env`]
+pub const USER_VAR: &str = "PGUSER";
+/// Environment variable holding the password of the user, see [`ConnectionInfo::from_env`]
+pub const PASSWORD_VAR: &str = "PGPASSWORD";
+/// Environment variable holding the name of the database, see [`ConnectionInfo::from_env`]
+pub const DATABASE_VAR: &str = "PGDATABASE";
+
+/// Errors originating from invalid connection settings
+#[derive(Error, Debug)]
+pub enum ConnectionConfigError {
+    #[error("`{0}` must be greater than 0")]
+    Zero(&'static str),
+    #[error("`min_connections` ({min}) is greater than `max_connections` ({max})")]
+    MinOverMax { min: u32, max: u32 },
+    #[error("`{var}` has an invalid value: {value:?}")]
+    InvalidVar { var: &'static str, value: String },
+}
+
+type Result<Return> = std::result::Result<Return, AdapterError>;
+
+/// Where to connect to
+///
+/// Discrete settings are handed to sqlx as they are rather than through a URL, so credentials may hold
+/// characters reserved in URLs such as `@`, `/` or `:` without being percent-encoded. The [`Debug`] output
+/// leaves the password and the URL out since the later may hold credentials too.
+#[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConnectionInfo {
+    /// A `postgres://` URL, percent-encoding is up to whoever wrote it
+    Url(String),
+    /// Discrete settings, the ones left to [`None`] keep sqlx's defaults
+    Parts {
+        host: String,
+        port: Option<u16>,
+        user: Option<String>,
+        password: Option<String>,
+        database: Option<String>,
+    },
+}
+
+impl ConnectionInfo {
+    /// Reads where to connect from the environment
+    ///
+    /// If [`HOST_VAR`] is set the discrete variables are used: [`HOST_VAR`], [`PORT_VAR`], [`USER_VAR`],
+    /// [`PASSWORD_VAR`] and [`DATABASE_VAR`], only the host being required. Otherwise `DATABASE_URL` is used.
+    /// Unlike [`crate::try_establish_connection`] this doesn't load `.env`.
+    ///
+    /// # Errors
+    /// Returns [`AdapterError::EnvError`] if neither [`HOST_VAR`] nor `DATABASE_URL` is set and
+    /// [`ConnectionConfigError::InvalidVar`] if [`PORT_VAR`] isn't a port number. Variables that aren't valid
+    /// unicode are considered unset.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    pub(crate) fn from_vars<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        if let Some(host) = lookup(HOST_VAR) {
+            return Ok(ConnectionInfo::Parts {
+                host,
+                port: parse_var(&lookup, PORT_VAR, u16::from_str)?,
+                user: lookup(USER_VAR),
+                password: lookup(PASSWORD_VAR),
+                database: lookup(DATABASE_VAR),
+            });
+        }
+        match lookup("DATABASE_URL") {
+            Some(url) => Ok(ConnectionInfo::Url(url)),
+            None => Err(AdapterError::EnvError {
+                var: "DATABASE_URL",
+                source: env::VarError::NotPresent,
+            }),
+        }
+    }
+
+    /// Options sqlx connects with
+    ///
+    /// # Error
+    /// Returns [`AdapterError::SqlxError`] if the URL is malformed.
+    pub fn to_options(&self) -> Result<PgConnectOptions> {
+        match self {
+            ConnectionInfo::Url(url) => Ok(PgConnectOptions::from_str(url)?),
+            ConnectionInfo::Parts {
+                host,
+                port,
+                user,
+                password,
+                database,
+            } => {
+                let mut options = PgConnectOptions::new().host(host);
+                if let Some(port) = port {
+                    options = options.port(*port);
+                }
+                if let Some(user) = user {
+                    options = options.username(user);
+                }
+                if let Some(password) = password {
+                    options = options.password(password);
+                }
+                if let Some(database) = database {
+                    options = options.database(database);
+                }
+                Ok(options)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ConnectionInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionInfo::Url(_) => f.debug_tuple("Url").field(&"<redacted>").finish(),
+            ConnectionInfo::Parts {
+                host,
+                port,
+                user,
+                password,
+                database,
+            } => f
+                .debug_struct("Parts")
+                .field("host", host)
+                .field("port", port)
+                .field("user", user)
+                .field("password", &password.as_ref().map(|_| "<redacted>"))
+                .field("database", database)
+                .finish(),
+        }
+    }
+}
+
+/// Whether and how TLS is used to connect, mirrors libpq's `sslmode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SslMode {
+    /// Never use TLS
+    Disable,
+    /// Try without TLS first, then with it
+    Allow,
+    /// Try with TLS first, then without it
+    Prefer,
+    /// Require TLS without checking the certificate of the server
+    Require,
+    /// Require TLS and check that the certificate of the server is signed by a trusted authority
+    VerifyCa,
+    /// Same as [`Self::VerifyCa`] and check that the host name matches the certificate
+    VerifyFull,
+}
+
+impl SslMode {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "disable" => SslMode::Disable,
+            "allow" => SslMode::Allow,
+            "prefer" => SslMode::Prefer,
+            "require" => SslMode::Require,
+            "verify-ca" => SslMode::VerifyCa,
+            "verify-full" => SslMode::VerifyFull,
+            _ => return None,
+        })
+    }
+}
+
+impl From<SslMode> for PgSslMode {
+    fn from(mode: SslMode) -> Self {
+        match mode {
+            SslMode::Disable => PgSslMode::Disable,
+            SslMode::Allow => PgSslMode::Allow,
+            SslMode::Prefer => PgSslMode::Prefer,
+            SslMode::Require => PgSslMode::Require,
+            SslMode::VerifyCa => PgSslMode::VerifyCa,
+            SslMode::VerifyFull => PgSslMode::VerifyFull,
+        }
+    }
+}
+
+/// Certificate of the authority the certificate of the server must be signed by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RootCert {
+    /// Path of a PEM file, read when connecting
+    Path(PathBuf),
+    /// Content of a PEM file
+    Pem(Vec<u8>),
+}
+
+/// Settings of the [connection pool]
+///
+/// Use [`ConnectionConfigBuilder`] or [`Self::from_env`] to create one.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionConfig {
+    /// Maximum number of connections of the pool. Defaults to 10.
+    pub max_connections: u32,
+    /// Number of connections the pool tries to keep open at all times. Defaults to 0.
+    pub min_connections: u32,
+    /// How long to wait for a connection before giving up. Defaults to 30 seconds.
+    pub acquire_timeout: Duration,
+    /// How long a connection can stay idle before being closed. Defaults to 10 minutes.
+    pub idle_timeout: Option<Duration>,
+    /// Level at which executed statements are logged. Defaults to [`LevelFilter::Info`].
+    pub statement_log_level: LevelFilter,
+    /// TLS mode of the connections. Defaults to [`None`], keeping the one of the URL or [`SslMode::Prefer`].
+    pub ssl_mode: Option<SslMode>,
+    /// Certificate checked against when [`Self::ssl_mode`] is [`SslMode::VerifyCa`] or [`SslMode::VerifyFull`].
+    /// Defaults to [`None`], keeping the one of the URL or the usual trusted authorities.
+    pub ssl_root_cert: Option<RootCert>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        ConnectionConfig {
+            max_connections: 10,
+            min_connections: 0,
+            acquire_timeout: Duration::from_secs(30),
+            idle_timeout: Some(Duration::from_secs(10 * 60)),
+            statement_log_level: LevelFilter::Info,
+            ssl_mode: None,
+            ssl_root_cert: None,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Reads the settings from the environment
+    ///
+    /// Settings whose variable is not set keep their default value. The variables are [`MAX_CONNECTIONS_VAR`],
+    /// [`MIN_CONNECTIONS_VAR`], [`ACQUIRE_TIMEOUT_VAR`], [`IDLE_TIMEOUT_VAR`], [`STATEMENT_LOG_LEVEL_VAR`],
+    /// [`SSL_MODE_VAR`] and [`SSL_ROOT_CERT_VAR`].
+    /// Unlike [`crate::try_establish_connection`] this doesn't load `.env`.
+    ///
+    /// # Errors
+    /// Returns [`ConnectionConfigError::InvalidVar`] if a variable can't be parsed and the same errors as
+    /// [`ConnectionConfigBuilder::build`] if the resulting settings are invalid.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    pub(crate) fn from_vars<F: Fn(&str) -> Option<String>>(lookup: F) -> Result<Self> {
+        let mut builder = ConnectionConfigBuilder::new();
+        if let Some(max) = parse_var(&lookup, MAX_CONNECTIONS_VAR, u32::from_str)? {
+            builder.max_connections(max)?;
+        }
+        if let Some(min) = parse_var(&lookup, MIN_CONNECTIONS_VAR, u32::from_str)? {
+            builder.min_connections(min);
+        }
+        if let Some(secs) = parse_var(&lookup, ACQUIRE_TIMEOUT_VAR, u64::from_str)? {
+            builder.acquire_timeout(Duration::from_secs(secs))?;
+        }
+        if let Some(timeout) = parse_var(&lookup, IDLE_TIMEOUT_VAR, |value| {
+            if value.eq_ignore_ascii_case("none") {
+                Ok(None)
+            } else {
+                value.parse().map(|secs| Some(Duration::from_secs(secs)))
+            }
+        })? {
+            builder.idle_timeout(timeout);
+        }
+        if let Some(level) = parse_var(&lookup, STATEMENT_LOG_LEVEL_VAR, LevelFilter::from_str)? {
+            builder.statement_log_level(level);
+        }
+        if let Some(mode) = parse_var(&lookup, SSL_MODE_VAR, |value| {
+            SslMode::from_name(value).ok_or(())
+        })? {
+            builder.ssl_mode(mode);
+        }
+        if let Some(path) = lookup(SSL_ROOT_CERT_VAR) {
+            builder.ssl_root_cert(RootCert::Path(path.into()));
+        }
+        builder.build()
+    }
+
+    /// Creates a [connection pool] to the database at `url` with these settings
+    ///
+    /// [connection pool]: sqlx::postgres::PgPool
+    pub(crate) async fn connect(&self, url: &str) -> Result<PgPool> {
+        self.connect_to(&ConnectionInfo::Url(url.to_string())).await
+    }
+
+    /// Creates a [connection pool] to the database described by `info` with these settings
+    ///
+    /// [connection pool]: sqlx::postgres::PgPool
+    pub(crate) async fn connect_to(&self, info: &ConnectionInfo) -> Result<PgPool> {
+        let options = self.apply_to(info.to_options()?);
+        Ok(PgPoolOptions::new()
+            .max_connections(self.max_connections)
+            .min_connections(self.min_connections)
+            .connect_timeout(self.acquire_timeout)
+            .idle_timeout(self.idle_timeout)
+            .connect_with(options)
+            .await?)
+    }
+
+    // settings of the connections themselves, as opposed to the ones of the pool
+    pub(crate) fn apply_to(&self, mut options: PgConnectOptions) -> PgConnectOptions {
+        options.log_statements(self.statement_log_level);
+        if let Some(mode) = self.ssl_mode {
+            options = options.ssl_mode(mode.into());
+        }
+        match &self.ssl_root_cert {
+            Some(RootCert::Path(path)) => options.ssl_root_cert(path),
+            Some(RootCert::Pem(pem)) => options.ssl_root_cert_from_pem(pem.clone()),
+            None => options,
+        }
+    }
+
+    /// Same as [`Self::connect`] but transient failures are retried up to `retries` times
+    ///
+    /// The first retry waits for `backoff`, and each following one waits twice as long as the previous one.
+    /// Every delay is lengthened by a random amount of up to half of it so that several clients don't retry
+    /// in lockstep.
+    pub(crate) async fn connect_retrying(
+        &self,
+        info: &ConnectionInfo,
+        retries: u32,
+        backoff: Duration,
+    ) -> Result<PgPool> {
+        let mut attempt = 0;
+        let mut delay = backoff;
+        loop {
+            match self.connect_to(info).await {
+                Err(AdapterError::SqlxError(error))
+                    if attempt < retries && is_transient(&error) =>
+                {
+                    attempt += 1;
+                    let wait = with_jitter(delay);
+                    log::warn!(
+                        "could not connect to the database ({}), retrying in {:?} ({}/{})",
+                        error,
+                        wait,
+                        attempt,
+                        retries
+                    );
+                    tokio::time::sleep(wait).await;
+                    delay = delay.saturating_mul(2);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+// Errors that may go away on their own, such as the database still starting up. Anything else, like bad
+// credentials or a malformed URL, won't be fixed by retrying.
+fn is_transient(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut => true,
+        // connection exceptions, `cannot_connect_now` and `too_many_connections`
+        sqlx::Error::Database(error) => matches!(
+            error.code(),
+            Some(code) if code.starts_with("08") || code == "57P03" || code == "53300"
+        ),
+        _ => false,
+    }
+}
+
+// Adds up to half of `delay`. The sub-second part of the clock is random enough for this.
+fn with_jitter(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |now| now.subsec_nanos());
+    delay + delay / 1000 * (nanos % 500)
+}
+
+fn parse_var<F, T, E, P>(lookup: &F, var: &'static str, parse: P) -> Result<Option<T>>
+where
+    F: Fn(&str) -> Option<String>,
+    P: Fn(&str) -> std::result::Result<T, E>,
+{
+    match lookup(var) {
+        Some(value) => match parse(value.trim()) {
+            Ok(parsed) => Ok(Some(parsed)),
+            Err(_) => Err(ConnectionConfigError::InvalidVar { var, value }.into()),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Builder for [`ConnectionConfig`]
+///
+/// Starts from [`ConnectionConfig::default`].
+#[derive(Debug, Default)]
+pub struct ConnectionConfigBuilder {
+    config: ConnectionConfig,
+}
+
+impl ConnectionConfigBuilder {
+    pub fn new() -> ConnectionConfigBuilder {
+        ConnectionConfigBuilder::default()
+    }
+
+    /// # Error
+    /// Returns [`ConnectionConfigError::Zero`] if `max` is 0.
+    pub fn max_connections(&mut self, max: u32) -> Result<&mut Self> {
+        if max == 0 {
+            return Err(ConnectionConfigError::Zero("max_connections").into());
+        }
+        self.config.max_connections = max;
+        Ok(self)
+    }
+
+    pub fn min_connections(&mut self, min: u32) -> &mut Self {
+        self.config.min_connections = min;
+        self
+    }
+
+    /// # Error
+    /// Returns [`ConnectionConfigError::Zero`] if `timeout` is 0.
+    pub fn acquire_timeout(&mut self, timeout: Duration) -> Result<&mut Self> {
+        if timeout.is_zero() {
+            return Err(ConnectionConfigError::Zero("acquire_timeout").into());
+        }
+        self.config.acquire_timeout = timeout;
+        Ok(self)
+    }
+
+    /// [`None`] keeps idle connections open forever.
+    pub fn idle_timeout(&mut self, timeout: Option<Duration>) -> &mut Self {
+        self.config.idle_timeout = timeout;
+        self
+    }
+
+    pub fn statement_log_level(&mut self, level: LevelFilter) -> &mut Self {
+        self.config.statement_log_level = level;
+        self
+    }
+
+    pub fn ssl_mode(&mut self, mode: SslMode) -> &mut Self {
+        self.config.ssl_mode = Some(mode);
+        self
+    }
+
+    pub fn ssl_root_cert(&mut self, cert: RootCert) -> &mut Self {
+        self.config.ssl_root_cert = Some(cert);
+        self
+    }
+
+    /// # Error
+    /// Returns [`ConnectionConfigError::MinOverMax`] if `min_connections` is greater than `max_connections`.
+    pub fn build(&self) -> Result<ConnectionConfig> {
+        let config = self.config.clone();
+        if config.min_connections > config.max_connections {
+            return Err(ConnectionConfigError::MinOverMax {
+                min: config.min_connections,
+                max: config.max_connections,
+            }
+            .into());
+        }
+        Ok(config)
+    }
+}
+
+/// Environment variable holding the URL of the read replica, see [`DbPools::from_env`]
+pub const REPLICA_URL_VAR: &str = "DATABASE_REPLICA_URL";
+
+/// Connection pools to a primary database and an optional read replica
+///
+/// Methods of the crate take any executor as `conn` so routing is done by passing the right pool: [`Self::read`]
+/// for methods that only read (getters, streams, counts) and [`Self::write`] for everything else. Without a
+/// replica both return the primary.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), db_adapter::AdapterError> {
+/// use db_adapter::{connection::{ConnectionConfig, DbPools}, guild::GuildConfig};
+/// use db_adapter::id::GuildId;
+///
+/// let pools = DbPools::from_env(&ConnectionConfig::default()).await?;
+/// let config = GuildConfig::from(GuildId(5844));
+/// if !config.get_advertise(pools.read()).await? {
+///     config.set_advertise(pools.write(), true).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Keep in mind that replicas lag behind the primary. A value read right after being written may be stale,
+/// read it from [`Self::write`] when that matters.
+#[derive(Debug, Clone)]
+pub struct DbPools {
+    /// Pool to the primary database. Every write goes through it.
+    pub primary: PgPool,
+    /// Pool to the read replica, if any
+    pub replica: Option<PgPool>,
+}
+
+impl DbPools {
+    /// Connects to `DATABASE_URL` and, if it is set, to [`REPLICA_URL_VAR`] with the settings of `config`
+    ///
+    /// `.env` is loaded before looking the variables up.
+    ///
+    /// # Errors
+    /// Returns [`AdapterError::EnvError`] if `DATABASE_URL` is not set or if either variable isn't valid unicode
+    /// and [`AdapterError::SqlxError`] if a connection could not be established.
+    pub async fn from_env(config: &ConnectionConfig) -> Result<Self> {
+        dotenv::dotenv().ok();
+        let primary = crate::env_var("DATABASE_URL")?;
+        let replica = match env::var(REPLICA_URL_VAR) {
+            Ok(url) => Some(url),
+            Err(env::VarError::NotPresent) => None,
+            Err(source) => {
+                return Err(AdapterError::EnvError {
+                    var: REPLICA_URL_VAR,
+                    source,
+                })
+            }
+        };
+        Self::connect(config, &primary, replica.as_deref()).await
+    }
+
+    /// Connects to the databases at `primary` and `replica` with the settings of `config`
+    ///
+    /// Unlike [`Self::from_env`] this neither reads the environment nor loads `.env`.
+    pub async fn connect(
+        config: &ConnectionConfig,
+        primary: &str,
+        replica: Option<&str>,
+    ) -> Result<Self> {
+        let primary = config.connect(primary).await?;
+        let replica = match replica {
+            Some(url) => Some(config.connect(url).await?),
+            None => None,
+        };
+        Ok(DbPools { primary, replica })
+    }
+
+    /// Pool to use for read-only queries: the replica if there is one, the primary otherwise
+    pub fn read(&self) -> &PgPool {
+        self.replica.as_ref().unwrap_or(&self.primary)
+    }
+
+    /// Pool to use for queries that write to the database, always the primary
+    pub fn write(&self) -> &PgPool {
+        &self.primary
+    }
+}
+
+impl From<PgPool> for DbPools {
+    /// A setup without any replica
+    fn from(primary: PgPool) -> Self {
+        DbPools {
+            primary,
+            replica: None,
+        }
+    }
+}
@@ -8,19 +8,52 @@
 //! but also conviniences such as welcome messages, administration
 //! channels or advertisement policy.
 //!
-//! [Guild]: serenity::model::guild::Guild
+//! [Guild]: https://docs.rs/serenity/0.10/serenity/model/guild/struct.Guild.html
 
-use crate::{as_pg_array, from_i64, stringify_option, to_i64, AdapterError};
-use async_recursion::async_recursion;
-use serenity::model::id::{ChannelId, GuildId, RoleId};
-use sqlx::{query, Executor, Postgres, Row};
+use crate::id::{ChannelId, GuildId, RoleId};
+use crate::{not_found_as, query_failed, try_from_i64, try_to_i64, AdapterError, TimedQuery};
+use futures::TryStreamExt;
+#[cfg(feature = "net")]
+use serde::{Deserialize, Serialize};
+use sqlx::{Acquire, Executor, PgConnection, Postgres, Row};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::future::Future;
+use std::str::FromStr;
 use thiserror::Error;
+use tokio_stream::{Stream, StreamExt};
 
-enum MessageType {
+/// Messages of a guild configuration, see [`GuildConfig::get_message_raw`] and [`GuildConfig::set_message_raw`]
+///
+/// [`AsRef<str>`] gives the name of the column holding the message.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    /// `welcome_message`
     Welcome,
+    /// `goodbye_message`
     Goodbye,
 }
 
+impl MessageType {
+    fn getter_operation(self) -> &'static str {
+        match self {
+            MessageType::Welcome => "guild.get_welcome_message",
+            MessageType::Goodbye => "guild.get_goodbye_message",
+        }
+    }
+
+    fn setter_operation(self) -> &'static str {
+        match self {
+            MessageType::Welcome => "guild.set_welcome_message",
+            MessageType::Goodbye => "guild.set_goodbye_message",
+        }
+    }
+}
+
 impl AsRef<str> for MessageType {
     fn as_ref(&self) -> &str {
         match self {
@@ -32,17 +65,152 @@ impl AsRef<str> for MessageType {
 
 /// Errors originating from the `GuildConfig` wrapper
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum GuildConfigError {
     #[error("`{field:?}` can't be over 2000 chracters")]
     MessageTooLong { field: String },
     #[error("{role:?} doesn't have privilege {privilege:?}")]
     RoleNoPrivilege { role: RoleId, privilege: Privilege },
-    #[error("GuildId({0}) already has a configuration entry")]
-    AlreadyExists(GuildId),
+    #[error("GuildId({id}) already has a configuration entry")]
+    AlreadyExists { id: GuildId, existing: GuildConfig },
+    #[error("{0:?} is not a valid guild ID")]
+    InvalidId(GuildId),
+    #[error("{role:?} has privilege Admin but not Manager")]
+    PrivilegeInvariantViolation { role: RoleId },
+    #[error("`{0}` is not a guild configuration field")]
+    UnknownField(String),
+    #[error("{privilege:?} can't have more than {max} roles")]
+    TooManyRoles { privilege: Privilege, max: u32 },
+}
+
+/// Message text accepted by [`GuildConfig::set_welcome_message`] (`Kind` is [`WelcomeMessage`]) and
+/// [`GuildConfig::set_goodbye_message`] (`Kind` is [`GoodbyeMessage`])
+///
+/// Implemented by `&str` and `Option<&str>`, which are checked when converted, and by `&WelcomeMessage` and
+/// `&GoodbyeMessage`, which were checked when constructed.
+pub trait IntoMessageText<'m, Kind> {
+    /// The text to store, [`None`] disabling the message
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::MessageTooLong`] if the text is over 2000 characters.
+    fn into_message_text(self) -> Result<Option<&'m str>>;
+}
+
+// Defines a message newtype checked on construction along with its `IntoMessageText` implementations
+macro_rules! message_newtype {
+    ($(#[$attr:meta])* $name:ident, $msg_ty:expr) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+        pub struct $name(String);
+
+        impl $name {
+            /// # Error
+            /// Returns [`GuildConfigError::MessageTooLong`] if `msg` is over 2000 characters.
+            pub fn new<S: Into<String>>(msg: S) -> Result<Self> {
+                Ok($name(
+                    checked_message($msg_ty, Cow::Owned(msg.into()))?.into_owned(),
+                ))
+            }
+
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(msg: $name) -> Self {
+                msg.0
+            }
+        }
+
+        impl<'m> IntoMessageText<'m, $name> for &'m $name {
+            fn into_message_text(self) -> Result<Option<&'m str>> {
+                Ok(Some(&self.0))
+            }
+        }
+
+        impl<'m> IntoMessageText<'m, $name> for &'m str {
+            fn into_message_text(self) -> Result<Option<&'m str>> {
+                checked_message($msg_ty, Cow::Borrowed(self))?;
+                Ok(Some(self))
+            }
+        }
+
+        impl<'m> IntoMessageText<'m, $name> for Option<&'m str> {
+            fn into_message_text(self) -> Result<Option<&'m str>> {
+                match self {
+                    Some(msg) => IntoMessageText::<'m, $name>::into_message_text(msg),
+                    None => Ok(None),
+                }
+            }
+        }
+    };
 }
 
+message_newtype!(
+    /// Welcome message of at most 2000 characters, see [`GuildConfig::set_welcome_message`]
+    WelcomeMessage,
+    MessageType::Welcome
+);
+message_newtype!(
+    /// Goodbye message of at most 2000 characters, see [`GuildConfig::set_goodbye_message`]
+    GoodbyeMessage,
+    MessageType::Goodbye
+);
+
+/// Number of roles each privilege of a guild can have unless configured otherwise
+///
+/// See [`GuildConfigBuilder::max_roles_per_privilege`] and [`GuildConfig::set_max_roles_per_privilege`].
+pub const DEFAULT_MAX_ROLES_PER_PRIVILEGE: u32 = 250;
+
 type Result<Return> = std::result::Result<Return, AdapterError>;
 
+fn to_ids<I: Into<u64> + Copy>(ids: &[I]) -> Result<Vec<i64>> {
+    ids.iter().map(|id| try_to_i64(*id)).collect()
+}
+
+fn from_ids<I: From<u64>>(ints: Vec<i64>) -> Result<Vec<I>> {
+    ints.into_iter().map(try_from_i64).collect()
+}
+
+fn positive_i32(name: &'static str, value: u32) -> Result<i32> {
+    if value == 0 {
+        return Err(AdapterError::InvalidArgument {
+            name,
+            reason: "must be positive",
+        });
+    }
+    i32::try_from(value).map_err(|_| AdapterError::InvalidArgument {
+        name,
+        reason: "must be at most i32::MAX",
+    })
+}
+
+// The `guilds_priv_*_cap` constraints back the checks made before writing privileges up, for concurrent writes
+fn too_many_roles_as(max: u32) -> impl FnOnce(AdapterError) -> AdapterError {
+    move |error| {
+        let violated = match error.sqlx_error() {
+            Some(sqlx::Error::Database(error)) => error.constraint().and_then(|constraint| {
+                Privilege::ALL
+                    .iter()
+                    .copied()
+                    .find(|privilege| constraint == format!("guilds_{}_cap", privilege.as_ref()))
+            }),
+            _ => None,
+        };
+        match violated {
+            Some(privilege) => GuildConfigError::TooManyRoles { privilege, max }.into(),
+            None => error,
+        }
+    }
+}
+
 /// Wraps around a `guilds` row
 ///
 /// [`GuildConfig`] provides an API covering every common use-case. When it doesn't piecing methods
@@ -55,7 +223,8 @@ type Result<Return> = std::result::Result<Return, AdapterError>;
 /// The major example of this is the `conn` parameter which generally accepts anything that
 /// implements: [`sqlx::Executor`]. This means both [`sqlx::PgPool`] and
 /// [`sqlx::PgConnection`] can be used. However some methods need to issue multiple queries.
-/// As such it requires a `conn` that implements [`Copy`]. In those cases simply pass a `&PgPool`
+/// Those take anything that implements [`sqlx::Acquire`] instead: `&PgPool`, `&mut PgConnection` or
+/// `&mut Transaction` (see [`crate::with_transaction`]). They run all their queries on the same connection.
 ///
 /// # Errors
 ///
@@ -63,173 +232,403 @@ type Result<Return> = std::result::Result<Return, AdapterError>;
 /// detailing the error.
 ///
 /// All methods provided by [`Self`] return a `Result` which's [`Err`] variant is
-/// [`AdapterError`]. One of the later's variant wraps around [`sqlx::Error`] which is returned by
-/// every [`sqlx`] method that interacts with the database. These are all about database errors, which for the
-/// user of the library, should only be caused by incorrect setup (see [`crate`]).
+/// [`AdapterError`]. One of the later's variant, [`AdapterError::Query`], wraps around [`sqlx::Error`] which is
+/// returned by every [`sqlx`] method that interacts with the database along with the name of the method. These are all about database errors, which for the
+/// user of the library, should only be caused by incorrect setup (see [`crate`]). Any method may also return
+/// [`AdapterError::IdOutOfRange`] if an ID doesn't fit in the database.
+///
+/// Methods reading the configuration return [`AdapterError::GuildNotFound`] if the guild has no entry.
 #[derive(Debug)]
 pub struct GuildConfig(pub GuildId);
 
 impl From<GuildId> for GuildConfig {
+    /// The ID isn't checked, see [`GuildConfig::try_new`] to reject a guild ID of 0
     fn from(src: GuildId) -> GuildConfig {
         GuildConfig(src)
     }
 }
 
+// Methods taking `A: Acquire` return an explicit `impl Future + Send` and leave the work to an `_inner` async fn.
+// When `A` is `&mut Transaction` rustc can't prove on its own that the future of an async fn holding
+// `A::Connection` is `Send`, so it couldn't be boxed in `crate::with_transaction`.
 impl GuildConfig {
+    /// Wraps around the guild `id`, checking it first
+    ///
+    /// A `TryFrom<GuildId>` implementation can't coexist with `From<GuildId>`, hence this constructor.
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::InvalidId`] if `id` is 0 since discord never issues such ID.
+    pub fn try_new(id: GuildId) -> Result<Self> {
+        if id.0 == 0 {
+            return Err(GuildConfigError::InvalidId(id).into());
+        }
+        Ok(GuildConfig(id))
+    }
+
     /// Adds a new entry to the `guilds` table.
     ///
     /// # Errors
     ///
-    /// Errors with [`GuildConfigError::AlreadyExists`] if a row with the same `id` already exists in the DB
-    pub async fn new<'a, 'b, PgExec: Executor<'a, Database = Postgres> + Copy>(
-        conn: PgExec,
+    /// Errors with [`GuildConfigError::AlreadyExists`] if a row with the same `id` already exists in the DB. The
+    /// error holds the existing configuration so it can be used right away. The builder is checked with
+    /// [`Self::validate_builder`] first.
+    pub fn new<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        conn: A,
+        builder: GuildConfigBuilder<'a>,
+    ) -> impl Future<Output = Result<Self>> + Send + 'a {
+        Self::new_inner(conn, builder)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn, builder), fields(guild = %builder.id)))]
+    async fn new_inner<'a, 'b, A: Acquire<'a, Database = Postgres>>(
+        conn: A,
         builder: GuildConfigBuilder<'b>,
     ) -> Result<Self> {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.new", Some(builder.id)))?;
+        Self::validate_builder(&mut *conn, &builder).await?;
         let guild_config = GuildConfig::from(builder.id);
-        if guild_config.exists(conn).await? {
-            return Err(GuildConfigError::AlreadyExists(builder.id).into());
-        };
 
-        let poll_chans = builder
-            .poll_chans
-            .map(|vec| vec.iter().map(|int| to_i64(int.0)).collect::<Vec<i64>>());
-        query!(
-            "INSERT INTO guilds(id, welcome_message, goodbye_message, advertise, admin_chan, poll_chans, priv_admin, priv_manager, priv_event) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
-            to_i64(builder.id),
-            builder.welcome_message,
-            builder.goodbye_message,
+        let poll_chans = builder.poll_chans.as_deref().map(to_ids).transpose()?;
+        let admin_chan = builder.admin_chan.map(try_to_i64).transpose()?;
+        let slap_threshold = builder
+            .slap_threshold
+            .map(|threshold| positive_i32("slap_threshold", threshold))
+            .transpose()?;
+        traced!(query!(
+            "INSERT INTO guilds(id, welcome_message, goodbye_message, advertise, admin_chan, poll_chans, priv_admin, priv_manager, priv_event, max_roles_per_privilege, slap_threshold) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            try_to_i64(builder.id)?,
+            @redact builder.welcome_message.as_deref(),
+            @redact builder.goodbye_message.as_deref(),
             builder.advertise,
-            builder.admin_chan.map(|int| to_i64(int.0)),
+            admin_chan,
             poll_chans.as_deref(),
-            &builder.priv_admin.iter().map(|role| to_i64(role.0)).collect::<Vec<i64>>(),
-            &builder.priv_manager.iter().map(|role| to_i64(role.0)).collect::<Vec<i64>>(),
-            &builder.priv_event.iter().map(|role| to_i64(role.0)).collect::<Vec<i64>>(),
-        )
-        .execute(conn)
-        .await?;
+            &to_ids(&builder.priv_admin)?,
+            &to_ids(&builder.priv_manager)?,
+            &to_ids(&builder.priv_event)?,
+            positive_i32("max_roles_per_privilege", builder.max_roles_per_privilege)?,
+            slap_threshold,
+        ))
+        .execute(&mut *conn)
+        .timed("guild.new", Some(builder.id))
+        .await
+        .map_err(too_many_roles_as(builder.max_roles_per_privilege))?;
 
         Ok(guild_config)
     }
 
+    /// Checks that [`Self::new`] would accept `builder`, without writing anything
+    ///
+    /// Handy to report a clean error to an API client before attempting the insert. A guild may still be
+    /// created in between so [`Self::new`] can fail with [`GuildConfigError::AlreadyExists`] nonetheless.
+    ///
+    /// # Errors
+    /// Returns [`GuildConfigError::AlreadyExists`] if the guild already has an entry, along with the errors of
+    /// [`GuildConfigBuilder::validate`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn, builder), fields(guild = %builder.id)))]
+    pub async fn validate_builder<'a, PgExec: Executor<'a, Database = Postgres>>(
+        conn: PgExec,
+        builder: &GuildConfigBuilder<'_>,
+    ) -> Result<()> {
+        builder.validate()?;
+        let existing = GuildConfig::from(builder.id);
+        if existing.exists(conn).await? {
+            return Err(GuildConfigError::AlreadyExists {
+                id: builder.id,
+                existing,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
     /// `true` if the guild exists in the database, `false` otherwise.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn exists<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<bool> {
-        let this_id: i64 = to_i64(self.0);
-        let ids = query!("SELECT id FROM guilds").fetch_all(conn).await?;
+        let this_id: i64 = try_to_i64(self.0)?;
+        let ids = traced!(query!("SELECT id FROM guilds"))
+            .fetch_all(conn)
+            .timed("guild.exists", Some(self.0))
+            .await?;
         Ok(ids.iter().any(|record| record.id == this_id))
     }
 
-    async fn get_message<'a, PgExec: Executor<'a, Database = Postgres>>(
+    /// Returns `self` if the guild exists in the database
+    ///
+    /// Handy to chain calls: `GuildConfig::from(id).require_exists(&pool).await?.get_admin_chan(&pool)`.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::GuildNotFound`] if the guild has no entry.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn require_exists<'a, PgExec: Executor<'a, Database = Postgres>>(
+        self,
+        conn: PgExec,
+    ) -> Result<Self> {
+        if self.exists(conn).await? {
+            Ok(self)
+        } else {
+            Err(AdapterError::GuildNotFound(self.0))
+        }
+    }
+
+    /// Whether each guild of `ids` exists in the database, fetched in a single query
+    ///
+    /// The map has an entry for every ID of `ids`. The database isn't queried if `ids` is empty.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn exists_batch<'a, PgExec: Executor<'a, Database = Postgres>>(
+        conn: PgExec,
+        ids: &[GuildId],
+    ) -> Result<HashMap<GuildId, bool>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let int_ids = to_ids(ids)?;
+        let found = traced!(query_scalar!(
+            "SELECT id FROM guilds WHERE id = ANY($1::bigint[])",
+            &int_ids
+        ))
+        .fetch_all(conn)
+        .timed("guild.exists_batch", None)
+        .await?;
+        Ok(ids
+            .iter()
+            .zip(&int_ids)
+            .map(|(id, int)| (*id, found.contains(int)))
+            .collect())
+    }
+
+    /// Configuration of each guild of `ids`, fetched in a single query
+    ///
+    /// Only guilds with a configuration entry are returned, sorted by ID and without duplicates. The database
+    /// isn't queried if `ids` is empty.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn get_many<'a, PgExec: Executor<'a, Database = Postgres>>(
+        conn: PgExec,
+        ids: &[GuildId],
+    ) -> Result<Vec<GuildConfigData>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        traced!(query!(
+            "SELECT id, welcome_message, goodbye_message, advertise, admin_chan, poll_chans, priv_admin, priv_manager, priv_event, default_slap_expiry_days, max_roles_per_privilege, slap_threshold FROM guilds WHERE id = ANY($1::bigint[]) ORDER BY id",
+            &to_ids(ids)?
+        ))
+        .fetch_all(conn)
+        .timed("guild.get_many", None)
+        .await?
+        .into_iter()
+        .map(|record| -> Result<GuildConfigData> {
+            Ok(GuildConfigData {
+                id: try_from_i64(record.id)?,
+                welcome_message: record.welcome_message,
+                goodbye_message: record.goodbye_message,
+                advertise: record.advertise,
+                admin_chan: record.admin_chan.map(try_from_i64).transpose()?,
+                poll_chans: record.poll_chans.map(from_ids).transpose()?,
+                privileges: PrivilegeSnapshot {
+                    admin: from_ids(record.priv_admin)?,
+                    manager: from_ids(record.priv_manager)?,
+                    event: from_ids(record.priv_event)?,
+                },
+                // the columns can only hold positive values
+                default_slap_expiry_days: record.default_slap_expiry_days.map(|days| days as u32),
+                max_roles_per_privilege: record.max_roles_per_privilege as u32,
+                slap_threshold: record.slap_threshold.map(|threshold| threshold as u32),
+            })
+        })
+        .collect()
+    }
+
+    /// A stream over the guilds missing any field of `criteria`, along with the fields they miss
+    ///
+    /// Guilds are sorted by ID and only the fields of `criteria` are reported. Nothing is returned if `criteria`
+    /// is empty.
+    pub fn incomplete<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        conn: PgExec,
+        criteria: CompletionCriteria,
+    ) -> impl Stream<Item = Result<(GuildConfig, Vec<MissingField>)>> + 'a {
+        traced!(query!(
+            r#"SELECT id, admin_chan IS NULL AS "admin_chan!", cardinality(priv_admin) + cardinality(priv_manager) + cardinality(priv_event) = 0 AS "any_privilege!", welcome_message IS NULL AS "welcome_message!", goodbye_message IS NULL AS "goodbye_message!" FROM guilds WHERE ($1 AND admin_chan IS NULL) OR ($2 AND cardinality(priv_admin) + cardinality(priv_manager) + cardinality(priv_event) = 0) OR ($3 AND welcome_message IS NULL) OR ($4 AND goodbye_message IS NULL) ORDER BY id"#,
+            criteria.contains(MissingField::AdminChan),
+            criteria.contains(MissingField::AnyPrivilege),
+            criteria.contains(MissingField::WelcomeMessage),
+            criteria.contains(MissingField::GoodbyeMessage)
+        ))
+        .fetch(conn)
+        .map_err(query_failed("guild.incomplete", None))
+        .map(move |res| {
+            res.and_then(|record| {
+                let missing = criteria
+                    .iter()
+                    .filter(|field| match field {
+                        MissingField::AdminChan => record.admin_chan,
+                        MissingField::AnyPrivilege => record.any_privilege,
+                        MissingField::WelcomeMessage => record.welcome_message,
+                        MissingField::GoodbyeMessage => record.goodbye_message,
+                    })
+                    .collect();
+                Ok((GuildConfig(try_from_i64(record.id)?), missing))
+            })
+        })
+    }
+
+    /// Message of type `msg_ty` currently in use, [`None`] if disabled
+    ///
+    /// [`Self::get_welcome_message`] and [`Self::get_goodbye_message`] are shorthands for it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_message_raw<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         msg_ty: MessageType,
     ) -> Result<Option<String>> {
-        Ok(sqlx::query(&format!(
-            "SELECT {} FROM guilds WHERE id={}",
-            msg_ty.as_ref(),
-            to_i64(self.0),
-        ))
-        .fetch_one(conn)
-        .await?
-        .try_get(msg_ty.as_ref())?)
+        let sql = format!("SELECT {} FROM guilds WHERE id=$1", msg_ty.as_ref());
+        trace_query!(sql, try_to_i64(self.0)?);
+        sqlx::query(&sql)
+            .bind(try_to_i64(self.0)?)
+            .fetch_one(conn)
+            .timed(msg_ty.getter_operation(), Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+            .try_get(msg_ty.as_ref())
+            .map_err(query_failed(msg_ty.getter_operation(), Some(self.0)))
     }
 
     /// `welcome_message` currently in use
     ///
     /// This is the message sent to new users when they join. Disabled if [`None`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn get_welcome_message<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<Option<String>> {
-        self.get_message(conn, MessageType::Welcome).await
+        self.get_message_raw(conn, MessageType::Welcome).await
     }
 
     /// `goodbye_message` currently in use
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn get_goodbye_message<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<Option<String>> {
-        self.get_message(conn, MessageType::Goodbye).await
+        self.get_message_raw(conn, MessageType::Goodbye).await
     }
 
-    async fn set_message<'a, PgExec: Executor<'a, Database = Postgres>>(
+    /// Change the message of type `msg_ty`, [`None`] disabling it
+    ///
+    /// Prefer [`Self::set_welcome_message`] and [`Self::set_goodbye_message`], which also accept messages
+    /// checked beforehand.
+    ///
+    /// # Error
+    /// If the message is over discord's length limit for a message (2000 characters) the query will not be made
+    /// and the method will return [`GuildConfigError::MessageTooLong`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, msg), fields(guild = %self.0)))]
+    pub async fn set_message_raw<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         msg_ty: MessageType,
         msg: Option<&str>,
     ) -> Result<()> {
-        if let Some(string) = msg {
-            if string.len() > 2000 {
-                return Err(GuildConfigError::MessageTooLong {
-                    field: msg_ty.as_ref().to_string(),
-                }
-                .into());
-            }
+        if let Some(msg) = msg {
+            checked_message(msg_ty, Cow::Borrowed(msg))?;
         }
-        sqlx::query(&format!(
-            "UPDATE guilds SET {}={} WHERE id={}",
-            msg_ty.as_ref(),
-            stringify_option(msg),
-            to_i64(self.0)
-        ))
-        .execute(conn)
-        .await?;
+        self.set_message(conn, msg_ty, msg).await
+    }
+
+    // `msg` must have been checked already, see `IntoMessageText`
+    async fn set_message<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        msg_ty: MessageType,
+        msg: Option<&str>,
+    ) -> Result<()> {
+        // the message is bound rather than formatted in so that it isn't logged with the query
+        let sql = format!("UPDATE guilds SET {}=$1 WHERE id=$2", msg_ty.as_ref());
+        trace_query!(
+            sql,
+            @redact msg,
+            try_to_i64(self.0)?
+        );
+        sqlx::query(&sql)
+            .bind(msg)
+            .bind(try_to_i64(self.0)?)
+            .execute(conn)
+            .timed(msg_ty.setter_operation(), Some(self.0))
+            .await?;
         Ok(())
     }
 
     /// Change `welcome_message`
     ///
+    /// `msg` is either a `&str`, an `Option<&str>` ([`None`] disabling the message) or a `&WelcomeMessage`, whose
+    /// length isn't checked again (see [`IntoMessageText`]).
+    ///
     /// # Error
     /// If the message is over discord's length limit for a message (2000 characters) the query will not be made
     /// and the method will return [`GuildConfigError::MessageTooLong`].
-    pub async fn set_welcome_message<'a, PgExec: Executor<'a, Database = Postgres>>(
-        &self,
-        conn: PgExec,
-        msg: Option<&str>,
-    ) -> Result<()> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, msg), fields(guild = %self.0)))]
+    pub async fn set_welcome_message<'a, 'm, PgExec, M>(&self, conn: PgExec, msg: M) -> Result<()>
+    where
+        PgExec: Executor<'a, Database = Postgres>,
+        M: IntoMessageText<'m, WelcomeMessage>,
+    {
+        let msg = msg.into_message_text()?;
         self.set_message(conn, MessageType::Welcome, msg).await
     }
 
     /// Change `goodbye_message`
     ///
+    /// `msg` is either a `&str`, an `Option<&str>` ([`None`] disabling the message) or a `&GoodbyeMessage`, whose
+    /// length isn't checked again (see [`IntoMessageText`]).
+    ///
     /// # Error
     /// If the message is over discord's length limit for a message (2000 characters) the query will not be made
     /// and the method will return [`GuildConfigError::MessageTooLong`].
-    pub async fn set_goodbye_message<'a, PgExec: Executor<'a, Database = Postgres>>(
-        &self,
-        conn: PgExec,
-        msg: Option<&str>,
-    ) -> Result<()> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, msg), fields(guild = %self.0)))]
+    pub async fn set_goodbye_message<'a, 'm, PgExec, M>(&self, conn: PgExec, msg: M) -> Result<()>
+    where
+        PgExec: Executor<'a, Database = Postgres>,
+        M: IntoMessageText<'m, GoodbyeMessage>,
+    {
+        let msg = msg.into_message_text()?;
         self.set_message(conn, MessageType::Goodbye, msg).await
     }
 
     /// `advertise`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn get_advertise<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<bool> {
-        Ok(
-            query!("SELECT advertise FROM guilds WHERE id=$1", to_i64(self.0))
-                .fetch_one(conn)
-                .await?
-                .advertise,
-        )
+        Ok(traced!(query!(
+            "SELECT advertise FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.get_advertise", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+        .advertise)
     }
 
     /// Change the advertisement policy
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn set_advertise<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         policy: bool,
     ) -> Result<()> {
-        query!(
+        traced!(query!(
             "UPDATE guilds SET advertise=$1 WHERE id=$2",
             policy,
-            to_i64(self.0)
-        )
+            try_to_i64(self.0)?
+        ))
         .execute(conn)
+        .timed("guild.set_advertise", Some(self.0))
         .await?;
         Ok(())
     }
@@ -238,185 +637,759 @@ impl GuildConfig {
     ///
     /// Events demanding the attention of guild admins are posted to the admin channel.
     /// This includes but is not limited to slap notices, upcoming updates, etc.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn get_admin_chan<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<Option<ChannelId>> {
-        Ok(
-            query!("SELECT admin_chan FROM guilds WHERE id=$1", to_i64(self.0))
-                .fetch_one(conn)
-                // maybe use fetch_optional? It works like this though :shrug:
-                .await?
-                .admin_chan
-                .map(|id| from_i64(id)),
-        )
+        traced!(query!(
+            "SELECT admin_chan FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.get_admin_chan", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+        .admin_chan
+        .map(try_from_i64)
+        .transpose()
     }
 
     /// Change the `admin_chan`
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn set_admin_chan<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         chan: Option<ChannelId>,
     ) -> Result<()> {
-        query!(
+        traced!(query!(
             "UPDATE guilds SET admin_chan=$1 WHERE id=$2",
-            match chan {
-                Some(chan) => Some(to_i64(chan.0)),
-                None => None,
-            },
-            to_i64(self.0)
-        )
+            chan.map(try_to_i64).transpose()?,
+            try_to_i64(self.0)?
+        ))
         .execute(conn)
+        .timed("guild.set_admin_chan", Some(self.0))
         .await?;
         Ok(())
     }
 
-    async fn get_raw_roles_with<'a, PgExec: Executor<'a, Database = Postgres>>(
+    /// `default_slap_expiry_days`
+    ///
+    /// When set, slaps issued in the guild expire after that many days unless they are given an expiry of their
+    /// own (see [`crate::slap::MemberSlapRecord::new_slap_expiring`]). `None` means slaps don't expire by default.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_default_slap_expiry_days<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        privilege: Privilege,
-    ) -> Result<Vec<i64>> {
-        Ok(sqlx::query(&format!(
-            "SELECT {} FROM guilds WHERE id={}",
-            privilege.as_ref(),
-            to_i64(self.0)
+    ) -> Result<Option<u32>> {
+        Ok(traced!(query!(
+            "SELECT default_slap_expiry_days FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
         ))
         .fetch_one(conn)
-        .await?
-        .try_get(privilege.as_ref())?)
+        .timed("guild.get_default_slap_expiry_days", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+        // the column can only hold positive values
+        .default_slap_expiry_days
+        .map(|days| days as u32))
     }
 
-    /// Roles with the specified privilege
-    pub async fn get_roles_with<'a, PgExec: Executor<'a, Database = Postgres>>(
+    /// Change the `default_slap_expiry_days`
+    ///
+    /// Slaps issued before the change keep their expiry.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `days` is 0 or over [`i32::MAX`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn set_default_slap_expiry_days<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        privilege: Privilege,
-    ) -> Result<Vec<RoleId>> {
-        Ok(self
-            .get_raw_roles_with(conn, privilege)
-            .await?
-            .iter()
-            .map(|int| from_i64(*int))
-            .collect())
+        days: Option<u32>,
+    ) -> Result<()> {
+        let days = days.map(|days| positive_i32("days", days)).transpose()?;
+        traced!(query!(
+            "UPDATE guilds SET default_slap_expiry_days=$1 WHERE id=$2",
+            days,
+            try_to_i64(self.0)?
+        ))
+        .execute(conn)
+        .timed("guild.set_default_slap_expiry_days", Some(self.0))
+        .await?;
+        Ok(())
     }
 
-    async fn update_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// `slap_threshold`
+    ///
+    /// Number of slaps after which a member should be escalated, for instance by alerting the admin channel.
+    /// `None` means escalation is disabled.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_slap_threshold<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        ids: &[i64],
-        privilege: Privilege,
+    ) -> Result<Option<u32>> {
+        Ok(traced!(query!(
+            "SELECT slap_threshold FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.get_slap_threshold", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+        // the column can only hold positive values
+        .slap_threshold
+        .map(|threshold| threshold as u32))
+    }
+
+    /// Change the `slap_threshold`, `None` disables escalation
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `threshold` is 0 or over [`i32::MAX`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn set_slap_threshold<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        threshold: Option<u32>,
     ) -> Result<()> {
-        sqlx::query(&format!(
-            "UPDATE guilds SET {}={} WHERE id={}",
-            privilege.as_ref(),
-            as_pg_array(ids),
-            to_i64(self.0)
+        let threshold = threshold
+            .map(|threshold| positive_i32("threshold", threshold))
+            .transpose()?;
+        traced!(query!(
+            "UPDATE guilds SET slap_threshold=$1 WHERE id=$2",
+            threshold,
+            try_to_i64(self.0)?
         ))
         .execute(conn)
+        .timed("guild.set_slap_threshold", Some(self.0))
         .await?;
         Ok(())
     }
 
-    //WARN: the Copy bound implies only immutable references can be passed
-    async fn grant_single_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// `max_roles_per_privilege`
+    ///
+    /// Number of roles each privilege can have, [`DEFAULT_MAX_ROLES_PER_PRIVILEGE`] unless configured otherwise.
+    /// Granting a privilege to more roles fails with [`GuildConfigError::TooManyRoles`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_max_roles_per_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        id: RoleId,
-        privilege: Privilege,
-    ) -> Result<()> {
-        let role_id = i64::from(id);
-        let mut roles = self.get_raw_roles_with(conn, privilege).await?;
-        roles.push(role_id);
-        self.update_privilege(conn, &roles, privilege).await
+    ) -> Result<u32> {
+        let max = traced!(query_scalar!(
+            "SELECT max_roles_per_privilege FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.get_max_roles_per_privilege", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        // the column can only hold positive values
+        Ok(max as u32)
     }
 
-    /// Gives a role a privilege
-    #[async_recursion] // because `async fn` doesn't support recursion
-    pub async fn grant_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// Change the `max_roles_per_privilege`
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `max` is 0 or over [`i32::MAX`] and
+    /// [`GuildConfigError::TooManyRoles`] if a privilege already has more roles than `max`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn set_max_roles_per_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        id: RoleId,
-        privilege: Privilege,
+        max: u32,
     ) -> Result<()> {
-        match privilege {
-            Privilege::Admin => {
-                self.grant_single_privilege(conn, id, Privilege::Manager)
-                    .await?;
-            }
-            Privilege::Manager | Privilege::Event => (),
-        };
-        self.grant_single_privilege(conn, id, privilege).await
+        traced!(query!(
+            "UPDATE guilds SET max_roles_per_privilege=$1 WHERE id=$2",
+            positive_i32("max", max)?,
+            try_to_i64(self.0)?
+        ))
+        .execute(conn)
+        .timed("guild.set_max_roles_per_privilege", Some(self.0))
+        .await
+        .map_err(too_many_roles_as(max))?;
+        Ok(())
     }
 
-    /// Strips a role from a privilege
-    // TODO: Consider using pg's `array_remove` utility instead, see: https://popsql.com/learn-sql/postgresql/how-to-modify-arrays-in-postgresql
-    #[async_recursion] // because `async fn` doesn't support recursion
-    pub async fn deny_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    async fn get_raw_roles_with<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        id: RoleId,
         privilege: Privilege,
-    ) -> Result<()> {
-        let to_remove = i64::from(id);
-        match privilege {
-            Privilege::Admin => self.deny_privilege(conn, id, Privilege::Manager).await?,
-            Privilege::Manager | Privilege::Event => (),
-        }
-        let mut roles = self.get_raw_roles_with(conn, privilege).await?;
-        let index = roles.iter().position(|int| *int == to_remove).ok_or(
-            GuildConfigError::RoleNoPrivilege {
-                role: id,
-                privilege,
-            },
-        )?;
-        roles.swap_remove(index);
-        self.update_privilege(conn, &roles, privilege).await?;
-
-        Ok(())
+    ) -> Result<Vec<i64>> {
+        let sql = format!("SELECT {} FROM guilds WHERE id=$1", privilege.as_ref());
+        trace_query!(sql, try_to_i64(self.0)?);
+        sqlx::query(&sql)
+            .bind(try_to_i64(self.0)?)
+            .fetch_one(conn)
+            .timed("guild.get_roles_with", Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?
+            .try_get(privilege.as_ref())
+            .map_err(query_failed("guild.get_roles_with", Some(self.0)))
     }
 
-    /// If all roles have a privilege
-    pub async fn have_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// Roles with the specified privilege
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_roles_with<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        roles: &[RoleId],
         privilege: Privilege,
-    ) -> Result<bool> {
-        let ids = roles.iter().map(|role| i64::from(*role));
+    ) -> Result<Vec<RoleId>> {
+        from_ids(self.get_raw_roles_with(conn, privilege).await?)
+    }
 
-        let db_roles = self.get_raw_roles_with(conn, privilege).await?;
-        for id in ids {
-            if !db_roles.contains(&id) {
-                return Ok(false);
-            }
-        }
+    /// Roles of every privilege, fetched in a single query
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_privilege_snapshot<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<PrivilegeSnapshot> {
+        let record = traced!(query!(
+            "SELECT priv_admin, priv_manager, priv_event FROM guilds WHERE id=$1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.get_privilege_snapshot", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        Ok(PrivilegeSnapshot {
+            admin: from_ids(record.priv_admin)?,
+            manager: from_ids(record.priv_manager)?,
+            event: from_ids(record.priv_event)?,
+        })
+    }
 
-        Ok(true)
+    /// Human-readable listing of the roles of every privilege, fetched in a single query
+    ///
+    /// Meant for audit commands, see the [`fmt::Display`] implementation of [`PrivilegeSnapshot`] for the format.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn get_full_privilege_audit<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<String> {
+        Ok(self.get_privilege_snapshot(conn).await?.to_string())
     }
 
-    /// If a role has a privilege
-    pub async fn has_privilege<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// What applying `new_snapshot` would change
+    ///
+    /// See [`PrivilegeDiff::between`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn diff_privileges<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        role: RoleId,
-        privilege: Privilege,
-    ) -> Result<bool> {
-        let id = i64::from(role);
-        Ok(self
-            .get_raw_roles_with(conn, privilege)
-            .await?
-            .contains(&id))
+        new_snapshot: &PrivilegeSnapshot,
+    ) -> Result<PrivilegeDiff> {
+        let current = self.get_privilege_snapshot(conn).await?;
+        Ok(PrivilegeDiff::between(&current, new_snapshot))
     }
-    // TODO: make a get_raw_privileges to make less queries when possible
 
-    /// Id a role has *all* specified privileges
-    pub async fn has_privileges<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// Roles with at least one privilege
+    ///
+    /// Every role appears only once, sorted by id.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn roles_with_any_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
-        role: RoleId,
-        privileges: &[Privilege],
+    ) -> Result<Vec<RoleId>> {
+        from_ids(
+            traced!(query_scalar!(
+                r#"SELECT ARRAY(SELECT DISTINCT unnest(priv_admin || priv_manager || priv_event) ORDER BY 1) as "roles!" FROM guilds WHERE id=$1"#,
+                try_to_i64(self.0)?
+            ))
+            .fetch_one(conn)
+            .timed("guild.roles_with_any_privilege", Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?,
+        )
+    }
+
+    /// Roles with every privilege of `required`
+    ///
+    /// Every role appears only once, sorted by id. With an empty `required` this is
+    /// [`Self::roles_with_any_privilege`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn roles_matching_privilege_set<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        required: PrivilegeSet,
+    ) -> Result<Vec<RoleId>> {
+        from_ids(
+            traced!(query_scalar!(
+                r#"SELECT ARRAY(SELECT DISTINCT role FROM unnest(priv_admin || priv_manager || priv_event) AS role WHERE (NOT $1 OR priv_admin @> ARRAY[role]) AND (NOT $2 OR priv_manager @> ARRAY[role]) AND (NOT $3 OR priv_event @> ARRAY[role]) ORDER BY 1) as "roles!" FROM guilds WHERE id=$4"#,
+                required.contains(Privilege::Admin),
+                required.contains(Privilege::Manager),
+                required.contains(Privilege::Event),
+                try_to_i64(self.0)?
+            ))
+            .fetch_one(conn)
+            .timed("guild.roles_matching_privilege_set", Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?,
+        )
+    }
+
+    /// Number of roles with the specified privilege
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn privilege_count<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        privilege: Privilege,
+    ) -> Result<usize> {
+        // `array_length` is NULL for empty arrays
+        let sql = format!(
+            "SELECT COALESCE(array_length({}, 1), 0) FROM guilds WHERE id=$1",
+            privilege.as_ref()
+        );
+        trace_query!(sql, try_to_i64(self.0)?);
+        let count: i32 = sqlx::query_scalar(&sql)
+            .bind(try_to_i64(self.0)?)
+            .fetch_one(conn)
+            .timed("guild.privilege_count", Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        Ok(count as usize)
+    }
+
+    async fn update_privilege<'a, PgExec, E>(
+        &self,
+        conn: PgExec,
+        ids: &[i64],
+        privilege: Privilege,
+        map_error: E,
+    ) -> Result<()>
+    where
+        PgExec: Executor<'a, Database = Postgres>,
+        E: FnOnce(AdapterError) -> AdapterError,
+    {
+        let sql = format!("UPDATE guilds SET {}=$1 WHERE id=$2", privilege.as_ref());
+        trace_query!(sql, ids, try_to_i64(self.0)?);
+        sqlx::query(&sql)
+            .bind(ids)
+            .bind(try_to_i64(self.0)?)
+            .execute(conn)
+            .timed("guild.update_privilege", Some(self.0))
+            .await
+            .map_err(map_error)?;
+        Ok(())
+    }
+
+    /// Replaces the roles of every privilege at once
+    ///
+    /// This is done in a single query so either all privileges are updated or none is.
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::PrivilegeInvariantViolation`] if a role of `admin` isn't in `manager` and
+    /// [`GuildConfigError::TooManyRoles`] if a privilege would have more roles than
+    /// [`Self::get_max_roles_per_privilege`], in which case nothing is changed.
+    pub fn set_all_privileges<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        admin: &'a [RoleId],
+        manager: &'a [RoleId],
+        event: &'a [RoleId],
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        self.set_all_privileges_inner(conn, admin, manager, event)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn set_all_privileges_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        admin: &[RoleId],
+        manager: &[RoleId],
+        event: &[RoleId],
+    ) -> Result<()> {
+        if let Some(role) = admin.iter().find(|role| !manager.contains(role)) {
+            return Err(GuildConfigError::PrivilegeInvariantViolation { role: *role }.into());
+        }
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.set_all_privileges", Some(self.0)))?;
+        let max = self.get_max_roles_per_privilege(&mut *conn).await?;
+        for (privilege, roles) in [
+            (Privilege::Admin, admin),
+            (Privilege::Manager, manager),
+            (Privilege::Event, event),
+        ] {
+            if roles.len() > max as usize {
+                return Err(GuildConfigError::TooManyRoles { privilege, max }.into());
+            }
+        }
+        traced!(query!(
+            "UPDATE guilds SET priv_admin=$1, priv_manager=$2, priv_event=$3 WHERE id=$4",
+            &to_ids(admin)?,
+            &to_ids(manager)?,
+            &to_ids(event)?,
+            try_to_i64(self.0)?
+        ))
+        .execute(&mut *conn)
+        .timed("guild.set_all_privileges", Some(self.0))
+        .await
+        .map_err(too_many_roles_as(max))?;
+        Ok(())
+    }
+
+    /// Overwrites the whole configuration of the guild with `snapshot` in a single query
+    ///
+    /// `snapshot.id` is ignored so a configuration fetched with [`Self::get_many`] can be restored to another
+    /// guild.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::GuildNotFound`] if the guild has no entry. Returns
+    /// [`GuildConfigError::MessageTooLong`], [`GuildConfigError::PrivilegeInvariantViolation`],
+    /// [`GuildConfigError::TooManyRoles`] or [`AdapterError::InvalidArgument`] if `snapshot` isn't valid, in which
+    /// case the query is not made.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, snapshot), fields(guild = %self.0)))]
+    pub async fn import_from_snapshot<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        snapshot: &GuildConfigData,
+    ) -> Result<()> {
+        for (msg_ty, msg) in [
+            (MessageType::Welcome, &snapshot.welcome_message),
+            (MessageType::Goodbye, &snapshot.goodbye_message),
+        ] {
+            if let Some(msg) = msg {
+                checked_message(msg_ty, Cow::Borrowed(msg))?;
+            }
+        }
+        let privileges = &snapshot.privileges;
+        if let Some(role) = privileges
+            .admin
+            .iter()
+            .find(|role| !privileges.manager.contains(role))
+        {
+            return Err(GuildConfigError::PrivilegeInvariantViolation { role: *role }.into());
+        }
+        let max = snapshot.max_roles_per_privilege;
+        let max_roles = positive_i32("max_roles_per_privilege", max)?;
+        for (privilege, roles) in [
+            (Privilege::Admin, &privileges.admin),
+            (Privilege::Manager, &privileges.manager),
+            (Privilege::Event, &privileges.event),
+        ] {
+            if roles.len() > max as usize {
+                return Err(GuildConfigError::TooManyRoles { privilege, max }.into());
+            }
+        }
+        let expiry_days = snapshot
+            .default_slap_expiry_days
+            .map(|days| positive_i32("default_slap_expiry_days", days))
+            .transpose()?;
+        let slap_threshold = snapshot
+            .slap_threshold
+            .map(|threshold| positive_i32("slap_threshold", threshold))
+            .transpose()?;
+        let poll_chans = snapshot.poll_chans.as_deref().map(to_ids).transpose()?;
+        let updated = traced!(query!(
+            "UPDATE guilds SET welcome_message=$1, goodbye_message=$2, advertise=$3, admin_chan=$4, poll_chans=$5, priv_admin=$6, priv_manager=$7, priv_event=$8, default_slap_expiry_days=$9, max_roles_per_privilege=$10, slap_threshold=$11 WHERE id=$12",
+            snapshot.welcome_message.as_deref(),
+            snapshot.goodbye_message.as_deref(),
+            snapshot.advertise,
+            snapshot.admin_chan.map(try_to_i64).transpose()?,
+            poll_chans.as_deref(),
+            &to_ids(&privileges.admin)?,
+            &to_ids(&privileges.manager)?,
+            &to_ids(&privileges.event)?,
+            expiry_days,
+            max_roles,
+            slap_threshold,
+            try_to_i64(self.0)?
+        ))
+        .execute(conn)
+        .timed("guild.import_from_snapshot", Some(self.0))
+        .await
+        .map_err(too_many_roles_as(max))?
+        .rows_affected();
+        if updated == 0 {
+            return Err(AdapterError::GuildNotFound(self.0));
+        }
+        Ok(())
+    }
+
+    /// Gives a role a privilege
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::TooManyRoles`] if the privilege, or one it implies, already has
+    /// [`Self::get_max_roles_per_privilege`] roles, in which case nothing is changed.
+    pub fn grant_privilege<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        id: RoleId,
+        privilege: Privilege,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        self.grant_privilege_inner(conn, id, privilege)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn grant_privilege_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        id: RoleId,
+        privilege: Privilege,
+    ) -> Result<()> {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.grant_privilege", Some(self.0)))?;
+        let role_id = try_to_i64(id)?;
+        let max = self.get_max_roles_per_privilege(&mut *conn).await?;
+        let mut updates = Vec::with_capacity(Privilege::ALL.len());
+        // the least powerful first so that the invariants hold at every step
+        for implied in privilege.implied().rev() {
+            let mut roles = self.get_raw_roles_with(&mut *conn, implied).await?;
+            if roles.len() >= max as usize {
+                return Err(GuildConfigError::TooManyRoles {
+                    privilege: implied,
+                    max,
+                }
+                .into());
+            }
+            roles.push(role_id);
+            updates.push((implied, roles));
+        }
+        for (implied, roles) in updates {
+            self.update_privilege(&mut *conn, &roles, implied, too_many_roles_as(max))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn deny_single_privilege(
+        &self,
+        conn: &mut PgConnection,
+        id: RoleId,
+        privilege: Privilege,
+    ) -> Result<()> {
+        let to_remove = try_to_i64(id)?;
+        let mut roles = self.get_raw_roles_with(&mut *conn, privilege).await?;
+        let index = roles.iter().position(|int| *int == to_remove).ok_or(
+            GuildConfigError::RoleNoPrivilege {
+                role: id,
+                privilege,
+            },
+        )?;
+        roles.swap_remove(index);
+        self.update_privilege(conn, &roles, privilege, |error| error)
+            .await
+    }
+
+    /// Strips a role from a privilege
+    // TODO: Consider using pg's `array_remove` utility instead, see: https://popsql.com/learn-sql/postgresql/how-to-modify-arrays-in-postgresql
+    pub fn deny_privilege<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        id: RoleId,
+        privilege: Privilege,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        self.deny_privilege_inner(conn, id, privilege)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn deny_privilege_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        id: RoleId,
+        privilege: Privilege,
+    ) -> Result<()> {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.deny_privilege", Some(self.0)))?;
+        for implied in privilege.implied().rev() {
+            self.deny_single_privilege(&mut conn, id, implied).await?;
+        }
+        Ok(())
+    }
+
+    /// Moves a privilege from one role to another
+    ///
+    /// This is done in a single query so either both roles are updated or none is. As with
+    /// [`Self::grant_privilege`] and [`Self::deny_privilege`], moving [`Privilege::Admin`] also moves
+    /// [`Privilege::Manager`]. If `to` already has the privilege it is only removed from `from`.
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::RoleNoPrivilege`] if `from` doesn't have the privilege, in which case nothing
+    /// is changed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn transfer_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        from: RoleId,
+        to: RoleId,
+        privilege: Privilege,
+    ) -> Result<()> {
+        let transfer = |column: &str| {
+            format!(
+                "{col}=CASE WHEN $2=ANY(array_remove({col}, $1)) THEN array_remove({col}, $1) ELSE array_append(array_remove({col}, $1), $2) END",
+                col = column
+            )
+        };
+        let columns = privilege
+            .implied()
+            .map(|implied| transfer(implied.as_ref()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "UPDATE guilds SET {} WHERE id=$3 AND $1=ANY({})",
+            columns,
+            privilege.as_ref()
+        );
+        let (from_id, to_id) = (try_to_i64(from)?, try_to_i64(to)?);
+        trace_query!(sql, from_id, to_id, try_to_i64(self.0)?);
+        let updated = sqlx::query(&sql)
+            .bind(from_id)
+            .bind(to_id)
+            .bind(try_to_i64(self.0)?)
+            .execute(conn)
+            .timed("guild.transfer_privilege", Some(self.0))
+            .await?
+            .rows_affected();
+        if updated == 0 {
+            return Err(GuildConfigError::RoleNoPrivilege {
+                role: from,
+                privilege,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Grants `dest` every privilege `source` has
+    ///
+    /// Privileges `dest` already has are kept and never duplicated. This is done in a single query so
+    /// either all privileges are copied or none is.
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::TooManyRoles`] if a privilege `dest` would get already has
+    /// [`Self::get_max_roles_per_privilege`] roles, in which case nothing is changed.
+    pub fn copy_privileges_from<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        source: RoleId,
+        dest: RoleId,
+    ) -> impl Future<Output = Result<()>> + Send + 'a {
+        self.copy_privileges_from_inner(conn, source, dest)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn copy_privileges_from_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        source: RoleId,
+        dest: RoleId,
+    ) -> Result<()> {
+        let copy = |privilege: Privilege| {
+            format!(
+                "{col}=CASE WHEN $1=ANY({col}) AND NOT $2=ANY({col}) THEN array_append({col}, $2) ELSE {col} END",
+                col = privilege.as_ref()
+            )
+        };
+        let sql = format!(
+            "UPDATE guilds SET {}, {}, {} WHERE id=$3",
+            copy(Privilege::Admin),
+            copy(Privilege::Manager),
+            copy(Privilege::Event)
+        );
+        let (source_id, dest_id) = (try_to_i64(source)?, try_to_i64(dest)?);
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.copy_privileges_from", Some(self.0)))?;
+        let max = self.get_max_roles_per_privilege(&mut *conn).await?;
+        trace_query!(sql, source_id, dest_id, try_to_i64(self.0)?);
+        sqlx::query(&sql)
+            .bind(source_id)
+            .bind(dest_id)
+            .bind(try_to_i64(self.0)?)
+            .execute(&mut *conn)
+            .timed("guild.copy_privileges_from", Some(self.0))
+            .await
+            .map_err(too_many_roles_as(max))?;
+        Ok(())
+    }
+
+    /// If all roles have a privilege
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn have_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        roles: &[RoleId],
+        privilege: Privilege,
+    ) -> Result<bool> {
+        let ids = to_ids(roles)?;
+
+        let db_roles = self.get_raw_roles_with(conn, privilege).await?;
+        for id in ids {
+            if !db_roles.contains(&id) {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// If at least one of `roles` has a privilege, such as any of a member's roles
+    ///
+    /// Makes a single query whatever the number of roles, none if `roles` is empty in which case it returns
+    /// `false`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn any_has_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        roles: &[RoleId],
+        privilege: Privilege,
+    ) -> Result<bool> {
+        if roles.is_empty() {
+            return Ok(false);
+        }
+        let ids = to_ids(roles)?;
+        let sql = format!(
+            "SELECT {} && $1::bigint[] FROM guilds WHERE id=$2",
+            privilege.as_ref()
+        );
+        trace_query!(sql, ids, try_to_i64(self.0)?);
+        sqlx::query_scalar::<_, bool>(&sql)
+            .bind(&ids)
+            .bind(try_to_i64(self.0)?)
+            .fetch_one(conn)
+            .timed("guild.any_has_privilege", Some(self.0))
+            .await
+            .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))
+    }
+
+    /// If a role has a privilege
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn has_privilege<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        role: RoleId,
+        privilege: Privilege,
     ) -> Result<bool> {
-        let privs = self.get_privileges_for(conn, role).await?;
+        let id = try_to_i64(role)?;
+        Ok(self
+            .get_raw_roles_with(conn, privilege)
+            .await?
+            .contains(&id))
+    }
+
+    /// Id a role has *all* specified privileges
+    pub fn has_privileges<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        role: RoleId,
+        privileges: &'a [Privilege],
+    ) -> impl Future<Output = Result<bool>> + Send + 'a {
+        self.has_privileges_inner(conn, role, privileges)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn has_privileges_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        role: RoleId,
+        privileges: &[Privilege],
+    ) -> Result<bool> {
+        let privs = self.get_privileges_for_inner(conn, role).await?;
         for privilege in privileges {
             if !privs.contains(privilege) {
                 return Ok(false);
@@ -425,32 +1398,128 @@ impl GuildConfig {
         Ok(true)
     }
 
-    /// All privileges granted to a role
-    pub async fn get_privileges_for<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    /// Privileges granted to a role, fetched in a single query
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn privilege_set_for<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         role: RoleId,
-    ) -> Result<Vec<Privilege>> {
-        let mut privs = Vec::with_capacity(3);
-        if self.has_privilege(conn, role, Privilege::Admin).await? {
-            privs.push(Privilege::Admin);
-            privs.push(Privilege::Manager);
-        } else if self.has_privilege(conn, role, Privilege::Manager).await? {
-            privs.push(Privilege::Manager);
+    ) -> Result<PrivilegeSet> {
+        let record = traced!(query!(
+            r#"SELECT $1=ANY(priv_admin) AS "admin!", $1=ANY(priv_manager) AS "manager!", $1=ANY(priv_event) AS "event!" FROM guilds WHERE id=$2"#,
+            try_to_i64(role)?,
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.privilege_set_for", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        let mut privileges = PrivilegeSet::default();
+        for (privilege, granted) in [
+            (Privilege::Admin, record.admin),
+            (Privilege::Manager, record.manager),
+            (Privilege::Event, record.event),
+        ] {
+            if granted {
+                privileges = privileges.insert(privilege);
+            }
         }
-        if self.has_privilege(conn, role, Privilege::Event).await? {
-            privs.push(Privilege::Event);
+        Ok(privileges)
+    }
+
+    /// All privileges granted to a role
+    ///
+    /// [`Privilege::Admin`] implies [`Privilege::Manager`]. Privileges are sorted from the most to the least
+    /// powerful.
+    pub fn get_privileges_for<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+        &'a self,
+        conn: A,
+        role: RoleId,
+    ) -> impl Future<Output = Result<Vec<Privilege>>> + Send + 'a {
+        self.get_privileges_for_inner(conn, role)
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    async fn get_privileges_for_inner<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        role: RoleId,
+    ) -> Result<Vec<Privilege>> {
+        let mut conn = conn
+            .acquire()
+            .await
+            .map_err(query_failed("guild.get_privileges_for", Some(self.0)))?;
+        let granted = self.privilege_set_for(&mut *conn, role).await?;
+        Ok(Privilege::ALL
+            .iter()
+            .copied()
+            .filter(|privilege| granted.iter().any(|held| held.implies(*privilege)))
+            .collect())
+    }
+
+    /// Privileges held through any of `roles`, typically those of a member, fetched in a single query
+    ///
+    /// [`Privilege::Admin`] implies [`Privilege::Manager`]. Use [`EffectivePrivileges::highest`] to get the most
+    /// powerful one. An empty `roles` slice holds no privilege.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn highest_privilege_of<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        roles: &[RoleId],
+    ) -> Result<EffectivePrivileges> {
+        if roles.is_empty() {
+            return Ok(EffectivePrivileges::default());
         }
-        Ok(privs)
+        let record = traced!(query!(
+            r#"SELECT priv_admin && $1::bigint[] AS "admin!", priv_manager && $1::bigint[] AS "manager!", priv_event && $1::bigint[] AS "event!" FROM guilds WHERE id=$2"#,
+            &to_ids(roles)?,
+            try_to_i64(self.0)?
+        ))
+        .fetch_one(conn)
+        .timed("guild.highest_privilege_of", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        Ok(EffectivePrivileges {
+            admin: record.admin,
+            manager: record.manager || record.admin,
+            event: record.event,
+        })
     }
 }
 
+/// IDs of every guild with a configuration entry, sorted
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+pub async fn get_all_guild_ids<'a, PgExec: Executor<'a, Database = Postgres>>(
+    conn: PgExec,
+) -> Result<Vec<GuildId>> {
+    from_ids(
+        traced!(query_scalar!("SELECT id FROM guilds ORDER BY id"))
+            .fetch_all(conn)
+            .timed("guild.get_all_guild_ids", None)
+            .await?,
+    )
+}
+
 /// Bot's permission system
 ///
 /// Botanist handles permissions through a different system than Discord. This way server admins
 /// can fine tune permissions so that users who should not have access to some discord permissions
 /// can still fully use the bot, or the other way around.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Privileges are displayed as `admin`, `manager` and `event`. They can be parsed from those names, in any case,
+/// or from the aliases `administrator`, `mod`, `moderator` and `events`:
+///
+/// ```
+/// use db_adapter::guild::Privilege;
+///
+/// let command = "give @role Mod";
+/// let privilege: Privilege = command.rsplit(' ').next().unwrap().parse().unwrap();
+/// assert_eq!(privilege, Privilege::Manager);
+/// assert_eq!(privilege.to_string(), "manager");
+/// assert!("owner".parse::<Privilege>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Privilege {
     /// The manager privilege provides low-level administration powers such as message deletion (`clear` command).
     ///  Generally it is good for moderators who are tasked with maintaining order.
@@ -462,6 +1531,45 @@ pub enum Privilege {
     Event,
 }
 
+impl Privilege {
+    /// Every privilege, from the most to the least powerful
+    pub const ALL: [Privilege; 3] = [Privilege::Admin, Privilege::Manager, Privilege::Event];
+
+    /// If a role with this privilege also has `other`
+    ///
+    /// Every privilege implies itself and [`Privilege::Admin`] implies [`Privilege::Manager`].
+    /// [`Privilege::Event`] is unrelated to the two others.
+    pub fn implies(self, other: Privilege) -> bool {
+        self == other || matches!((self, other), (Privilege::Admin, Privilege::Manager))
+    }
+
+    /// Privileges implied by this one, itself included, from the most to the least powerful
+    pub fn implied(self) -> impl DoubleEndedIterator<Item = Privilege> {
+        Privilege::ALL
+            .iter()
+            .copied()
+            .filter(move |other| self.implies(*other))
+    }
+}
+
+/// Privileges are partially ordered by [`Privilege::implies`]
+///
+/// [`Privilege::Admin`] is greater than [`Privilege::Manager`] while [`Privilege::Event`] can't be compared to
+/// either of them.
+impl PartialOrd for Privilege {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        if self == other {
+            Some(Ordering::Equal)
+        } else if self.implies(*other) {
+            Some(Ordering::Greater)
+        } else if other.implies(*self) {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
+}
+
 impl AsRef<str> for Privilege {
     fn as_ref(&self) -> &str {
         match self {
@@ -472,29 +1580,345 @@ impl AsRef<str> for Privilege {
     }
 }
 
+impl fmt::Display for Privilege {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Privilege::Admin => "admin",
+            Privilege::Manager => "manager",
+            Privilege::Event => "event",
+        })
+    }
+}
+
+/// Error returned when parsing a [`Privilege`] from an unknown name
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("`{0}` is not a privilege")]
+pub struct ParsePrivilegeError(pub String);
+
+impl FromStr for Privilege {
+    type Err = ParsePrivilegeError;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "admin" | "administrator" => Ok(Privilege::Admin),
+            "manager" | "mod" | "moderator" => Ok(Privilege::Manager),
+            "event" | "events" => Ok(Privilege::Event),
+            _ => Err(ParsePrivilegeError(name.to_string())),
+        }
+    }
+}
+
+/// Privileges held by a member through their roles, see [`GuildConfig::highest_privilege_of`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EffectivePrivileges {
+    pub admin: bool,
+    pub manager: bool,
+    pub event: bool,
+}
+
+impl EffectivePrivileges {
+    /// Most powerful privilege held
+    ///
+    /// Privileges are ranked as follows: [`Privilege::Admin`] > [`Privilege::Manager`] > [`Privilege::Event`].
+    /// Since [`Privilege::Event`] is unrelated to the two others it is only returned when neither of them is held.
+    /// Returns [`None`] if no privilege is held.
+    pub fn highest(&self) -> Option<Privilege> {
+        if self.admin {
+            Some(Privilege::Admin)
+        } else if self.manager {
+            Some(Privilege::Manager)
+        } else if self.event {
+            Some(Privilege::Event)
+        } else {
+            None
+        }
+    }
+}
+
+/// A set of [`Privilege`]s, stored as a bitfield
+///
+/// It is [`Copy`] and never allocates. Get the privileges of a role with [`GuildConfig::privilege_set_for`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct PrivilegeSet(u8);
+
+impl PrivilegeSet {
+    const ALL: [Privilege; 3] = [Privilege::Manager, Privilege::Admin, Privilege::Event];
+
+    fn bit(privilege: Privilege) -> u8 {
+        match privilege {
+            Privilege::Manager => 1,
+            Privilege::Admin => 1 << 1,
+            Privilege::Event => 1 << 2,
+        }
+    }
+
+    /// If `privilege` is in the set
+    pub fn contains(self, privilege: Privilege) -> bool {
+        self.0 & Self::bit(privilege) != 0
+    }
+
+    /// The set with `privilege` added
+    #[must_use]
+    pub fn insert(self, privilege: Privilege) -> Self {
+        PrivilegeSet(self.0 | Self::bit(privilege))
+    }
+
+    /// The set without `privilege`
+    #[must_use]
+    pub fn remove(self, privilege: Privilege) -> Self {
+        PrivilegeSet(self.0 & !Self::bit(privilege))
+    }
+
+    /// `true` if there is no privilege in the set
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Privileges in the set, in the order manager, admin, event
+    pub fn iter(self) -> impl Iterator<Item = Privilege> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(move |privilege| self.contains(*privilege))
+    }
+}
+
+impl std::iter::FromIterator<Privilege> for PrivilegeSet {
+    fn from_iter<I: IntoIterator<Item = Privilege>>(privileges: I) -> Self {
+        privileges
+            .into_iter()
+            .fold(PrivilegeSet::default(), PrivilegeSet::insert)
+    }
+}
+
+/// Part of a guild's configuration that may have been left unset, see [`GuildConfig::incomplete`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MissingField {
+    /// No admin channel
+    AdminChan,
+    /// No role has any privilege
+    AnyPrivilege,
+    /// No welcome message
+    WelcomeMessage,
+    /// No goodbye message
+    GoodbyeMessage,
+}
+
+/// A set of [`MissingField`]s, stored as a bitfield
+///
+/// Tells [`GuildConfig::incomplete`] which fields a guild must have to be complete.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CompletionCriteria(u8);
+
+impl CompletionCriteria {
+    const ALL: [MissingField; 4] = [
+        MissingField::AdminChan,
+        MissingField::AnyPrivilege,
+        MissingField::WelcomeMessage,
+        MissingField::GoodbyeMessage,
+    ];
+
+    fn bit(field: MissingField) -> u8 {
+        match field {
+            MissingField::AdminChan => 1,
+            MissingField::AnyPrivilege => 1 << 1,
+            MissingField::WelcomeMessage => 1 << 2,
+            MissingField::GoodbyeMessage => 1 << 3,
+        }
+    }
+
+    /// Every field
+    pub fn all() -> Self {
+        Self::ALL.iter().copied().collect()
+    }
+
+    /// If `field` is in the set
+    pub fn contains(self, field: MissingField) -> bool {
+        self.0 & Self::bit(field) != 0
+    }
+
+    /// The set with `field` added
+    #[must_use]
+    pub fn insert(self, field: MissingField) -> Self {
+        CompletionCriteria(self.0 | Self::bit(field))
+    }
+
+    /// The set without `field`
+    #[must_use]
+    pub fn remove(self, field: MissingField) -> Self {
+        CompletionCriteria(self.0 & !Self::bit(field))
+    }
+
+    /// `true` if there is no field in the set
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Fields in the set, in the order of [`MissingField`]'s variants
+    pub fn iter(self) -> impl Iterator<Item = MissingField> {
+        Self::ALL
+            .iter()
+            .copied()
+            .filter(move |field| self.contains(*field))
+    }
+}
+
+impl std::iter::FromIterator<MissingField> for CompletionCriteria {
+    fn from_iter<I: IntoIterator<Item = MissingField>>(fields: I) -> Self {
+        fields
+            .into_iter()
+            .fold(CompletionCriteria::default(), CompletionCriteria::insert)
+    }
+}
+
+/// Whole configuration of a guild
+///
+/// Fetched in bulk by [`GuildConfig::get_many`] and restored by [`GuildConfig::import_from_snapshot`]. With the
+/// `net` feature it can be serialized, IDs are written as strings.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuildConfigData {
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str"))]
+    pub id: GuildId,
+    pub welcome_message: Option<String>,
+    pub goodbye_message: Option<String>,
+    pub advertise: bool,
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str::option"))]
+    pub admin_chan: Option<ChannelId>,
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str::option_vec"))]
+    pub poll_chans: Option<Vec<ChannelId>>,
+    pub privileges: PrivilegeSnapshot,
+    pub default_slap_expiry_days: Option<u32>,
+    pub max_roles_per_privilege: u32,
+    pub slap_threshold: Option<u32>,
+}
+
+/// Roles of every privilege of a guild
+///
+/// Get the current one with [`GuildConfig::get_privilege_snapshot`]. With the `net` feature it can be
+/// serialized, IDs are written as strings.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrivilegeSnapshot {
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str::vec"))]
+    pub admin: Vec<RoleId>,
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str::vec"))]
+    pub manager: Vec<RoleId>,
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str::vec"))]
+    pub event: Vec<RoleId>,
+}
+
+impl PrivilegeSnapshot {
+    /// Roles with the specified privilege
+    pub fn roles_with(&self, privilege: Privilege) -> &[RoleId] {
+        match privilege {
+            Privilege::Admin => &self.admin,
+            Privilege::Manager => &self.manager,
+            Privilege::Event => &self.event,
+        }
+    }
+}
+
+/// One line per privilege listing its roles, such as `Admin: [2480, 9801]`
+///
+/// Lines are in the order of [`Privilege::ALL`], privileges without roles are listed as `[]`.
+impl fmt::Display for PrivilegeSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, privilege) in Privilege::ALL.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            let label = match privilege {
+                Privilege::Admin => "Admin",
+                Privilege::Manager => "Manager",
+                Privilege::Event => "Event",
+            };
+            write!(f, "{}: [", label)?;
+            for (j, role) in self.roles_with(*privilege).iter().enumerate() {
+                if j > 0 {
+                    f.write_str(", ")?;
+                }
+                write!(f, "{}", role)?;
+            }
+            f.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Roles that gain or lose a privilege between two [`PrivilegeSnapshot`]s
+///
+/// Privileges without any change have no entry so nothing changed if both maps are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrivilegeDiff {
+    pub added: HashMap<Privilege, Vec<RoleId>>,
+    pub removed: HashMap<Privilege, Vec<RoleId>>,
+}
+
+impl PrivilegeDiff {
+    /// Changes needed to go from `old` to `new`
+    ///
+    /// Roles are listed in the order they appear in their snapshot.
+    pub fn between(old: &PrivilegeSnapshot, new: &PrivilegeSnapshot) -> PrivilegeDiff {
+        let missing_from = |roles: &[RoleId], other: &[RoleId]| -> Vec<RoleId> {
+            roles
+                .iter()
+                .filter(|role| !other.contains(role))
+                .copied()
+                .collect()
+        };
+        let mut diff = PrivilegeDiff::default();
+        for privilege in [Privilege::Admin, Privilege::Manager, Privilege::Event] {
+            let (old_roles, new_roles) = (old.roles_with(privilege), new.roles_with(privilege));
+            let added = missing_from(new_roles, old_roles);
+            if !added.is_empty() {
+                diff.added.insert(privilege, added);
+            }
+            let removed = missing_from(old_roles, new_roles);
+            if !removed.is_empty() {
+                diff.removed.insert(privilege, removed);
+            }
+        }
+        diff
+    }
+
+    /// `true` if there is no change
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
 /// Builder for new configuration entries
 ///
 /// This should only be used when the bot joins a new [Guild].
 /// This builder is used to quickly whip up a new configuration with sensible defaults
 /// that can be easilly overriden. For how to use see [`GuildConfig::new()`] and the tests.
 ///
-/// [Guild]: serenity::model::guild::Guild`
+/// [Guild]: https://docs.rs/serenity/0.10/serenity/model/guild/struct.Guild.html
 #[derive(Debug)]
 pub struct GuildConfigBuilder<'a> {
     id: GuildId,
-    welcome_message: Option<&'a str>,
-    goodbye_message: Option<&'a str>,
+    welcome_message: Option<Cow<'a, str>>,
+    goodbye_message: Option<Cow<'a, str>>,
     advertise: bool,
     admin_chan: Option<ChannelId>,
     poll_chans: Option<Vec<ChannelId>>,
     priv_manager: Vec<RoleId>,
     priv_admin: Vec<RoleId>,
     priv_event: Vec<RoleId>,
+    max_roles_per_privilege: u32,
+    slap_threshold: Option<u32>,
 }
 
 impl<'a> GuildConfigBuilder<'a> {
-    pub fn new(id: GuildId) -> GuildConfigBuilder<'a> {
-        GuildConfigBuilder {
+    /// # Error
+    /// Returns [`GuildConfigError::InvalidId`] if `id` is 0 since discord never issues such ID.
+    pub fn new(id: GuildId) -> Result<GuildConfigBuilder<'a>> {
+        if id.0 == 0 {
+            return Err(GuildConfigError::InvalidId(id).into());
+        }
+        Ok(GuildConfigBuilder {
             id,
             welcome_message: None,
             goodbye_message: None,
@@ -504,35 +1928,152 @@ impl<'a> GuildConfigBuilder<'a> {
             priv_manager: vec![],
             priv_admin: vec![],
             priv_event: vec![],
-        }
+            max_roles_per_privilege: DEFAULT_MAX_ROLES_PER_PRIVILEGE,
+            slap_threshold: None,
+        })
     }
 
     pub fn welcome_message(&mut self, msg: &'a str) -> Result<&mut Self> {
-        if msg.len() > 2000 {
-            Err(GuildConfigError::MessageTooLong {
-                field: "welcome_message".into(),
-            }
-            .into())
-        } else {
-            self.welcome_message = Some(msg);
-            Ok(self)
-        }
+        self.welcome_message = Some(checked_message(MessageType::Welcome, msg.into())?);
+        Ok(self)
     }
 
     pub fn goodbye_message(&mut self, msg: &'a str) -> Result<&mut Self> {
-        if msg.len() > 2000 {
-            Err(GuildConfigError::MessageTooLong {
-                field: "goodbye_message".into(),
-            }
-            .into())
-        } else {
-            self.goodbye_message = Some(msg);
-            Ok(self)
-        }
+        self.goodbye_message = Some(checked_message(MessageType::Goodbye, msg.into())?);
+        Ok(self)
     }
 
     pub fn advertise(&mut self, v: bool) -> &mut Self {
         self.advertise = v;
         self
     }
+
+    /// Number of roles each privilege can have, [`DEFAULT_MAX_ROLES_PER_PRIVILEGE`] by default
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `max` is 0 or over [`i32::MAX`].
+    pub fn max_roles_per_privilege(&mut self, max: u32) -> Result<&mut Self> {
+        positive_i32("max_roles_per_privilege", max)?;
+        self.max_roles_per_privilege = max;
+        Ok(self)
+    }
+
+    /// Number of slaps after which members are escalated, escalation is disabled by default
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `threshold` is 0 or over [`i32::MAX`].
+    pub fn slap_threshold(&mut self, threshold: u32) -> Result<&mut Self> {
+        positive_i32("slap_threshold", threshold)?;
+        self.slap_threshold = Some(threshold);
+        Ok(self)
+    }
+
+    /// Checks the configuration as a whole without querying the database, [`GuildConfig::new`] calls it before
+    /// inserting anything
+    ///
+    /// # Error
+    /// Returns [`GuildConfigError::InvalidId`] if the ID is 0, [`GuildConfigError::MessageTooLong`] if a message
+    /// is over 2000 characters and [`GuildConfigError::TooManyRoles`] if a privilege has more roles than the
+    /// builder's `max_roles_per_privilege`.
+    pub fn validate(&self) -> Result<()> {
+        if self.id.0 == 0 {
+            return Err(GuildConfigError::InvalidId(self.id).into());
+        }
+        for (msg_ty, msg) in [
+            (MessageType::Welcome, &self.welcome_message),
+            (MessageType::Goodbye, &self.goodbye_message),
+        ] {
+            if let Some(msg) = msg {
+                checked_message(msg_ty, Cow::Borrowed(msg))?;
+            }
+        }
+        let max = self.max_roles_per_privilege;
+        for (privilege, roles) in [
+            (Privilege::Admin, &self.priv_admin),
+            (Privilege::Manager, &self.priv_manager),
+            (Privilege::Event, &self.priv_event),
+        ] {
+            if roles.len() > max as usize {
+                return Err(GuildConfigError::TooManyRoles { privilege, max }.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+fn checked_message(msg_ty: MessageType, msg: Cow<'_, str>) -> Result<Cow<'_, str>> {
+    if msg.len() > 2000 {
+        return Err(GuildConfigError::MessageTooLong {
+            field: msg_ty.as_ref().to_string(),
+        }
+        .into());
+    }
+    Ok(msg)
+}
+
+/// Loads a builder from string fields, such as a table of a deserialized config file
+///
+/// `id` is required, `welcome_message`, `goodbye_message`, `advertise` (`true` or `false`),
+/// `max_roles_per_privilege` and `slap_threshold` are optional and validated like the builder's setters.
+///
+/// # Errors
+/// Returns [`GuildConfigError::UnknownField`] for any other key and [`AdapterError::InvalidArgument`] if `id` is
+/// missing or a value can't be parsed.
+impl TryFrom<HashMap<String, String>> for GuildConfigBuilder<'static> {
+    type Error = AdapterError;
+
+    fn try_from(mut fields: HashMap<String, String>) -> Result<Self> {
+        let id = fields
+            .remove("id")
+            .ok_or(AdapterError::InvalidArgument {
+                name: "id",
+                reason: "missing",
+            })?
+            .parse::<u64>()
+            .map_err(|_| AdapterError::InvalidArgument {
+                name: "id",
+                reason: "not a snowflake",
+            })?;
+        let mut builder = GuildConfigBuilder::new(id.into())?;
+        for (field, value) in fields {
+            match field.as_str() {
+                "welcome_message" => {
+                    builder.welcome_message =
+                        Some(checked_message(MessageType::Welcome, value.into())?)
+                }
+                "goodbye_message" => {
+                    builder.goodbye_message =
+                        Some(checked_message(MessageType::Goodbye, value.into())?)
+                }
+                "advertise" => {
+                    builder.advertise =
+                        value.parse().map_err(|_| AdapterError::InvalidArgument {
+                            name: "advertise",
+                            reason: "must be `true` or `false`",
+                        })?
+                }
+                "max_roles_per_privilege" => {
+                    let max = value
+                        .parse::<u32>()
+                        .map_err(|_| AdapterError::InvalidArgument {
+                            name: "max_roles_per_privilege",
+                            reason: "not a number",
+                        })?;
+                    builder.max_roles_per_privilege(max)?;
+                }
+                "slap_threshold" => {
+                    let threshold =
+                        value
+                            .parse::<u32>()
+                            .map_err(|_| AdapterError::InvalidArgument {
+                                name: "slap_threshold",
+                                reason: "not a number",
+                            })?;
+                    builder.slap_threshold(threshold)?;
+                }
+                _ => return Err(GuildConfigError::UnknownField(field).into()),
+            }
+        }
+        Ok(builder)
+    }
 }
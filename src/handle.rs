@@ -0,0 +1,385 @@
+//! Handles capturing the [connection pool]
+//!
+//! Passing a `conn` to every method gets noisy when an application only ever uses one pool. [`BotanistDb`]
+//! wraps the pool and hands out handles for guilds and slap records. Their methods are the ones of
+//! [`crate::guild::GuildConfig`], [`crate::slap::GuildSlapRecord`] and [`crate::slap::MemberSlapRecord`] minus
+//! the `conn` argument.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), db_adapter::AdapterError> {
+//! use db_adapter::handle::BotanistDb;
+//!
+//! let db = BotanistDb::from(db_adapter::try_establish_connection().await?);
+//! let guild = db.guild(4200.into());
+//! guild.set_advertise(false).await?;
+//! let slaps = db.slaps(guild.id()).len().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The handles only cover queries run straight on the pool. Use the modules' types directly to run them in a
+//! transaction (see [`crate::with_transaction`]) or on a specific connection.
+//!
+//! [connection pool]: sqlx::postgres::PgPool
+
+use crate::guild::{
+    self, EffectivePrivileges, GuildConfigBuilder, GuildConfigData, MessageType, Privilege,
+    PrivilegeDiff, PrivilegeSet, PrivilegeSnapshot,
+};
+use crate::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+use crate::metrics::PoolMetrics;
+use crate::slap::{
+    self, ActivityBucket, Enforcer, MemberSlapSummary, MonthlySlapSummary, SlapReport,
+    ThresholdStatus,
+};
+use crate::{AdapterError, PgPool};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures::TryStreamExt;
+use tokio_stream::{Stream, StreamExt};
+
+type Result<Return> = std::result::Result<Return, AdapterError>;
+
+/// Entry point of the handle API, wraps a [connection pool]
+///
+/// Cloning it is cheap, clones share the same pool.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+#[derive(Debug, Clone)]
+pub struct BotanistDb {
+    pool: PgPool,
+}
+
+impl BotanistDb {
+    /// Wraps `pool`
+    pub fn new(pool: PgPool) -> Self {
+        BotanistDb { pool }
+    }
+
+    /// The wrapped pool, to use the rest of the API
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Closes the wrapped pool once its in-flight queries are done, see [`crate::shutdown`]
+    ///
+    /// Every clone of the handle shares the pool so they can't be used afterwards either.
+    pub async fn close(&self, timeout: std::time::Duration) -> Result<()> {
+        crate::shutdown(self.pool.clone(), timeout).await
+    }
+
+    /// Current metrics of the wrapped pool, see [`crate::metrics::metrics`]
+    pub fn metrics(&self) -> PoolMetrics {
+        crate::metrics::metrics(&self.pool)
+    }
+
+    /// Handle to the configuration of the guild `id`
+    ///
+    /// The guild may not have a configuration entry yet, see [`GuildHandle::exists`].
+    pub fn guild(&self, id: GuildId) -> GuildHandle {
+        GuildHandle {
+            inner: guild::GuildConfig(id),
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Creates the configuration entry of a guild, see [`guild::GuildConfig::new`]
+    pub async fn create_guild(&self, builder: GuildConfigBuilder<'_>) -> Result<GuildHandle> {
+        let inner = guild::GuildConfig::new(&self.pool, builder).await?;
+        Ok(GuildHandle {
+            inner,
+            pool: self.pool.clone(),
+        })
+    }
+
+    /// Handle to the slap record of the guild `id`
+    pub fn slaps(&self, id: GuildId) -> GuildSlapsHandle {
+        GuildSlapsHandle {
+            inner: slap::GuildSlapRecord(id),
+            pool: self.pool.clone(),
+        }
+    }
+
+    /// Handle to the slap record of `member` in the guild `guild`
+    pub fn member_slaps(&self, guild: GuildId, member: UserId) -> MemberSlapsHandle {
+        MemberSlapsHandle {
+            inner: slap::MemberSlapRecord(guild, member),
+            pool: self.pool.clone(),
+        }
+    }
+}
+
+impl From<PgPool> for BotanistDb {
+    fn from(pool: PgPool) -> Self {
+        BotanistDb::new(pool)
+    }
+}
+
+// Defines async methods forwarding to the method of the same name on `self.inner`, with the pool as `conn`
+macro_rules! forward {
+    ($path:literal; $(fn $name:ident(&self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            #[doc = concat!("See [`", $path, "::", stringify!($name), "`]")]
+            pub async fn $name(&self $(, $arg: $ty)*) -> Result<$ret> {
+                self.inner.$name(&self.pool $(, $arg)*).await
+            }
+        )*
+    };
+}
+
+/// Configuration of a guild, see [`guild::GuildConfig`]
+///
+/// Obtained from [`BotanistDb::guild`] or [`BotanistDb::create_guild`].
+#[derive(Debug)]
+pub struct GuildHandle {
+    inner: guild::GuildConfig,
+    pool: PgPool,
+}
+
+impl GuildHandle {
+    /// ID of the guild
+    pub fn id(&self) -> GuildId {
+        self.inner.0
+    }
+
+    /// See [`guild::GuildConfig::require_exists`]
+    pub async fn require_exists(self) -> Result<Self> {
+        let GuildHandle { inner, pool } = self;
+        let inner = inner.require_exists(&pool).await?;
+        Ok(GuildHandle { inner, pool })
+    }
+
+    forward! {
+        "guild::GuildConfig";
+        fn exists(&self) -> bool;
+        fn get_welcome_message(&self) -> Option<String>;
+        fn get_goodbye_message(&self) -> Option<String>;
+        fn set_welcome_message(&self, msg: Option<&str>) -> ();
+        fn set_goodbye_message(&self, msg: Option<&str>) -> ();
+        fn get_message_raw(&self, msg_ty: MessageType) -> Option<String>;
+        fn set_message_raw(&self, msg_ty: MessageType, msg: Option<&str>) -> ();
+        fn get_advertise(&self) -> bool;
+        fn set_advertise(&self, policy: bool) -> ();
+        fn get_admin_chan(&self) -> Option<ChannelId>;
+        fn set_admin_chan(&self, chan: Option<ChannelId>) -> ();
+        fn get_default_slap_expiry_days(&self) -> Option<u32>;
+        fn set_default_slap_expiry_days(&self, days: Option<u32>) -> ();
+        fn get_slap_threshold(&self) -> Option<u32>;
+        fn set_slap_threshold(&self, threshold: Option<u32>) -> ();
+        fn get_max_roles_per_privilege(&self) -> u32;
+        fn set_max_roles_per_privilege(&self, max: u32) -> ();
+        fn get_roles_with(&self, privilege: Privilege) -> Vec<RoleId>;
+        fn get_privilege_snapshot(&self) -> PrivilegeSnapshot;
+        fn get_full_privilege_audit(&self) -> String;
+        fn diff_privileges(&self, new_snapshot: &PrivilegeSnapshot) -> PrivilegeDiff;
+        fn roles_with_any_privilege(&self) -> Vec<RoleId>;
+        fn roles_matching_privilege_set(&self, required: PrivilegeSet) -> Vec<RoleId>;
+        fn privilege_count(&self, privilege: Privilege) -> usize;
+        fn set_all_privileges(&self, admin: &[RoleId], manager: &[RoleId], event: &[RoleId]) -> ();
+        fn import_from_snapshot(&self, snapshot: &GuildConfigData) -> ();
+        fn grant_privilege(&self, id: RoleId, privilege: Privilege) -> ();
+        fn deny_privilege(&self, id: RoleId, privilege: Privilege) -> ();
+        fn transfer_privilege(&self, from: RoleId, to: RoleId, privilege: Privilege) -> ();
+        fn copy_privileges_from(&self, source: RoleId, dest: RoleId) -> ();
+        fn have_privilege(&self, roles: &[RoleId], privilege: Privilege) -> bool;
+        fn any_has_privilege(&self, roles: &[RoleId], privilege: Privilege) -> bool;
+        fn has_privilege(&self, role: RoleId, privilege: Privilege) -> bool;
+        fn has_privileges(&self, role: RoleId, privileges: &[Privilege]) -> bool;
+        fn privilege_set_for(&self, role: RoleId) -> PrivilegeSet;
+        fn get_privileges_for(&self, role: RoleId) -> Vec<Privilege>;
+        fn highest_privilege_of(&self, roles: &[RoleId]) -> EffectivePrivileges;
+    }
+}
+
+/// Slap record of a guild, see [`slap::GuildSlapRecord`]
+///
+/// Obtained from [`BotanistDb::slaps`].
+#[derive(Debug)]
+pub struct GuildSlapsHandle {
+    inner: slap::GuildSlapRecord,
+    pool: PgPool,
+}
+
+impl GuildSlapsHandle {
+    /// ID of the guild
+    pub fn id(&self) -> GuildId {
+        self.inner.0
+    }
+
+    /// See [`slap::GuildSlapRecord::slaps`]
+    pub fn slaps(&self) -> impl Stream<Item = Result<SlapReport>> + '_ {
+        self.inner.slaps(&self.pool)
+    }
+
+    /// See [`slap::GuildSlapRecord::offenders`], the records are returned as handles
+    pub fn offenders(&self) -> impl Stream<Item = Result<MemberSlapsHandle>> + '_ {
+        self.inner.offenders(&self.pool).map(move |record| {
+            record.map(|inner| MemberSlapsHandle {
+                inner,
+                pool: self.pool.clone(),
+            })
+        })
+    }
+
+    /// See [`slap::GuildSlapRecord::collect_offenders`], the records are returned as handles
+    pub async fn collect_offenders(&self) -> Result<Vec<MemberSlapsHandle>> {
+        self.offenders().try_collect().await
+    }
+
+    /// See [`slap::GuildSlapRecord::offenders_sorted_by_slap_count`], the records are returned as handles
+    pub fn offenders_sorted_by_slap_count(
+        &self,
+    ) -> impl Stream<Item = Result<(MemberSlapsHandle, i64)>> + '_ {
+        self.inner
+            .offenders_sorted_by_slap_count(&self.pool)
+            .map(move |record| {
+                record.map(|(inner, count)| {
+                    (
+                        MemberSlapsHandle {
+                            inner,
+                            pool: self.pool.clone(),
+                        },
+                        count,
+                    )
+                })
+            })
+    }
+
+    /// See [`slap::GuildSlapRecord::active_members_with_slaps`], the records are returned as handles
+    pub fn active_members_with_slaps(
+        &self,
+        since: DateTime<Utc>,
+    ) -> impl Stream<Item = Result<MemberSlapsHandle>> + '_ {
+        self.inner
+            .active_members_with_slaps(&self.pool, since)
+            .map(move |record| {
+                record.map(|inner| MemberSlapsHandle {
+                    inner,
+                    pool: self.pool.clone(),
+                })
+            })
+    }
+
+    forward! {
+        "slap::GuildSlapRecord";
+        fn new_slap(
+            &self,
+            sentence: MessageId,
+            offender: UserId,
+            enforcer: Enforcer,
+            reason: Option<&str>
+        ) -> SlapReport;
+        fn len(&self) -> usize;
+        fn recent(&self, n: u32) -> Vec<SlapReport>;
+        fn latest(&self) -> Option<SlapReport>;
+        fn oldest(&self) -> Option<SlapReport>;
+        fn slaps_on_date(&self, date: NaiveDate) -> Vec<SlapReport>;
+        fn offender_count(&self) -> usize;
+        fn active_offender_count(&self) -> usize;
+        fn rolling_7day_count(&self) -> usize;
+        fn rolling_30day_count(&self) -> usize;
+        fn slap_rate(&self, days: u32) -> f64;
+        fn monthly_summary(&self, year: i32, month: u32) -> MonthlySlapSummary;
+        fn activity(
+            &self,
+            from: DateTime<Utc>,
+            to: DateTime<Utc>,
+            bucket: ActivityBucket
+        ) -> Vec<(DateTime<Utc>, u64)>;
+        fn slap_heatmap_by_weekday(&self) -> [usize; 7];
+        fn prune_older_than(&self, cutoff: DateTime<Utc>) -> u64;
+        fn expunge_expired_slaps(&self) -> usize;
+    }
+
+    /// See [`slap::GuildSlapRecord::export_as_json`]
+    #[cfg(feature = "net")]
+    pub async fn export_as_json(&self) -> Result<String> {
+        self.inner.export_as_json(&self.pool).await
+    }
+
+    /// See [`slap::GuildSlapRecord::export_json`]
+    #[cfg(feature = "net")]
+    pub fn export_json(&self) -> impl Stream<Item = Result<String>> + '_ {
+        self.inner.export_json(&self.pool)
+    }
+
+    /// See [`slap::GuildSlapRecord::import_from_json`]
+    #[cfg(feature = "net")]
+    pub async fn import_from_json(&self, json: &str) -> Result<usize> {
+        self.inner.import_from_json(&self.pool, json).await
+    }
+
+    /// See [`slap::GuildSlapRecord::export_csv`]
+    #[cfg(feature = "csv")]
+    pub async fn export_csv(&self) -> Result<String> {
+        self.inner.export_csv(&self.pool).await
+    }
+
+    /// See [`slap::GuildSlapRecord::write_csv_to`]
+    #[cfg(feature = "csv")]
+    pub async fn write_csv_to<W: tokio::io::AsyncWrite + Unpin>(&self, writer: W) -> Result<()> {
+        self.inner.write_csv_to(&self.pool, writer).await
+    }
+}
+
+/// Slap record of a guild member, see [`slap::MemberSlapRecord`]
+///
+/// Obtained from [`BotanistDb::member_slaps`] or [`GuildSlapsHandle::offenders`].
+#[derive(Debug)]
+pub struct MemberSlapsHandle {
+    inner: slap::MemberSlapRecord,
+    pool: PgPool,
+}
+
+impl MemberSlapsHandle {
+    /// ID of the guild
+    pub fn guild(&self) -> GuildId {
+        self.inner.0
+    }
+
+    /// ID of the member
+    pub fn member(&self) -> UserId {
+        self.inner.1
+    }
+
+    /// See [`slap::MemberSlapRecord::slaps`]
+    pub fn slaps(&self) -> impl Stream<Item = Result<SlapReport>> + '_ {
+        self.inner.slaps(&self.pool)
+    }
+
+    /// See [`slap::MemberSlapRecord::active_slaps`]
+    pub fn active_slaps(&self) -> impl Stream<Item = Result<SlapReport>> + '_ {
+        self.inner.active_slaps(&self.pool)
+    }
+
+    /// See [`slap::MemberSlapRecord::slaps_including_pardoned`]
+    pub fn slaps_including_pardoned(&self) -> impl Stream<Item = Result<SlapReport>> + '_ {
+        self.inner.slaps_including_pardoned(&self.pool)
+    }
+
+    forward! {
+        "slap::MemberSlapRecord";
+        fn new_slap(
+            &self,
+            sentence: MessageId,
+            enforcer: Enforcer,
+            reason: Option<&str>
+        ) -> SlapReport;
+        fn new_slap_expiring(
+            &self,
+            sentence: MessageId,
+            enforcer: Enforcer,
+            reason: Option<&str>,
+            duration: Duration
+        ) -> SlapReport;
+        fn recent(&self, n: u32) -> Vec<SlapReport>;
+        fn latest(&self) -> Option<SlapReport>;
+        fn oldest(&self) -> Option<SlapReport>;
+        fn last_n_slaps(&self, n: i64) -> Vec<SlapReport>;
+        fn len(&self) -> usize;
+        fn active_len(&self) -> usize;
+        fn len_including_pardoned(&self) -> usize;
+        fn threshold_exceeded(&self, since: Option<DateTime<Utc>>) -> Option<ThresholdStatus>;
+        fn slap_summary(&self) -> MemberSlapSummary;
+        fn pardon_all_community_slaps(&self) -> usize;
+    }
+}
@@ -0,0 +1,55 @@
+//! Discord IDs used throughout the crate
+//!
+//! With the default `serenity` feature these are [serenity]'s own ID types so values can be passed back and
+//! forth without conversion. Without it the crate doesn't depend on serenity at all and this module defines
+//! lookalike newtypes around a `u64` instead. Either way every ID is a tuple struct whose field is the raw
+//! snowflake and converts from and into `u64`, so code sticking to that works with both.
+//!
+//! [serenity]: https://docs.rs/serenity/0.10
+
+#[cfg(feature = "serenity")]
+pub use serenity::model::id::{ChannelId, GuildId, MessageId, RoleId, UserId};
+
+#[cfg(not(feature = "serenity"))]
+macro_rules! snowflake {
+    ($($(#[$doc:meta])* $name:ident;)*) => {
+        $(
+            $(#[$doc])*
+            #[cfg_attr(feature = "net", derive(serde::Deserialize, serde::Serialize))]
+            #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+            pub struct $name(pub u64);
+
+            impl From<u64> for $name {
+                fn from(id: u64) -> Self {
+                    $name(id)
+                }
+            }
+
+            impl From<$name> for u64 {
+                fn from(id: $name) -> Self {
+                    id.0
+                }
+            }
+
+            impl std::fmt::Display for $name {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    self.0.fmt(f)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(not(feature = "serenity"))]
+snowflake! {
+    /// ID of a text channel
+    ChannelId;
+    /// ID of a guild (server)
+    GuildId;
+    /// ID of a message
+    MessageId;
+    /// ID of a role
+    RoleId;
+    /// ID of a user
+    UserId;
+}
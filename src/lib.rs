@@ -14,7 +14,9 @@
 //! [`sqlx`]'s cli tool. Moreover deviations from the scheme provided by the migration scripts
 //! will be detected by the tests (see `tests/framework` and [`mod@sqlx::migrate`]).
 //!
-//! Finally providing a rust library allows [db_adapter] to provide useful abstractions.
+//! Finally providing a rust library allows [db_adapter] to provide useful abstractions. The most commonly used
+//! ones can be imported at once from [`prelude`]. Applications using a single pool may prefer the handles of
+//! [`handle`], which don't take a connection on every call.
 //!
 //! # Setup
 //! Setup is intended to be as simple as possible so if you find some way to simplify a step please open
@@ -23,7 +25,8 @@
 //!  if you don't know how. Then rename `.env-example` to `.env` and enter make sure you place your values
 //! in it.
 //! Now install [sqlx-cli] and run the migrations using `sqlx migrate run`. If you set up the DB and `.env`
-//! correctly you should be good to go!
+//! correctly you should be good to go! Alternatively your application can apply the migrations itself on startup
+//! with [`run_migrations`]. Either way [`assert_schema_compatible`] tells whether the database matches the crate.
 //! If you're only using the library you don't need to do anything else but you could still
 //! run the tests just in case: `cargo t`.
 //!
@@ -37,77 +40,455 @@
 //! [sqlx-cli]: https://github.com/launchbadge/sqlx/tree/master/sqlx-cli
 //! [db_adapter]: [`self`]
 
+use connection::{ConnectionConfig, ConnectionInfo};
+use futures::future::BoxFuture;
+use id::{GuildId, MessageId};
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
 pub use sqlx::postgres::PgPool;
-use std::borrow::Cow;
-use std::convert::TryFrom;
+use sqlx::{Postgres, Transaction};
 use std::env;
-use std::fmt::Write;
+use std::time::Duration;
 use thiserror::Error;
 
+// Logs a query and its parameters when the `tracing` feature is enabled. Parameters prefixed with `@redact`
+// are not logged, use it for PII such as message contents.
+macro_rules! trace_query {
+    (@param @redact $param:expr) => {
+        &"<redacted>" as &dyn std::fmt::Debug
+    };
+    (@param $param:expr) => {
+        &$param as &dyn std::fmt::Debug
+    };
+    ($sql:expr $(, $(@$redact:ident)? $param:expr)* $(,)?) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            query = %$sql,
+            params = ?&[$(trace_query!(@param $(@$redact)? $param)),*] as &[&dyn std::fmt::Debug]
+        );
+    };
+}
+
+// Same as `sqlx::query!`, `sqlx::query_scalar!` and `sqlx::query_as!` but the query is logged with
+// `trace_query!`
+macro_rules! traced {
+    (query_as!($row:ident, $sql:literal $(, $(@$redact:ident)? $param:expr)* $(,)?)) => {{
+        trace_query!($sql $(, $(@$redact)? $param)*);
+        sqlx::query_as!($row, $sql $(, $param)*)
+    }};
+    ($query:ident!($sql:literal $(, $(@$redact:ident)? $param:expr)* $(,)?)) => {{
+        trace_query!($sql $(, $(@$redact)? $param)*);
+        sqlx::$query!($sql $(, $param)*)
+    }};
+}
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+pub mod connection;
 pub mod guild;
+pub mod handle;
+pub mod id;
+pub mod metrics;
+#[cfg(feature = "net")]
+pub mod net;
+pub mod prelude;
 pub mod slap;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 #[cfg(test)]
 mod tests;
+#[cfg(feature = "utils")]
+pub mod utils;
+#[cfg(not(feature = "utils"))]
+pub(crate) mod utils;
+
+pub(crate) use utils::{try_from_i64, try_to_i64};
+
+// Counts a query for `metrics::metrics`, wraps its error in `AdapterError::Query` and logs how long it took to
+// execute when the `tracing` feature is enabled. Call it on the future returned by `execute`, `fetch_one`,
+// `fetch_all` or `fetch_optional`. `operation` is named after the public method, such as
+// "guild.set_welcome_message".
+pub(crate) trait TimedQuery<T>:
+    std::future::Future<Output = Result<T, sqlx::Error>> + Sized
+{
+    fn timed(self, operation: &'static str, guild: Option<GuildId>) -> Timed<Self> {
+        Timed {
+            query: Box::pin(self),
+            start: std::time::Instant::now(),
+            operation,
+            guild,
+        }
+    }
+}
+
+impl<T, F: std::future::Future<Output = Result<T, sqlx::Error>>> TimedQuery<T> for F {}
+
+pub(crate) struct Timed<F> {
+    query: std::pin::Pin<Box<F>>,
+    start: std::time::Instant,
+    operation: &'static str,
+    guild: Option<GuildId>,
+}
+
+impl<T, F: std::future::Future<Output = Result<T, sqlx::Error>>> std::future::Future for Timed<F> {
+    type Output = Result<T, AdapterError>;
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Self::Output> {
+        let output = match self.query.as_mut().poll(cx) {
+            std::task::Poll::Ready(output) => output,
+            std::task::Poll::Pending => return std::task::Poll::Pending,
+        };
+        let elapsed = self.start.elapsed();
+        metrics::record(elapsed, output.is_ok());
+        #[cfg(feature = "tracing")]
+        tracing::debug!(operation = self.operation, ?elapsed, "query executed");
+        std::task::Poll::Ready(output.map_err(query_failed(self.operation, self.guild)))
+    }
+}
+
+// Wraps the errors of the query `operation` in `AdapterError::Query`, for streams and decoding which `timed`
+// doesn't cover
+pub(crate) fn query_failed(
+    operation: &'static str,
+    guild: Option<GuildId>,
+) -> impl Fn(sqlx::Error) -> AdapterError {
+    move |source| AdapterError::Query {
+        operation,
+        guild,
+        source,
+    }
+}
 
 /// Creates a [connection pool] to the database
 ///
 /// # Panic
-/// Panics if `DATABASE_URL` is not set or if the connection could not be established.
+/// Panics if neither `DATABASE_URL` nor `PGHOST` is set or if the connection could not be established.
+/// See [`try_establish_connection`] for a fallible alternative.
 ///
 /// [connection pool]: sqlx::postgres::PgPool
 pub async fn establish_connection() -> PgPool {
+    match try_establish_connection().await {
+        Ok(pool) => pool,
+        Err(AdapterError::EnvError { var, .. }) => panic!("`{}` was not set", var),
+        Err(error) => panic!(
+            "Could not establish connection to the database: {:?}",
+            error
+        ),
+    }
+}
+
+/// Creates a [connection pool] to the database described by the environment
+///
+/// Either `DATABASE_URL` or the discrete `PG*` variables are used, see [`ConnectionInfo::from_env`].
+/// `.env` is loaded before looking the variables up. This function, [`establish_connection`],
+/// [`establish_connection_config`], [`establish_connection_retrying`] and [`connection::DbPools::from_env`] are
+/// the only ones that touch `.env`.
+///
+/// # Errors
+/// Returns [`AdapterError::EnvError`] if neither `DATABASE_URL` nor `PGHOST` is set,
+/// [`connection::ConnectionConfigError::InvalidVar`] if `PGPORT` is invalid and [`AdapterError::SqlxError`] if
+/// the connection could not be established.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+pub async fn try_establish_connection() -> Result<PgPool, AdapterError> {
+    establish_connection_config(&ConnectionConfig::default()).await
+}
+
+/// Creates a [connection pool] to the database described by the environment with the settings of `config`
+///
+/// Works like [`try_establish_connection`] otherwise.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+pub async fn establish_connection_config(
+    config: &ConnectionConfig,
+) -> Result<PgPool, AdapterError> {
+    dotenv::dotenv().ok();
+    config.connect_to(&ConnectionInfo::from_env()?).await
+}
+
+/// Creates a [connection pool] to the database described by the environment, retrying on transient failures
+///
+/// Useful when the application may start before the database, for example in containers. Connection failures
+/// that may go away on their own (the database refusing connections or still starting up) are retried up to
+/// `retries` times. The first retry happens after `backoff`, each following one waits twice as long with
+/// some random jitter added. Every retry is logged at the warn level.
+///
+/// Works like [`try_establish_connection`] otherwise.
+///
+/// # Errors
+/// Same as [`try_establish_connection`]. Errors that retrying can't fix, such as bad credentials or a malformed
+/// URL, are returned right away. Otherwise the last error is returned once all retries failed.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+pub async fn establish_connection_retrying(
+    retries: u32,
+    backoff: Duration,
+) -> Result<PgPool, AdapterError> {
     dotenv::dotenv().ok();
-    PgPool::connect(&env::var("DATABASE_URL").expect("`DATABASE_URL` was not set"))
+    ConnectionConfig::default()
+        .connect_retrying(&ConnectionInfo::from_env()?, retries, backoff)
         .await
-        .expect(
-            format!(
-                "Could not establish connection to {:?}",
-                &env::var("DATABASE_URL")
-            )
-            .as_str(),
-        )
+}
+
+/// Creates a [connection pool] to the database at `url`
+///
+/// Unlike [`try_establish_connection`] this neither reads the environment nor loads `.env`.
+///
+/// # Errors
+/// Returns [`AdapterError::SqlxError`] if `url` is malformed or the connection could not be established.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+pub async fn establish_connection_with(url: &str) -> Result<PgPool, AdapterError> {
+    ConnectionConfig::default().connect(url).await
+}
+
+/// Closes `pool` once its in-flight queries are done, for instance when the application receives `SIGTERM`
+///
+/// The pool stops handing out connections right away, then the connections in use are waited for up to `timeout`
+/// so that no transaction is cut short. Connections returned after the deadline are closed as they come back.
+///
+/// # Errors
+/// Returns [`AdapterError::ShutdownTimedOut`] if connections were still in use when `timeout` elapsed.
+pub async fn shutdown(pool: PgPool, timeout: Duration) -> Result<(), AdapterError> {
+    tokio::time::timeout(timeout, pool.close())
+        .await
+        .map_err(|_| AdapterError::ShutdownTimedOut(timeout))
+}
+
+fn env_var(var: &'static str) -> Result<String, AdapterError> {
+    env::var(var).map_err(|source| AdapterError::EnvError { var, source })
+}
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Applies the migrations shipped with the crate
+///
+/// This does the same as `sqlx migrate run` but without requiring [sqlx-cli]. Migrations that were already
+/// applied are skipped so it's fine to call this every time the application starts.
+///
+/// # Errors
+/// Returns [`AdapterError::MigrateError`] if a migration could not be applied.
+///
+/// [sqlx-cli]: https://github.com/launchbadge/sqlx/tree/master/sqlx-cli
+pub async fn run_migrations(pool: &PgPool) -> Result<(), AdapterError> {
+    Ok(MIGRATOR.run(pool).await?)
+}
+
+/// Number of migrations shipped with the crate that weren't applied to the database yet
+///
+/// The migrations table is created if it doesn't exist yet.
+pub async fn pending_migrations(pool: &PgPool) -> Result<usize, AdapterError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    Ok(MIGRATOR
+        .iter()
+        .filter(|migration| !applied.iter().any(|done| done.version == migration.version))
+        .count())
+}
+
+/// Version of the latest migration shipped with the crate, which is the schema its methods expect
+///
+/// It is generated from the embedded migrations by the build script.
+pub const EXPECTED_SCHEMA_VERSION: i64 = include!(concat!(env!("OUT_DIR"), "/schema_version.rs"));
+
+/// Version of the latest migration applied to the database, 0 if none was
+///
+/// The migrations table is created if it doesn't exist yet.
+pub async fn schema_version(pool: &PgPool) -> Result<i64, AdapterError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    let applied = conn.list_applied_migrations().await?;
+    Ok(applied
+        .iter()
+        .map(|migration| migration.version)
+        .max()
+        .unwrap_or(0))
+}
+
+/// Checks that the schema of the database is [`EXPECTED_SCHEMA_VERSION`]
+///
+/// Meant to be called on startup, before serving anything: the crate's invariants only hold on the schema of
+/// its own migrations. A database that is behind can be upgraded with [`run_migrations`], one that is ahead
+/// requires a newer version of the crate.
+///
+/// # Errors
+/// Returns [`AdapterError::SchemaMismatch`] if the database is at another version.
+pub async fn assert_schema_compatible(pool: &PgPool) -> Result<(), AdapterError> {
+    let found = schema_version(pool).await?;
+    if found != EXPECTED_SCHEMA_VERSION {
+        return Err(AdapterError::SchemaMismatch {
+            expected: EXPECTED_SCHEMA_VERSION,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// `true` if all migrations shipped with the crate were applied to the database
+///
+/// The migrations table is created if it doesn't exist yet.
+pub async fn check_migrations(pool: &PgPool) -> Result<bool, MigrateError> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    if conn.dirty_version().await?.is_some() {
+        return Ok(false);
+    }
+    let applied = conn.list_applied_migrations().await?;
+    Ok(MIGRATOR
+        .iter()
+        .all(|migration| applied.iter().any(|done| done.version == migration.version)))
+}
+
+/// Runs `operations` in a transaction
+///
+/// The transaction is committed if `operations` returns [`Ok`] and rolled back otherwise, so either all of
+/// its writes are kept or none is. Pass `&mut *tx` as the `conn` of the crate's methods. Because of how
+/// closures returning futures are typed the future must be boxed:
+///
+/// ```no_run
+/// # async fn example(pool: db_adapter::PgPool) -> Result<(), db_adapter::AdapterError> {
+/// use db_adapter::{guild::{GuildConfig, GuildConfigBuilder, Privilege}, with_transaction};
+/// use db_adapter::id::{GuildId, RoleId};
+///
+/// let config = with_transaction(&pool, |tx| {
+///     Box::pin(async move {
+///         let config = GuildConfig::new(&mut *tx, GuildConfigBuilder::new(GuildId(5844))?).await?;
+///         config.grant_privilege(&mut *tx, RoleId(22522), Privilege::Admin).await?;
+///         Ok(config)
+///     })
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+/// Returns the error of `operations` if it fails and [`AdapterError::SqlxError`] if the transaction could not
+/// be started or committed.
+pub async fn with_transaction<T, F>(pool: &PgPool, operations: F) -> Result<T, AdapterError>
+where
+    F: for<'t> FnOnce(
+        &'t mut Transaction<'static, Postgres>,
+    ) -> BoxFuture<'t, Result<T, AdapterError>>,
+{
+    let mut tx = pool.begin().await?;
+    match operations(&mut tx).await {
+        Ok(value) => {
+            tx.commit().await?;
+            Ok(value)
+        }
+        Err(error) => {
+            // the transaction is rolled back when dropped anyway, only the error of `operations` matters
+            if let Err(rollback) = tx.rollback().await {
+                log::warn!("could not roll the transaction back: {}", rollback);
+            }
+            Err(error)
+        }
+    }
 }
 
 /// Wrapper around all errors coming from the crate
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum AdapterError {
     /// [`sqlx::Error`] errors
     ///
     /// The crate uses [`sqlx`] under the hood to communicate with the DBs.
     /// If the later fails for any reason the error is relayed.
-    #[error("could not execute querry")]
+    #[error("could not execute query")]
     SqlxError(#[from] sqlx::Error),
+    /// A query of the method `operation` failed
+    ///
+    /// Queries issued by [`guild`] and [`slap`] fail with this variant rather than [`Self::SqlxError`], which is
+    /// left to the ones that don't belong to a specific operation, such as opening a transaction.
+    #[error("could not execute query `{operation}`{}", for_guild(.guild))]
+    Query {
+        operation: &'static str,
+        guild: Option<GuildId>,
+        source: sqlx::Error,
+    },
+    /// An environment variable required by the crate is missing or invalid
+    #[error("environment variable `{var}` is missing or invalid")]
+    EnvError {
+        var: &'static str,
+        source: env::VarError,
+    },
+    /// The migrations could not be applied or inspected
+    #[error("migration error")]
+    MigrateError(#[from] MigrateError),
+    /// The schema of the database isn't the one the crate expects, see [`assert_schema_compatible`]
+    #[error("the database schema is at version {found} but version {expected} is expected")]
+    SchemaMismatch { expected: i64, found: i64 },
+    /// Invalid connection settings
+    #[error("invalid connection settings")]
+    ConnectionConfigError(#[from] connection::ConnectionConfigError),
+    /// The guild has no configuration entry
+    #[error("{0:?} has no configuration entry")]
+    GuildNotFound(GuildId),
+    /// No slap was issued with this sentence
+    #[error("no slap has {0:?} as sentence")]
+    SlapNotFound(MessageId),
+    /// An ID doesn't fit in the type it is converted to
+    ///
+    /// IDs are stored as `bigint`s so they must be at most [`i64::MAX`] and the database must not hold negative IDs.
+    #[error("ID {value} is out of range")]
+    IdOutOfRange { value: i128 },
+    /// An argument is outside of the values the method accepts
+    #[error("invalid `{name}`: {reason}")]
+    InvalidArgument {
+        name: &'static str,
+        reason: &'static str,
+    },
     /// Errors with guilds' configuration
     #[error("guild configuration error")]
     GuildError(#[from] guild::GuildConfigError),
+    /// Errors with the slap system
+    #[error("slap error")]
+    SlapError(#[from] slap::SlapError),
+    /// Writing to a user provided writer failed (ex: CSV exports)
+    ///
+    /// Also returned if the runtime of the [`blocking`] API could not be started.
+    #[error("could not write output")]
+    IoError(#[from] std::io::Error),
+    /// The [`blocking`] API was called from within an async runtime
+    ///
+    /// Blocking there would stall the runtime, use the async API instead.
+    #[error("the blocking API can't be used from within an async runtime")]
+    BlockingInAsyncContext,
+    /// Connections were still in use when the deadline of [`shutdown`] was hit
+    #[error("connections were still in use after {0:?}")]
+    ShutdownTimedOut(Duration),
 }
 
-pub(crate) fn as_pg_array(ids: &[i64]) -> String {
-    let mut array = String::new();
-    if ids.is_empty() {
-        array.push_str("'{}'");
-        return array;
-    }
-    write!(array, "'{{").unwrap();
-    for int in ids {
-        write!(array, "{},", int).unwrap();
+impl AdapterError {
+    /// The underlying [`sqlx::Error`] of [`Self::SqlxError`] and [`Self::Query`]
+    pub fn sqlx_error(&self) -> Option<&sqlx::Error> {
+        match self {
+            AdapterError::SqlxError(error) | AdapterError::Query { source: error, .. } => {
+                Some(error)
+            }
+            _ => None,
+        }
     }
-    array.pop(); //removing the trailing comma
-    write!(array, "}}'").unwrap();
-    array
 }
 
-pub(crate) fn stringify_option<'a, T: std::fmt::Display>(option: Option<T>) -> Cow<'a, str> {
-    match option {
-        Some(value) => Cow::Owned(format!("'{}'", value)),
-        None => Cow::Borrowed("NULL"),
+fn for_guild(guild: &Option<GuildId>) -> String {
+    match guild {
+        Some(guild) => format!(" for guild {}", guild),
+        None => String::new(),
     }
 }
 
-pub(crate) fn from_i64<I: From<u64>>(int: i64) -> I {
-    u64::try_from(int).unwrap().into()
-}
-
-pub(crate) fn to_i64<I: Into<u64>>(id: I) -> i64 {
-    i64::try_from(id.into()).unwrap()
+// Turns `sqlx::Error::RowNotFound` into `not_found`, other errors are left untouched
+pub(crate) fn not_found_as(not_found: AdapterError) -> impl FnOnce(AdapterError) -> AdapterError {
+    move |error| {
+        if matches!(error.sqlx_error(), Some(sqlx::Error::RowNotFound)) {
+            not_found
+        } else {
+            error
+        }
+    }
 }
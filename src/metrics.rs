@@ -0,0 +1,75 @@
+//! Metrics of the [connection pool] and of the queries issued by the crate, for monitoring
+//!
+//! Every query made by the methods of [`crate::guild`] and [`crate::slap`] is counted as it completes, those
+//! streamed (such as [`crate::slap::GuildSlapRecord::slaps`]) excepted. The counters are shared by every pool of
+//! the process and only go up, exporters compute rates from them.
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), db_adapter::AdapterError> {
+//! let pool = db_adapter::try_establish_connection().await?;
+//! let metrics = db_adapter::metrics::metrics(&pool);
+//! println!("{} connections in use", metrics.size as usize - metrics.idle);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [connection pool]: sqlx::postgres::PgPool
+
+use crate::PgPool;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+static QUERIES_EXECUTED: AtomicU64 = AtomicU64::new(0);
+static QUERIES_ERRORED: AtomicU64 = AtomicU64::new(0);
+static QUERIES_NANOS: AtomicU64 = AtomicU64::new(0);
+
+/// Snapshot of the state of a [connection pool] and of the query counters, see [`metrics`]
+///
+/// [connection pool]: sqlx::postgres::PgPool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Number of open connections, idle or in use
+    pub size: u32,
+    /// Number of idle connections
+    pub idle: usize,
+    /// Rough estimate of how long acquiring a connection takes: zero if a connection is idle, the mean
+    /// execution time of the queries otherwise
+    pub acquire_wait_estimate: Duration,
+    /// Number of queries executed since the start of the process, failed ones included
+    pub queries_executed: u64,
+    /// Number of queries which failed since the start of the process
+    pub queries_errored: u64,
+}
+
+/// Current metrics of `pool`
+///
+/// Reading them doesn't touch the database. See [`crate::handle::BotanistDb::metrics`] for handles.
+pub fn metrics(pool: &PgPool) -> PoolMetrics {
+    let idle = pool.num_idle();
+    let queries_executed = QUERIES_EXECUTED.load(Ordering::Relaxed);
+    let acquire_wait_estimate = if idle > 0 || queries_executed == 0 {
+        Duration::from_secs(0)
+    } else {
+        Duration::from_nanos(QUERIES_NANOS.load(Ordering::Relaxed) / queries_executed)
+    };
+    PoolMetrics {
+        size: pool.size(),
+        idle,
+        acquire_wait_estimate,
+        queries_executed,
+        queries_errored: QUERIES_ERRORED.load(Ordering::Relaxed),
+    }
+}
+
+// Called by `Timed` once a query completed
+pub(crate) fn record(elapsed: Duration, succeeded: bool) {
+    QUERIES_EXECUTED.fetch_add(1, Ordering::Relaxed);
+    QUERIES_NANOS.fetch_add(
+        u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX),
+        Ordering::Relaxed,
+    );
+    if !succeeded {
+        QUERIES_ERRORED.fetch_add(1, Ordering::Relaxed);
+    }
+}
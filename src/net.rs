@@ -0,0 +1,620 @@
+//! HTTP representation of the crate's errors
+//!
+//! [`ErrorResponse`] holds everything a handler needs to answer a failed request: the HTTP status and a JSON
+//! body. Database and setup failures are all reported as `500 internal_error` without any detail so that
+//! connection strings and queries never leak to clients. Both [`ErrorResponse`] and [`AdapterError`] implement
+//! [Rocket]'s `Responder` so handlers can return `Result<T, AdapterError>` as is:
+//!
+//! ```no_run
+//! use db_adapter::{guild::GuildConfig, id::GuildId, AdapterError, PgPool};
+//! use rocket::State;
+//!
+//! #[rocket::get("/guilds/<id>/advertise")]
+//! async fn advertise(pool: &State<PgPool>, id: u64) -> Result<String, AdapterError> {
+//!     let advertise = GuildConfig::from(GuildId(id)).get_advertise(pool.inner()).await?;
+//!     Ok(advertise.to_string())
+//! }
+//! ```
+//!
+//! Other frameworks can build their response from the fields of [`ErrorResponse`].
+//!
+//! [`DbAdapterFairing`] creates the pool handlers such as the one above get from Rocket's state:
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), rocket::Error> {
+//! use db_adapter::net::DbAdapterFairing;
+//!
+//! rocket::build()
+//!     .attach(DbAdapterFairing::from_env().run_migrations(true))
+//!     .launch()
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Routes about a guild can take an [`ExistingGuildConfig`], which checks that the guild of the route has a
+//! configuration entry.
+//!
+//! [`AdapterError`] and [`GuildConfigError`] can also be serialized directly, for trusted clients such as the
+//! dashboard. They are serialized as a flat map with a `code` and the fields listed below. IDs are strings
+//! because snowflakes don't fit in a javascript number. Codes and field names are stable: new ones may be
+//! added but existing ones are only changed in major releases.
+//!
+//! | `code` | Fields |
+//! |---|---|
+//! | `database_error` | `kind`: the kind of [`sqlx::Error`] in snake case, `sqlstate` for `kind: database` |
+//! | `env_error` | `var` |
+//! | `migrate_error` | |
+//! | `schema_mismatch` | `expected`, `found`: schema versions as numbers |
+//! | `invalid_connection_config` | |
+//! | `guild_not_found` | `guild` |
+//! | `slap_not_found` | `sentence` |
+//! | `id_out_of_range` | `value` |
+//! | `invalid_argument` | `name`, `reason` |
+//! | `io_error` | |
+//! | `blocking_in_async_context` | |
+//! | `shutdown_timed_out` | `timeout_ms` |
+//! | `message_too_long` | `field` |
+//! | `role_no_privilege` | `role`, `privilege` (`admin`, `manager` or `event`) |
+//! | `guild_already_exists` | `guild` |
+//! | `invalid_guild_id` | `guild` |
+//! | `privilege_invariant_violation` | `role` |
+//! | `unknown_field` | `field` |
+//! | `too_many_roles` | `privilege`, `max` |
+//! | `cutoff_in_future` | `cutoff` (RFC 3339) |
+//! | `reason_too_long` | `max_length` |
+//!
+//! Messages, queries and connection details are never included.
+//!
+//! [`GuildConfigData`](crate::guild::GuildConfigData) and [`PrivilegeSnapshot`](crate::guild::PrivilegeSnapshot)
+//! implement `Serialize` and `Deserialize` so configurations can be exported and imported as JSON. Their IDs are
+//! strings as well.
+//!
+//! [Rocket]: https://rocket.rs
+
+use crate::connection::ConnectionConfig;
+use crate::guild::{GuildConfig, GuildConfigError, Privilege};
+use crate::handle::{BotanistDb, GuildHandle};
+use crate::id::GuildId;
+use crate::slap::{SlapError, MAX_REASON_LENGTH};
+use crate::{AdapterError, PgPool};
+use rocket::fairing::{self, Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::outcome::Outcome;
+use rocket::request::{self, FromRequest};
+use rocket::response::{self, Responder, Response};
+use rocket::{Build, Orbit, Request, Rocket};
+use serde::de::{Deserializer, Error as _};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// An [`AdapterError`] ready to be sent to an HTTP client
+///
+/// Only `code`, `message` and `ids` are serialized. IDs are serialized as strings because snowflakes don't fit
+/// in a javascript number.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorResponse {
+    /// HTTP status code
+    #[serde(skip)]
+    pub status: u16,
+    /// Stable identifier of the error, in snake case
+    pub code: &'static str,
+    /// Human readable description of the error
+    pub message: String,
+    /// IDs the error is about, keyed by their kind (`guild`, `role`, `sentence`)
+    pub ids: BTreeMap<&'static str, String>,
+}
+
+impl ErrorResponse {
+    fn new(status: u16, code: &'static str, message: String) -> Self {
+        ErrorResponse {
+            status,
+            code,
+            message,
+            ids: BTreeMap::new(),
+        }
+    }
+
+    fn with_id<I: Into<u64>>(mut self, kind: &'static str, id: I) -> Self {
+        self.ids.insert(kind, id.into().to_string());
+        self
+    }
+}
+
+impl From<&AdapterError> for ErrorResponse {
+    fn from(error: &AdapterError) -> Self {
+        let message = error.to_string();
+        match error {
+            AdapterError::GuildNotFound(guild) => {
+                ErrorResponse::new(404, "guild_not_found", message).with_id("guild", *guild)
+            }
+            AdapterError::SlapNotFound(sentence) => {
+                ErrorResponse::new(404, "slap_not_found", message).with_id("sentence", *sentence)
+            }
+            AdapterError::IdOutOfRange { .. } => {
+                ErrorResponse::new(422, "id_out_of_range", message)
+            }
+            AdapterError::InvalidArgument { .. } => {
+                ErrorResponse::new(422, "invalid_argument", message)
+            }
+            AdapterError::GuildError(error) => guild_error(error),
+            AdapterError::SlapError(error) => slap_error(error),
+            AdapterError::SqlxError(_)
+            | AdapterError::Query { .. }
+            | AdapterError::EnvError { .. }
+            | AdapterError::MigrateError(_)
+            | AdapterError::SchemaMismatch { .. }
+            | AdapterError::ConnectionConfigError(_)
+            | AdapterError::IoError(_)
+            | AdapterError::BlockingInAsyncContext
+            | AdapterError::ShutdownTimedOut(_) => {
+                ErrorResponse::new(500, "internal_error", "internal server error".to_string())
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ErrorResponse {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_string(&self).map_err(|_| Status::InternalServerError)?;
+        Response::build_from(body.respond_to(request)?)
+            .status(Status::new(self.status))
+            .header(ContentType::JSON)
+            .ok()
+    }
+}
+
+impl<'r> Responder<'r, 'static> for AdapterError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        ErrorResponse::from(&self).respond_to(request)
+    }
+}
+
+/// Rocket fairing creating the [connection pool] on ignite and closing it on shutdown
+///
+/// The pool is added to Rocket's managed state, handlers get it with `&State<PgPool>`. If the pool can't be
+/// created, or the migrations can't be applied when [`Self::run_migrations`] is set, the error is logged and
+/// ignition is aborted so that the application doesn't start without a database. On shutdown the pool is closed
+/// with [`crate::shutdown`], giving in-flight queries [`Self::shutdown_timeout`] to complete.
+///
+/// [connection pool]: sqlx::postgres::PgPool
+#[derive(Debug, Clone)]
+pub struct DbAdapterFairing {
+    // the environment is read when `None`
+    url: Option<String>,
+    config: ConnectionConfig,
+    migrate: bool,
+    shutdown_timeout: Duration,
+}
+
+impl DbAdapterFairing {
+    /// Connects to the database described by the environment, like [`crate::try_establish_connection`]
+    ///
+    /// The environment is read on ignite, not when the fairing is created.
+    pub fn from_env() -> Self {
+        DbAdapterFairing {
+            url: None,
+            config: ConnectionConfig::default(),
+            migrate: false,
+            shutdown_timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Connects to the database at `url`, like [`crate::establish_connection_with`]
+    pub fn with_url<S: Into<String>>(url: S) -> Self {
+        DbAdapterFairing {
+            url: Some(url.into()),
+            ..DbAdapterFairing::from_env()
+        }
+    }
+
+    /// Settings of the pool, [`ConnectionConfig::default`] unless changed
+    pub fn config(mut self, config: ConnectionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Whether to apply the migrations with [`crate::run_migrations`] once connected, `false` by default
+    pub fn run_migrations(mut self, migrate: bool) -> Self {
+        self.migrate = migrate;
+        self
+    }
+
+    /// How long in-flight queries are waited for on shutdown, 5 seconds by default
+    pub fn shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    async fn connect(&self) -> Result<PgPool, AdapterError> {
+        let pool = match &self.url {
+            Some(url) => self.config.connect(url).await?,
+            None => crate::establish_connection_config(&self.config).await?,
+        };
+        if self.migrate {
+            crate::run_migrations(&pool).await?;
+        }
+        Ok(pool)
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for DbAdapterFairing {
+    fn info(&self) -> Info {
+        Info {
+            name: "db-adapter",
+            kind: Kind::Ignite | Kind::Shutdown,
+        }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
+        match self.connect().await {
+            Ok(pool) => Ok(rocket.manage(pool)),
+            Err(error) => {
+                match std::error::Error::source(&error) {
+                    Some(source) => {
+                        log::error!("could not set up the database: {}: {}", error, source)
+                    }
+                    None => log::error!("could not set up the database: {}", error),
+                }
+                Err(rocket)
+            }
+        }
+    }
+
+    async fn on_shutdown(&self, rocket: &Rocket<Orbit>) {
+        if let Some(pool) = rocket.state::<PgPool>() {
+            if let Err(error) = crate::shutdown(pool.clone(), self.shutdown_timeout).await {
+                log::warn!("could not close the pool gracefully: {}", error);
+            }
+        }
+    }
+}
+
+/// Request guard holding the configuration of the guild of the route, which is checked to exist
+///
+/// The guild ID is read from the first dynamic segment of the route. Since the guard takes care of it, the
+/// segment can be left unnamed: `/guilds/<_>/welcome`. The pool is the one managed by Rocket, see
+/// [`DbAdapterFairing`].
+///
+/// The guard fails with [`AdapterError::GuildNotFound`] (404) if the guild has no entry, with
+/// [`AdapterError::InvalidArgument`] or [`GuildConfigError::InvalidId`] (422) if the segment isn't a guild ID
+/// and with an internal error (500) if the pool is missing or the query failed. Rocket answers failed guards
+/// with its catchers. To answer with the JSON body of the error instead, take a
+/// `Result<ExistingGuildConfig, AdapterError>` and return the error:
+///
+/// ```no_run
+/// use db_adapter::{net::ExistingGuildConfig, AdapterError};
+///
+/// #[rocket::get("/guilds/<_>/welcome")]
+/// async fn welcome(
+///     guild: Result<ExistingGuildConfig, AdapterError>,
+/// ) -> Result<Option<String>, AdapterError> {
+///     guild?.get_welcome_message().await
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ExistingGuildConfig(pub GuildHandle);
+
+impl Deref for ExistingGuildConfig {
+    type Target = GuildHandle;
+
+    fn deref(&self) -> &GuildHandle {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ExistingGuildConfig {
+    type Error = AdapterError;
+
+    async fn from_request(request: &'r Request<'_>) -> request::Outcome<Self, AdapterError> {
+        match existing_guild(request).await {
+            Ok(guild) => Outcome::Success(ExistingGuildConfig(guild)),
+            Err(error) => Outcome::Error((Status::new(ErrorResponse::from(&error).status), error)),
+        }
+    }
+}
+
+async fn existing_guild(request: &Request<'_>) -> Result<GuildHandle, AdapterError> {
+    let id = guild_segment(request)
+        .and_then(|segment| segment.parse::<u64>().ok())
+        .ok_or(AdapterError::InvalidArgument {
+            name: "guild",
+            reason: "must be a guild ID",
+        })?;
+    let guild = GuildConfig::try_new(GuildId::from(id))?;
+    let pool = request.rocket().state::<PgPool>().ok_or_else(|| {
+        sqlx::Error::Configuration("no `PgPool` is managed, attach `DbAdapterFairing`".into())
+    })?;
+    BotanistDb::new(pool.clone())
+        .guild(guild.0)
+        .require_exists()
+        .await
+}
+
+// the segment of the request matched by the first dynamic segment of its route
+fn guild_segment<'a>(request: &'a Request<'_>) -> Option<&'a str> {
+    let route = request.route()?;
+    let index = route
+        .uri
+        .path()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .position(|segment| segment.starts_with('<'))?;
+    request.uri().path().segments().get(index)
+}
+
+fn guild_error(error: &GuildConfigError) -> ErrorResponse {
+    let message = error.to_string();
+    match error {
+        GuildConfigError::AlreadyExists { id, .. } => {
+            ErrorResponse::new(409, "guild_already_exists", message).with_id("guild", *id)
+        }
+        GuildConfigError::InvalidId(id) => {
+            ErrorResponse::new(422, "invalid_guild_id", message).with_id("guild", *id)
+        }
+        GuildConfigError::MessageTooLong { .. } => {
+            ErrorResponse::new(422, "message_too_long", message)
+        }
+        GuildConfigError::RoleNoPrivilege { role, .. } => {
+            ErrorResponse::new(422, "role_no_privilege", message).with_id("role", *role)
+        }
+        GuildConfigError::PrivilegeInvariantViolation { role } => {
+            ErrorResponse::new(422, "privilege_invariant_violation", message).with_id("role", *role)
+        }
+        GuildConfigError::UnknownField(_) => ErrorResponse::new(422, "unknown_field", message),
+        GuildConfigError::TooManyRoles { .. } => ErrorResponse::new(422, "too_many_roles", message),
+    }
+}
+
+fn slap_error(error: &SlapError) -> ErrorResponse {
+    let message = error.to_string();
+    match error {
+        SlapError::CutoffInFuture(_) => ErrorResponse::new(422, "cutoff_in_future", message),
+        SlapError::ReasonTooLong => ErrorResponse::new(422, "reason_too_long", message),
+    }
+}
+
+impl Serialize for AdapterError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            AdapterError::SqlxError(error) | AdapterError::Query { source: error, .. } => {
+                map.serialize_entry("code", "database_error")?;
+                map.serialize_entry("kind", sqlx_error_kind(error))?;
+                if let sqlx::Error::Database(error) = error {
+                    map.serialize_entry("sqlstate", &error.code())?;
+                }
+            }
+            AdapterError::EnvError { var, .. } => {
+                map.serialize_entry("code", "env_error")?;
+                map.serialize_entry("var", var)?;
+            }
+            AdapterError::MigrateError(_) => map.serialize_entry("code", "migrate_error")?,
+            AdapterError::SchemaMismatch { expected, found } => {
+                map.serialize_entry("code", "schema_mismatch")?;
+                map.serialize_entry("expected", expected)?;
+                map.serialize_entry("found", found)?;
+            }
+            AdapterError::ConnectionConfigError(_) => {
+                map.serialize_entry("code", "invalid_connection_config")?
+            }
+            AdapterError::GuildNotFound(guild) => {
+                map.serialize_entry("code", "guild_not_found")?;
+                map.serialize_entry("guild", &guild.0.to_string())?;
+            }
+            AdapterError::SlapNotFound(sentence) => {
+                map.serialize_entry("code", "slap_not_found")?;
+                map.serialize_entry("sentence", &sentence.0.to_string())?;
+            }
+            AdapterError::IdOutOfRange { value } => {
+                map.serialize_entry("code", "id_out_of_range")?;
+                map.serialize_entry("value", &value.to_string())?;
+            }
+            AdapterError::InvalidArgument { name, reason } => {
+                map.serialize_entry("code", "invalid_argument")?;
+                map.serialize_entry("name", name)?;
+                map.serialize_entry("reason", reason)?;
+            }
+            AdapterError::GuildError(error) => guild_error_entries(&mut map, error)?,
+            AdapterError::SlapError(SlapError::CutoffInFuture(cutoff)) => {
+                map.serialize_entry("code", "cutoff_in_future")?;
+                map.serialize_entry("cutoff", &cutoff.to_rfc3339())?;
+            }
+            AdapterError::SlapError(SlapError::ReasonTooLong) => {
+                map.serialize_entry("code", "reason_too_long")?;
+                map.serialize_entry("max_length", &MAX_REASON_LENGTH)?;
+            }
+            AdapterError::IoError(_) => map.serialize_entry("code", "io_error")?,
+            AdapterError::BlockingInAsyncContext => {
+                map.serialize_entry("code", "blocking_in_async_context")?
+            }
+            AdapterError::ShutdownTimedOut(timeout) => {
+                map.serialize_entry("code", "shutdown_timed_out")?;
+                map.serialize_entry("timeout_ms", &(timeout.as_millis() as u64))?;
+            }
+        }
+        map.end()
+    }
+}
+
+impl Serialize for GuildConfigError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(None)?;
+        guild_error_entries(&mut map, self)?;
+        map.end()
+    }
+}
+
+fn guild_error_entries<M: SerializeMap>(
+    map: &mut M,
+    error: &GuildConfigError,
+) -> Result<(), M::Error> {
+    match error {
+        GuildConfigError::MessageTooLong { field } => {
+            map.serialize_entry("code", "message_too_long")?;
+            map.serialize_entry("field", field)
+        }
+        GuildConfigError::RoleNoPrivilege { role, privilege } => {
+            map.serialize_entry("code", "role_no_privilege")?;
+            map.serialize_entry("role", &role.0.to_string())?;
+            map.serialize_entry("privilege", privilege_name(*privilege))
+        }
+        GuildConfigError::AlreadyExists { id, .. } => {
+            map.serialize_entry("code", "guild_already_exists")?;
+            map.serialize_entry("guild", &id.0.to_string())
+        }
+        GuildConfigError::InvalidId(id) => {
+            map.serialize_entry("code", "invalid_guild_id")?;
+            map.serialize_entry("guild", &id.0.to_string())
+        }
+        GuildConfigError::PrivilegeInvariantViolation { role } => {
+            map.serialize_entry("code", "privilege_invariant_violation")?;
+            map.serialize_entry("role", &role.0.to_string())
+        }
+        GuildConfigError::UnknownField(field) => {
+            map.serialize_entry("code", "unknown_field")?;
+            map.serialize_entry("field", field)
+        }
+        GuildConfigError::TooManyRoles { privilege, max } => {
+            map.serialize_entry("code", "too_many_roles")?;
+            map.serialize_entry("privilege", privilege_name(*privilege))?;
+            map.serialize_entry("max", max)
+        }
+    }
+}
+
+fn privilege_name(privilege: Privilege) -> &'static str {
+    match privilege {
+        Privilege::Admin => "admin",
+        Privilege::Manager => "manager",
+        Privilege::Event => "event",
+    }
+}
+
+// only the kind of error is safe to share, the rest may hold queries or connection strings
+fn sqlx_error_kind(error: &sqlx::Error) -> &'static str {
+    match error {
+        sqlx::Error::Configuration(_) => "configuration",
+        sqlx::Error::Database(_) => "database",
+        sqlx::Error::Io(_) => "io",
+        sqlx::Error::Tls(_) => "tls",
+        sqlx::Error::Protocol(_) => "protocol",
+        sqlx::Error::RowNotFound => "row_not_found",
+        sqlx::Error::TypeNotFound { .. } => "type_not_found",
+        sqlx::Error::ColumnIndexOutOfBounds { .. } => "column_index_out_of_bounds",
+        sqlx::Error::ColumnNotFound(_) => "column_not_found",
+        sqlx::Error::ColumnDecode { .. } => "column_decode",
+        sqlx::Error::Decode(_) => "decode",
+        sqlx::Error::PoolTimedOut => "pool_timed_out",
+        sqlx::Error::PoolClosed => "pool_closed",
+        sqlx::Error::WorkerCrashed => "worker_crashed",
+        sqlx::Error::Migrate(_) => "migrate",
+        _ => "other",
+    }
+}
+
+// an ID written as a string, snowflakes don't fit in a javascript number
+struct IdStr<I>(I);
+
+impl<I: Copy + Into<u64>> Serialize for IdStr<I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let id: u64 = self.0.into();
+        serializer.collect_str(&id)
+    }
+}
+
+impl<'de, I: From<u64>> Deserialize<'de> for IdStr<I> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        id.parse::<u64>()
+            .map(|id| IdStr(I::from(id)))
+            .map_err(D::Error::custom)
+    }
+}
+
+/// `#[serde(with = "...")]` modules writing IDs as strings
+pub(crate) mod id_str {
+    use super::IdStr;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<I: Copy + Into<u64>, S: Serializer>(
+        id: &I,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        IdStr(*id).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, I: From<u64>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<I, D::Error> {
+        Ok(IdStr::deserialize(deserializer)?.0)
+    }
+
+    /// For `Option`s of IDs
+    pub mod option {
+        use super::IdStr;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<I: Copy + Into<u64>, S: Serializer>(
+            id: &Option<I>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            id.map(IdStr).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, I: From<u64>, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<I>, D::Error> {
+            Ok(Option::<IdStr<I>>::deserialize(deserializer)?.map(|id| id.0))
+        }
+    }
+
+    /// For `Vec`s of IDs
+    pub mod vec {
+        use super::IdStr;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<I: Copy + Into<u64>, S: Serializer>(
+            ids: &[I],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(ids.iter().copied().map(IdStr))
+        }
+
+        pub fn deserialize<'de, I: From<u64>, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<I>, D::Error> {
+            Ok(Vec::<IdStr<I>>::deserialize(deserializer)?
+                .into_iter()
+                .map(|id| id.0)
+                .collect())
+        }
+    }
+
+    /// For `Option`s of `Vec`s of IDs
+    pub mod option_vec {
+        use super::IdStr;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<I: Copy + Into<u64>, S: Serializer>(
+            ids: &Option<Vec<I>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            ids.as_ref()
+                .map(|ids| ids.iter().copied().map(IdStr).collect::<Vec<_>>())
+                .serialize(serializer)
+        }
+
+        pub fn deserialize<'de, I: From<u64>, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<I>>, D::Error> {
+            Ok(Option::<Vec<IdStr<I>>>::deserialize(deserializer)?
+                .map(|ids| ids.into_iter().map(|id| id.0).collect()))
+        }
+    }
+}
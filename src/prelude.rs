@@ -0,0 +1,16 @@
+//! Re-exports of the most commonly used items
+//!
+//! Most users of the crate need the same handful of types. Rather than importing each of them from their
+//! own module you can glob import the prelude:
+//!
+//! ```
+//! use db_adapter::prelude::*;
+//! ```
+
+pub use crate::guild::{GuildConfig, GuildConfigBuilder, Privilege};
+pub use crate::handle::BotanistDb;
+pub use crate::slap::{Enforcer, GuildSlapRecord, MemberSlapRecord, SlapReport};
+pub use crate::{
+    establish_connection, establish_connection_config, establish_connection_retrying,
+    establish_connection_with, shutdown, try_establish_connection, AdapterError, PgPool,
+};
@@ -5,59 +5,264 @@
 //!
 //! ## Errors
 //! All methods of this module which return a `Result` do so because sql querries through to the database may
-//! fail. As such you should handle [`AdapterError::SqlxError`]. Because it is part of the signature of most methods
+//! fail. As such you should handle [`AdapterError::Query`]. Because it is part of the signature of most methods
 //! errors are undocumented if they only return a database error. Otherwise an *Error* section is provided.
+//! The same goes for [`AdapterError::IdOutOfRange`] which any method may return if an ID doesn't fit in the
+//! database.
+//!
+//! ## Pardons
+//! Slaps are not deleted when they are forgiven. Instead a slap can be pardoned (see [`SlapReport::pardon`]),
+//! in which case it is kept for transparency but no longer counts towards the offender's record.
+//! Methods of [`MemberSlapRecord`] ignore pardoned slaps unless their name says otherwise.
+//!
+//! ## Retention
+//! Slaps are mostly deleted through pruning (see [`GuildSlapRecord::prune_older_than`] and
+//! [`prune_all_guilds`]) and expiry (see [`MemberSlapRecord::new_slap_expiring`] and
+//! [`GuildSlapRecord::expunge_expired_slaps`]). Neither cares whether a slap was pardoned or not. Guilds can
+//! also make every slap expire by default, see [`crate::guild::GuildConfig::set_default_slap_expiry_days`].
+//! Community amnesties delete the community slaps of a member outright, see
+//! [`MemberSlapRecord::pardon_all_community_slaps`].
 
-use crate::{from_i64, stringify_option, to_i64, AdapterError};
+use crate::{not_found_as, query_failed, try_from_i64, try_to_i64, AdapterError, TimedQuery};
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
 #[cfg(feature = "net")]
 use serde::{Deserialize, Serialize};
 
-use serenity::{
-    futures::TryStreamExt,
-    model::id::{GuildId, MessageId, UserId},
-};
-use sqlx::{query, query_scalar, Executor, Postgres};
+use crate::id::{ChannelId, GuildId, MessageId, UserId};
+use futures::{future::Either, TryStreamExt};
+use sqlx::{Acquire, Executor, Postgres};
+use std::convert::TryFrom;
+use std::future::Future;
+use thiserror::Error;
+#[cfg(feature = "csv")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio_stream::{Stream, StreamExt};
 
+/// Number of slaps deleted per query by [`prune_all_guilds`]
+pub const PRUNE_BATCH_SIZE: i64 = 1000;
+
+/// Maximum number of characters of a slap's reason
+pub const MAX_REASON_LENGTH: usize = 2048;
+
+/// Reason shown by [`SlapReport::reason_or_default`] when the default reason was used
+pub const DEFAULT_REASON: &str = "No reason given";
+
+/// Maximum number of characters of the value of an embed field, longer values are truncated by
+/// [`SlapReport::to_embed_fields`]
+pub const EMBED_FIELD_VALUE_LIMIT: usize = 1024;
+
+/// Errors originating from the slap system
+#[derive(Error, Debug)]
+pub enum SlapError {
+    #[error("retention cutoff {0} is in the future")]
+    CutoffInFuture(DateTime<Utc>),
+    #[error("slap reason can't be over {} characters", MAX_REASON_LENGTH)]
+    ReasonTooLong,
+}
+
 /// Method through which the slap was issued
 ///
 /// Botanist allows slaps to be given either by a member with the
-/// `manager` privilege or by a public vote.
-//internally uses None as Community
+/// `manager` privilege, by a public vote or by the bot itself.
+//internally uses None as Community and `AUTOMATIC_ENFORCER` as Automatic
 #[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
 pub enum Enforcer {
     /// The verdict was issued by popular vote
     Community,
     /// A manager issued a slap. Their [`UserId`] is encapsulated.
-    Manager(UserId),
+    ///
+    /// The ID can't be 0, slaps with such an enforcer are rejected with [`AdapterError::InvalidArgument`].
+    Manager(#[cfg_attr(feature = "net", serde(with = "crate::net::id_str"))] UserId),
+    /// The bot issued the slap on its own (ex: automod)
+    Automatic,
 }
 
+// discord never issues a snowflake of 0 so it can't be mistaken for a manager
+const AUTOMATIC_ENFORCER: u64 = 0;
+
 impl From<Option<u64>> for Enforcer {
+    /// `Some(0)` is decoded as [`Enforcer::Automatic`]
     fn from(option: Option<u64>) -> Self {
         match option {
+            Some(AUTOMATIC_ENFORCER) => Enforcer::Automatic,
             Some(id) => Enforcer::Manager(id.into()),
             None => Enforcer::Community,
         }
     }
 }
 
-fn option_to_enforcer(option: Option<i64>) -> Enforcer {
-    match option {
-        Some(int) => Enforcer::Manager(from_i64(int)),
-        None => Enforcer::Community,
+impl Enforcer {
+    /// The manager's ID if the slap was issued by a manager
+    pub fn manager_id(self) -> Option<UserId> {
+        match self {
+            Enforcer::Manager(user) => Some(user),
+            _ => None,
+        }
+    }
+
+    /// `true` if the slap was issued by popular vote
+    pub fn is_community(self) -> bool {
+        self == Enforcer::Community
+    }
+
+    /// `true` if the slap was issued by a manager
+    pub fn is_manager(self) -> bool {
+        matches!(self, Enforcer::Manager(_))
     }
 }
 
-pub(crate) fn enforcer_to_option(enforcer: Enforcer) -> Option<UserId> {
+fn option_to_enforcer(option: Option<i64>) -> Result<Enforcer> {
+    Ok(Enforcer::from(option.map(try_from_i64::<u64>).transpose()?))
+}
+
+// `Enforcer::Manager(UserId(0))` is rejected since it would be read back as `Enforcer::Automatic`
+pub(crate) fn enforcer_to_option(enforcer: Enforcer) -> Result<Option<UserId>> {
     match enforcer {
-        Enforcer::Manager(user) => Some(user),
-        Enforcer::Community => None,
+        Enforcer::Manager(user) if user.0 == AUTOMATIC_ENFORCER => {
+            Err(AdapterError::InvalidArgument {
+                name: "enforcer",
+                reason: "a manager can't have the ID 0",
+            })
+        }
+        Enforcer::Manager(user) => Ok(Some(user)),
+        Enforcer::Automatic => Ok(Some(UserId(AUTOMATIC_ENFORCER))),
+        Enforcer::Community => Ok(None),
+    }
+}
+
+fn to_pardon_info(by: Option<i64>, at: Option<DateTime<Utc>>) -> Result<Option<PardonInfo>> {
+    match (by, at) {
+        (Some(by), Some(at)) => Ok(Some(PardonInfo {
+            by: try_from_i64(by)?,
+            at,
+        })),
+        _ => Ok(None),
+    }
+}
+
+// A `slaps` row as selected by every query returning `SlapReport`s, the columns must be selected in this order
+struct RawSlap {
+    sentence: i64,
+    offender: i64,
+    enforcer: Option<i64>,
+    reason: Option<String>,
+    pardoned_by: Option<i64>,
+    pardoned_at: Option<DateTime<Utc>>,
+    expires_at: Option<DateTime<Utc>>,
+    notes: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl RawSlap {
+    fn into_report(self) -> Result<SlapReport> {
+        Ok(SlapReport {
+            sentence: try_from_i64(self.sentence)?,
+            offender: try_from_i64(self.offender)?,
+            enforcer: option_to_enforcer(self.enforcer)?,
+            reason: self.reason,
+            pardoned: to_pardon_info(self.pardoned_by, self.pardoned_at)?,
+            expires_at: self.expires_at,
+            notes: self.notes,
+            created_at: self.created_at,
+        })
     }
 }
 
 type Result<R> = std::result::Result<R, AdapterError>;
+
+// Streams can't return early so an ID that can't be converted is reported as the stream's only item
+fn stream_or_error<'a, T: 'a, S: Stream<Item = Result<T>> + 'a>(
+    stream: Result<S>,
+) -> impl Stream<Item = Result<T>> + 'a {
+    match stream {
+        Ok(stream) => Either::Left(stream),
+        Err(error) => Either::Right(tokio_stream::once(Err(error))),
+    }
+}
+
+/// Details about the pardon of a slap
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct PardonInfo {
+    /// Who pardoned the slap.
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str"))]
+    pub by: UserId,
+    /// When the slap was pardoned.
+    pub at: DateTime<Utc>,
+}
+
+/// Slap statistics of a guild over a month, see [`GuildSlapRecord::monthly_summary`]
+///
+/// Pardoned slaps are counted. Slaps issued by [`Enforcer::Automatic`] only count towards `total` and
+/// `unique_offenders`.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MonthlySlapSummary {
+    /// Number of slaps issued during the month
+    pub total: usize,
+    /// Number of slaps issued by [`Enforcer::Community`]
+    pub community: usize,
+    /// Number of slaps issued by an [`Enforcer::Manager`]
+    pub manager: usize,
+    /// Number of members slapped at least once
+    pub unique_offenders: usize,
+}
+
+/// Slap counts of a member by enforcer, see [`MemberSlapRecord::slap_summary`]
+///
+/// Pardoned slaps aren't counted. Slaps issued by [`Enforcer::Automatic`] only count towards `total`.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemberSlapSummary {
+    /// Number of slaps of the member, same as [`MemberSlapRecord::len`]
+    pub total: usize,
+    /// Number of slaps issued by [`Enforcer::Community`]
+    pub community: usize,
+    /// Number of slaps issued by an [`Enforcer::Manager`]
+    pub manager: usize,
+}
+
+/// Slap count of a member against the escalation threshold of their guild, see
+/// [`MemberSlapRecord::threshold_exceeded`]
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThresholdStatus {
+    /// Number of active slaps of the member
+    pub count: usize,
+    /// The guild's `slap_threshold`
+    pub threshold: u32,
+    /// Whether `count` reached `threshold`
+    pub exceeded: bool,
+}
+
+/// Length of the periods counted by [`GuildSlapRecord::activity`]
+///
+/// Periods are delimited in UTC, weeks start on Monday.
+#[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ActivityBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl ActivityBucket {
+    // unit understood by postgres' `date_trunc` and intervals
+    fn unit(self) -> &'static str {
+        match self {
+            ActivityBucket::Day => "day",
+            ActivityBucket::Week => "week",
+            ActivityBucket::Month => "month",
+        }
+    }
+}
+
 /// A single slap object
+///
+/// With the `net` feature it can be serialized, IDs are written as strings since snowflakes don't fit in a
+/// javascript number.
 #[cfg_attr(feature = "net", derive(Deserialize, Serialize))]
 #[derive(Debug, PartialEq, Eq)]
 pub struct SlapReport {
@@ -67,8 +272,10 @@ pub struct SlapReport {
     /// points to the message attributed to the reason of the slap. That is to say the one users collectively reacted
     /// with the slap emoji.
     /// Otherwise it points to the message that issued the slap (so a command message).
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str"))]
     pub sentence: MessageId,
     /// The slapped user.
+    #[cfg_attr(feature = "net", serde(with = "crate::net::id_str"))]
     pub offender: UserId,
     /// Who delivered the slap.
     ///
@@ -76,44 +283,236 @@ pub struct SlapReport {
     pub enforcer: Enforcer,
     /// The reason for the slap.
     ///
-    /// This is [`None`] if `enforcer` is  [`Enforcer::Community`] or if the default reason was used.
-    /// The default reason is used when the enforcer doesn't provide a `reason` argument when issueing the slap.
+    /// This is [`None`] if the default reason was used. The default reason is used when the enforcer doesn't
+    /// provide a `reason` argument when issueing the slap.
+    /// When `enforcer` is [`Enforcer::Community`] the reason generally holds the content of `sentence`.
     pub reason: Option<String>,
+    /// The pardon of the slap, if any.
+    ///
+    /// Pardoned slaps are kept in the database but do not count towards the offender's record anymore.
+    pub pardoned: Option<PardonInfo>,
+    /// When the slap expires, if it is temporary.
+    ///
+    /// Expired slaps are kept and counted until [`GuildSlapRecord::expunge_expired_slaps`] deletes them.
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Private notes of the moderators, see [`Self::set_notes`].
+    ///
+    /// Unlike `reason` these are meant for moderators only and shouldn't be shown to the offender.
+    pub notes: Option<String>,
+    /// When the slap was issued.
+    pub created_at: DateTime<Utc>,
 }
 
 impl SlapReport {
     /// Retrieves a SlapReport
     ///
     /// Returns [`None`] if no such slap exists.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
     pub async fn get<'a, PgExec: Executor<'a, Database = Postgres>>(
         conn: PgExec,
         sentence: MessageId,
     ) -> Result<Option<SlapReport>> {
-        Ok(query!(
-            "SELECT offender, enforcer, reason FROM slaps WHERE sentence=$1",
-            to_i64(sentence)
-        )
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE sentence=$1",
+            try_to_i64(sentence)?
+        ))
         .fetch_optional(conn)
+        .timed("slap.get", None)
         .await?
-        .map(|record| SlapReport {
-            sentence,
-            offender: UserId(from_i64(record.offender)),
-            enforcer: option_to_enforcer(record.enforcer),
-            reason: record.reason,
-        }))
+        .map(RawSlap::into_report)
+        .transpose()
+    }
+
+    /// Same as [`Self::get`] but a missing slap is an error
+    ///
+    /// # Error
+    /// Returns [`AdapterError::SlapNotFound`] if no such slap exists.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+    pub async fn require_get<'a, PgExec: Executor<'a, Database = Postgres>>(
+        conn: PgExec,
+        sentence: MessageId,
+    ) -> Result<SlapReport> {
+        SlapReport::get(conn, sentence)
+            .await?
+            .ok_or(AdapterError::SlapNotFound(sentence))
+    }
+
+    /// Pardons the slap
+    ///
+    /// The slap is not deleted, it is only flagged as pardoned so that it doesn't count towards the
+    /// offender's record anymore. Pardoning an already pardoned slap overrides the previous [`PardonInfo`].
+    ///
+    /// # Error
+    /// Returns [`AdapterError::SlapNotFound`] if the slap isn't in the database.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(sentence = %self.sentence)))]
+    pub async fn pardon<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &mut self,
+        conn: PgExec,
+        by: UserId,
+    ) -> Result<()> {
+        let at = traced!(query_scalar!(
+            r#"UPDATE slaps SET pardoned_by=$1, pardoned_at=now() WHERE sentence=$2 RETURNING pardoned_at as "pardoned_at!""#,
+            try_to_i64(by)?,
+            try_to_i64(self.sentence)?
+        ))
+        .fetch_one(conn)
+        .timed("slap.pardon", None)
+        .await
+        .map_err(not_found_as(AdapterError::SlapNotFound(self.sentence)))?;
+        self.pardoned = Some(PardonInfo { by, at });
+        Ok(())
+    }
+
+    /// Replaces the moderators' notes of the slap, `None` removes them
+    ///
+    /// Unlike `reason` the notes have no length limit.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::SlapNotFound`] if the slap isn't in the database.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, notes), fields(sentence = %self.sentence)))]
+    pub async fn set_notes<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &mut self,
+        conn: PgExec,
+        notes: Option<&str>,
+    ) -> Result<()> {
+        let updated = traced!(query!(
+            "UPDATE slaps SET notes=$1 WHERE sentence=$2",
+            @redact notes,
+            try_to_i64(self.sentence)?
+        ))
+        .execute(conn)
+        .timed("slap.set_notes", None)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(AdapterError::SlapNotFound(self.sentence));
+        }
+        self.notes = notes.map(str::to_owned);
+        Ok(())
+    }
+
+    /// `true` if the slap was issued by popular vote, see [`Enforcer::is_community`]
+    pub fn is_community_issued(&self) -> bool {
+        self.enforcer.is_community()
+    }
+
+    /// `true` if the slap was issued by a manager, see [`Enforcer::is_manager`]
+    pub fn is_manager_issued(&self) -> bool {
+        self.enforcer.is_manager()
+    }
+
+    /// The ID of the manager who issued the slap, see [`Enforcer::manager_id`]
+    pub fn manager_id(&self) -> Option<UserId> {
+        self.enforcer.manager_id()
+    }
+}
+
+/// Formatting helpers for Discord messages and embeds
+///
+/// Users are formatted as mentions. Embeds are left to the caller so that these don't depend on serenity, the
+/// fields of [`Self::to_embed_fields`] can be passed to serenity's `CreateEmbed::fields` as is.
+impl SlapReport {
+    /// Mention of the offender
+    pub fn offender_mention(&self) -> String {
+        format!("<@{}>", self.offender.0)
+    }
+
+    /// Mention of the manager who issued the slap, `Community` or `Automatic` otherwise
+    pub fn enforcer_mention(&self) -> String {
+        match self.enforcer {
+            Enforcer::Manager(user) => format!("<@{}>", user.0),
+            Enforcer::Community => "Community".to_string(),
+            Enforcer::Automatic => "Automatic".to_string(),
+        }
+    }
+
+    /// The reason of the slap, [`DEFAULT_REASON`] if the default reason was used
+    pub fn reason_or_default(&self) -> &str {
+        self.reason.as_deref().unwrap_or(DEFAULT_REASON)
     }
+
+    /// Link to `sentence`, which was sent in `channel` of `guild`
+    pub fn jump_link(&self, guild: GuildId, channel: ChannelId) -> String {
+        format!(
+            "https://discord.com/channels/{}/{}/{}",
+            guild.0, channel.0, self.sentence.0
+        )
+    }
+
+    /// Fields describing the slap as `(name, value, inline)`
+    ///
+    /// The offender, the enforcer and the reason are always present, the pardoner and the expiry only if the
+    /// slap has them. Values over [`EMBED_FIELD_VALUE_LIMIT`] characters are truncated with an ellipsis. Notes
+    /// are left out since they aren't meant to be shown to the offender.
+    pub fn to_embed_fields(&self) -> Vec<(String, String, bool)> {
+        let mut fields = vec![
+            ("Offender".to_string(), self.offender_mention(), true),
+            ("Enforcer".to_string(), self.enforcer_mention(), true),
+            (
+                "Reason".to_string(),
+                truncated(self.reason_or_default(), EMBED_FIELD_VALUE_LIMIT),
+                false,
+            ),
+        ];
+        if let Some(pardon) = &self.pardoned {
+            fields.push((
+                "Pardoned by".to_string(),
+                format!("<@{}>", pardon.by.0),
+                true,
+            ));
+        }
+        if let Some(expires_at) = self.expires_at {
+            // rendered by discord in the reader's timezone
+            fields.push((
+                "Expires".to_string(),
+                format!("<t:{}:f>", expires_at.timestamp()),
+                true,
+            ));
+        }
+        fields
+    }
+}
+
+fn truncated(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max - 1).collect();
+    truncated.push('…');
+    truncated
 }
 
-async fn insert_raw_slap<'a, PgExec: Executor<'a, Database = Postgres>, S: std::fmt::Display>(
+// Returns when the slap expires, computed by the database so that it matches `expunge_expired_slaps`
+async fn insert_raw_slap<'a, PgExec: Executor<'a, Database = Postgres>>(
     conn: PgExec,
     sentence: i64,
     guild: i64,
     offender: i64,
-    enforcer: Enforcer,
-    reason: Option<S>,
-) -> Result<()> {
-    sqlx::query(&format!("INSERT INTO slaps(sentence, guild, offender, enforcer, reason) VALUES ({}, {}, {}, {}, {})",sentence, guild, offender, stringify_option(enforcer_to_option(enforcer)), stringify_option(reason))).execute(conn).await?;
-    Ok(())
+    enforcer: &Enforcer,
+    reason: Option<&str>,
+    expires_in: Option<Duration>,
+) -> Result<(Option<DateTime<Utc>>, DateTime<Utc>)> {
+    if let Some(reason) = reason {
+        if reason.chars().count() > MAX_REASON_LENGTH {
+            return Err(SlapError::ReasonTooLong.into());
+        }
+    }
+    let enforcer = enforcer_to_option(*enforcer)?.map(try_to_i64).transpose()?;
+    let expires_in = expires_in.map(|duration| duration.num_milliseconds() as f64 / 1000.0);
+    let record = traced!(query!(
+        "INSERT INTO slaps(sentence, guild, offender, enforcer, reason, expires_at) VALUES ($1, $2, $3, $4, $5, now() + COALESCE(make_interval(secs => $6), make_interval(days => (SELECT default_slap_expiry_days FROM guilds WHERE id=$2)))) RETURNING expires_at, created_at",
+        sentence,
+        guild,
+        offender,
+        enforcer,
+        @redact reason,
+        expires_in
+    ))
+    .fetch_one(conn)
+    .timed("slap.insert", try_from_i64(guild).ok())
+    .await?;
+    Ok((record.expires_at, record.created_at))
 }
 
 /// Record of slaps of a guild member
@@ -122,68 +521,361 @@ pub struct MemberSlapRecord(pub GuildId, pub UserId);
 
 impl MemberSlapRecord {
     ///Adds a slap entry for this member
-    pub async fn new_slap<'a, PgExec: Executor<'a, Database = Postgres> + Copy>(
+    ///
+    /// A `reason` can be given whatever the `enforcer` is. The slap expires after the guild's default expiry if
+    /// it has one (see [`crate::guild::GuildConfig::set_default_slap_expiry_days`]).
+    ///
+    /// # Error
+    /// Returns [`SlapError::ReasonTooLong`] if `reason` is over [`MAX_REASON_LENGTH`] characters and
+    /// [`AdapterError::InvalidArgument`] if `enforcer` is a manager with the ID 0.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, reason), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn new_slap<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         sentence: MessageId,
         enforcer: Enforcer,
-        reason: Option<String>,
+        reason: Option<&str>,
     ) -> Result<SlapReport> {
-        insert_raw_slap(
+        let (expires_at, created_at) = insert_raw_slap(
             conn,
-            to_i64(sentence),
-            to_i64(self.0),
-            to_i64(self.1),
-            enforcer.clone(),
-            //try and remove this clone. Consider making stringify_option more generic for that
-            reason.clone(),
+            try_to_i64(sentence)?,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+            &enforcer,
+            reason,
+            None,
         )
         .await?;
         Ok(SlapReport {
             sentence,
             offender: self.1,
             enforcer,
+            reason: reason.map(str::to_owned),
+            pardoned: None,
+            expires_at,
+            notes: None,
+            created_at,
+        })
+    }
+
+    ///Adds a temporary slap entry for this member
+    ///
+    /// Works like [`Self::new_slap`] but the slap expires `duration` from now, see
+    /// [`GuildSlapRecord::expunge_expired_slaps`]. `duration` is rounded down to the millisecond.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `duration` isn't positive or `enforcer` is a manager with the
+    /// ID 0 and [`SlapError::ReasonTooLong`] if `reason` is over [`MAX_REASON_LENGTH`] characters.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, reason), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn new_slap_expiring<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        sentence: MessageId,
+        enforcer: Enforcer,
+        reason: Option<&str>,
+        duration: Duration,
+    ) -> Result<SlapReport> {
+        if duration <= Duration::zero() {
+            return Err(AdapterError::InvalidArgument {
+                name: "duration",
+                reason: "must be positive",
+            });
+        }
+        let (expires_at, created_at) = insert_raw_slap(
+            conn,
+            try_to_i64(sentence)?,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+            &enforcer,
             reason,
+            Some(duration),
+        )
+        .await?;
+        Ok(SlapReport {
+            sentence,
+            offender: self.1,
+            enforcer,
+            reason: reason.map(str::to_owned),
+            pardoned: None,
+            expires_at,
+            notes: None,
+            created_at,
         })
     }
 
-    ///A stream over all of the member's slaps
+    ///A stream over all of the member's slaps, pardoned ones excluded
     pub fn slaps<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
         &'a self,
         conn: PgExec,
     ) -> impl Stream<Item = Result<SlapReport>> + 'a {
-        let offender = to_i64(self.1);
-        query!(
-            "SELECT sentence, enforcer, reason FROM slaps WHERE guild=$1 AND offender=$2",
-            to_i64(self.0),
-            offender
-        )
-        .fetch(conn)
-        .map_err(|e| AdapterError::from(e))
-        .map(move |res| {
-            res.map(|record| SlapReport {
-                sentence: MessageId(from_i64(record.sentence)),
-                offender: self.1,
-                enforcer: match record.enforcer {
-                    Some(user) => Enforcer::Manager(UserId(from_i64(user))),
-                    None => Enforcer::Community,
-                },
-                reason: record.reason,
-            })
-        })
+        let ids = try_to_i64(self.0).and_then(|guild| Ok((guild, try_to_i64(self.1)?)));
+        stream_or_error(ids.map(move |(guild, offender)| {
+            traced!(query_as!(
+                RawSlap,
+                "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL",
+                guild,
+                offender
+            ))
+            .fetch(conn)
+            .map_err(query_failed("member_slaps.slaps", Some(self.0)))
+            .map(|res| res.and_then(RawSlap::into_report))
+        }))
     }
 
-    ///The number of slaps of the member
+    ///A stream over the member's active slaps: neither pardoned nor expired
+    ///
+    /// Unlike [`Self::slaps`] this doesn't wait for [`GuildSlapRecord::expunge_expired_slaps`] to ignore expired
+    /// slaps.
+    pub fn active_slaps<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> impl Stream<Item = Result<SlapReport>> + 'a {
+        let ids = try_to_i64(self.0).and_then(|guild| Ok((guild, try_to_i64(self.1)?)));
+        stream_or_error(ids.map(move |(guild, offender)| {
+            traced!(query_as!(
+                RawSlap,
+                "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL AND (expires_at IS NULL OR expires_at > now())",
+                guild,
+                offender
+            ))
+            .fetch(conn)
+            .map_err(query_failed("member_slaps.active_slaps", Some(self.0)))
+            .map(|res| res.and_then(RawSlap::into_report))
+        }))
+    }
+
+    ///A stream over all of the member's slaps, pardoned ones included
+    pub fn slaps_including_pardoned<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> impl Stream<Item = Result<SlapReport>> + 'a {
+        let ids = try_to_i64(self.0).and_then(|guild| Ok((guild, try_to_i64(self.1)?)));
+        stream_or_error(ids.map(move |(guild, offender)| {
+            traced!(query_as!(
+                RawSlap,
+                "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2",
+                guild,
+                offender
+            ))
+            .fetch(conn)
+            .map_err(query_failed("member_slaps.slaps_including_pardoned", Some(self.0)))
+            .map(|res| res.and_then(RawSlap::into_report))
+        }))
+    }
+
+    ///The `n` latest slaps of the member, pardoned ones excluded
+    ///
+    /// Slaps are ordered from the newest to the oldest.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn recent<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        n: u32,
+    ) -> Result<Vec<SlapReport>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL ORDER BY sentence DESC LIMIT $3",
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+            i64::from(n)
+        ))
+        .fetch_all(conn)
+        .timed("member_slaps.recent", Some(self.0))
+        .await?
+        .into_iter()
+        .map(RawSlap::into_report)
+        .collect()
+    }
+
+    ///The latest slap of the member, pardoned ones excluded, [`None`] if there is none
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn latest<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<Option<SlapReport>> {
+        Ok(self.recent(conn, 1).await?.pop())
+    }
+
+    ///The oldest slap of the member, pardoned ones excluded, [`None`] if there is none
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn oldest<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<Option<SlapReport>> {
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL ORDER BY sentence LIMIT 1",
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?
+        ))
+        .fetch_optional(conn)
+        .timed("member_slaps.oldest", Some(self.0))
+        .await?
+        .map(RawSlap::into_report)
+        .transpose()
+    }
+
+    ///The `n` latest slaps of the member by creation date, pardoned ones excluded
+    ///
+    /// Slaps are ordered from the newest to the oldest. [`Self::recent`] orders them by sentence instead.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `n` isn't positive, in which case the query is not made.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn last_n_slaps<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        n: i64,
+    ) -> Result<Vec<SlapReport>> {
+        if n <= 0 {
+            return Err(AdapterError::InvalidArgument {
+                name: "n",
+                reason: "must be positive",
+            });
+        }
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL ORDER BY created_at DESC, sentence DESC LIMIT $3",
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+            n
+        ))
+        .fetch_all(conn)
+        .timed("member_slaps.last_n_slaps", Some(self.0))
+        .await?
+        .into_iter()
+        .map(RawSlap::into_report)
+        .collect()
+    }
+
+    ///The number of slaps of the member, pardoned ones excluded
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
     pub async fn len<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<usize> {
-        Ok(query_scalar!(
+        Ok(traced!(query_scalar!(
+            r#"SELECT COUNT(sentence) as "count!" FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL"#,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+        ))
+        .fetch_one(conn)
+        .timed("member_slaps.len", Some(self.0))
+        .await? as usize)
+    }
+
+    ///The number of active slaps of the member, see [`Self::active_slaps`]
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn active_len<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        Ok(traced!(query_scalar!(
+            r#"SELECT COUNT(sentence) as "count!" FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL AND (expires_at IS NULL OR expires_at > now())"#,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+        ))
+        .fetch_one(conn)
+        .timed("member_slaps.active_len", Some(self.0))
+        .await? as usize)
+    }
+
+    ///Deletes the member's slaps issued by [`Enforcer::Community`], returns how many were deleted
+    ///
+    /// Meant for amnesty votes. Unlike [`SlapReport::pardon`] the slaps are removed rather than flagged, slaps
+    /// issued by a manager or by the bot are kept.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn pardon_all_community_slaps<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        Ok(traced!(query!(
+            "DELETE FROM slaps WHERE guild=$1 AND offender=$2 AND enforcer IS NULL",
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?
+        ))
+        .execute(conn)
+        .timed("member_slaps.pardon_all_community_slaps", Some(self.0))
+        .await?
+        .rows_affected() as usize)
+    }
+
+    ///Compares the member's active slaps to the escalation threshold of the guild
+    ///
+    /// Lives here rather than on [`crate::guild::GuildConfig`] since it's meant to be called right after
+    /// slapping the member: if `exceeded` is `true` the bot should escalate. Only active slaps count (see
+    /// [`Self::active_len`]), those issued before `since` are ignored when it's given. The threshold and the count
+    /// are read in a single query. Returns `None` if the guild has no threshold, see
+    /// [`crate::guild::GuildConfig::set_slap_threshold`].
+    ///
+    /// # Error
+    /// Returns [`AdapterError::GuildNotFound`] if the guild has no configuration entry.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn threshold_exceeded<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<Option<ThresholdStatus>> {
+        let record = traced!(query!(
+            r#"SELECT slap_threshold, (SELECT COUNT(sentence) FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL AND (expires_at IS NULL OR expires_at > now()) AND ($3::timestamptz IS NULL OR created_at >= $3)) as "count!" FROM guilds WHERE id=$1"#,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+            since
+        ))
+        .fetch_one(conn)
+        .timed("member_slaps.threshold_exceeded", Some(self.0))
+        .await
+        .map_err(not_found_as(AdapterError::GuildNotFound(self.0)))?;
+        Ok(record.slap_threshold.map(|threshold| {
+            let count = record.count as usize;
+            // the column can only hold positive values
+            let threshold = threshold as u32;
+            ThresholdStatus {
+                count,
+                threshold,
+                exceeded: count >= threshold as usize,
+            }
+        }))
+    }
+
+    ///Counts the member's slaps by enforcer in a single query, pardoned ones excluded
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn slap_summary<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<MemberSlapSummary> {
+        // an enforcer of 0 is `Enforcer::Automatic`
+        let record = traced!(query!(
+            r#"SELECT COUNT(*) as "total!", COUNT(*) FILTER (WHERE enforcer IS NULL) as "community!", COUNT(*) FILTER (WHERE enforcer <> 0) as "manager!" FROM slaps WHERE guild=$1 AND offender=$2 AND pardoned_at IS NULL"#,
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?
+        ))
+        .fetch_one(conn)
+        .timed("member_slaps.slap_summary", Some(self.0))
+        .await?;
+        Ok(MemberSlapSummary {
+            total: record.total as usize,
+            community: record.community as usize,
+            manager: record.manager as usize,
+        })
+    }
+
+    ///The number of slaps of the member, pardoned ones included
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0, offender = %self.1)))]
+    pub async fn len_including_pardoned<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        Ok(traced!(query_scalar!(
             r#"SELECT COUNT(sentence) as "count!" FROM slaps WHERE guild=$1 AND offender=$2"#,
-            to_i64(self.0),
-            to_i64(self.1),
-        )
+            try_to_i64(self.0)?,
+            try_to_i64(self.1)?,
+        ))
         .fetch_one(conn)
+        .timed("member_slaps.len_including_pardoned", Some(self.0))
         .await? as usize)
     }
 }
@@ -206,72 +898,152 @@ pub struct GuildSlapRecord(pub GuildId);
 
 impl GuildSlapRecord {
     ///Adds a slap to the guild
-    pub async fn new_slap<
-        'a,
-        PgExec: Executor<'a, Database = Postgres> + Copy,
-        S: std::fmt::Display,
-    >(
+    ///
+    /// Works like [`MemberSlapRecord::new_slap`].
+    ///
+    /// # Error
+    /// Returns [`SlapError::ReasonTooLong`] if `reason` is over [`MAX_REASON_LENGTH`] characters and
+    /// [`AdapterError::InvalidArgument`] if `enforcer` is a manager with the ID 0.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, reason), fields(guild = %self.0)))]
+    pub async fn new_slap<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
         sentence: MessageId,
         offender: UserId,
         enforcer: Enforcer,
-        reason: Option<S>,
+        reason: Option<&str>,
     ) -> Result<SlapReport> {
-        let reason = reason.map(|s| s.to_string());
-        insert_raw_slap(
+        let (expires_at, created_at) = insert_raw_slap(
             conn,
-            to_i64(sentence),
-            to_i64(self.0),
-            to_i64(offender),
-            enforcer.clone(),
-            reason.clone(),
+            try_to_i64(sentence)?,
+            try_to_i64(self.0)?,
+            try_to_i64(offender)?,
+            &enforcer,
+            reason,
+            None,
         )
         .await?;
         Ok(SlapReport {
             sentence,
             offender,
             enforcer,
-            reason,
+            reason: reason.map(str::to_owned),
+            pardoned: None,
+            expires_at,
+            notes: None,
+            created_at,
         })
     }
 
-    ///Number of slaps in the guild
+    ///Number of slaps in the guild, pardoned ones included
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn len<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<usize> {
-        Ok(query_scalar!(
+        Ok(traced!(query_scalar!(
             // "count!" is to force non-null -> see sqlx::query! docs
             r#"SELECT COUNT(sentence) as "count!" FROM slaps WHERE guild=$1"#,
-            to_i64(self.0),
-        )
+            try_to_i64(self.0)?,
+        ))
         .fetch_one(conn)
+        .timed("guild_slaps.len", Some(self.0))
         .await? as usize)
     }
 
-    ///A stream over all slaps of the guild
+    ///A stream over all slaps of the guild, pardoned ones included
+    ///
+    /// Slaps are ordered from the oldest to the newest.
     pub fn slaps<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
         &'a self,
         conn: PgExec,
     ) -> impl Stream<Item = Result<SlapReport>> + 'a {
-        query!(
-            "SELECT sentence, offender, enforcer, reason FROM slaps WHERE guild=$1",
-            to_i64(self.0),
-        )
-        .fetch(conn)
-        .map_err(|e| AdapterError::from(e))
-        .map(move |res| {
-            res.map(|record| SlapReport {
-                sentence: MessageId(from_i64(record.sentence)),
-                offender: UserId(from_i64(record.offender)),
-                enforcer: match record.enforcer {
-                    Some(user) => Enforcer::Manager(UserId(from_i64(user))),
-                    None => Enforcer::Community,
-                },
-                reason: record.reason,
-            })
-        })
+        stream_or_error(try_to_i64(self.0).map(move |guild| {
+            traced!(query_as!(
+                RawSlap,
+                "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 ORDER BY created_at, sentence",
+                guild,
+            ))
+            .fetch(conn)
+            .map_err(query_failed("guild_slaps.slaps", Some(self.0)))
+            .map(|res| res.and_then(RawSlap::into_report))
+        }))
+    }
+
+    ///The `n` latest slaps of the guild, pardoned ones included
+    ///
+    /// Slaps are ordered from the newest to the oldest.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn recent<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        n: u32,
+    ) -> Result<Vec<SlapReport>> {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 ORDER BY sentence DESC LIMIT $2",
+            try_to_i64(self.0)?,
+            i64::from(n)
+        ))
+        .fetch_all(conn)
+        .timed("guild_slaps.recent", Some(self.0))
+        .await?
+        .into_iter()
+        .map(RawSlap::into_report)
+        .collect()
+    }
+
+    ///The latest slap of the guild, pardoned ones included, [`None`] if there is none
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn latest<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<Option<SlapReport>> {
+        Ok(self.recent(conn, 1).await?.pop())
+    }
+
+    ///The oldest slap of the guild, pardoned ones included, [`None`] if there is none
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn oldest<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<Option<SlapReport>> {
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 ORDER BY sentence LIMIT 1",
+            try_to_i64(self.0)?
+        ))
+        .fetch_optional(conn)
+        .timed("guild_slaps.oldest", Some(self.0))
+        .await?
+        .map(RawSlap::into_report)
+        .transpose()
+    }
+
+    /// Slaps issued on `date` (in UTC), pardoned ones included
+    ///
+    /// Slaps are ordered from the oldest to the newest.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn slaps_on_date<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        date: NaiveDate,
+    ) -> Result<Vec<SlapReport>> {
+        traced!(query_as!(
+            RawSlap,
+            "SELECT sentence, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at FROM slaps WHERE guild=$1 AND DATE(created_at AT TIME ZONE 'UTC') = $2 ORDER BY created_at, sentence",
+            try_to_i64(self.0)?,
+            date
+        ))
+        .fetch_all(conn)
+        .timed("guild_slaps.slaps_on_date", Some(self.0))
+        .await?
+        .into_iter()
+        .map(RawSlap::into_report)
+        .collect()
     }
 
     ///A stream over all members with a slap record
@@ -279,30 +1051,596 @@ impl GuildSlapRecord {
         &'a self,
         conn: PgExec,
     ) -> impl Stream<Item = Result<MemberSlapRecord>> + 'a {
-        query!(
-            "SELECT DISTINCT offender FROM slaps WHERE guild=$1",
-            to_i64(self.0)
-        )
-        .fetch(conn)
-        .map_err(|e| AdapterError::from(e))
-        .map(move |res| {
-            res.map(|record| MemberSlapRecord(self.0, UserId(from_i64(record.offender))))
-        })
+        stream_or_error(try_to_i64(self.0).map(move |guild| {
+            traced!(query!(
+                "SELECT DISTINCT offender FROM slaps WHERE guild=$1",
+                guild
+            ))
+            .fetch(conn)
+            .map_err(query_failed("guild_slaps.offenders", Some(self.0)))
+            .map(move |res| {
+                res.and_then(|record| Ok(MemberSlapRecord(self.0, try_from_i64(record.offender)?)))
+            })
+        }))
+    }
+
+    ///All members with a slap record, collected from [`Self::offenders`]
+    ///
+    /// Stops at the first error.
+    pub async fn collect_offenders<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> Result<Vec<MemberSlapRecord>> {
+        self.offenders(conn).try_collect().await
+    }
+
+    ///A stream over all members with a slap record along with their number of slaps, pardoned ones included
+    ///
+    /// Members are sorted from the most to the least slapped, ties by ID.
+    pub fn offenders_sorted_by_slap_count<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> impl Stream<Item = Result<(MemberSlapRecord, i64)>> + 'a {
+        stream_or_error(try_to_i64(self.0).map(move |guild| {
+            traced!(query!(
+                r#"SELECT offender, COUNT(*) AS "cnt!" FROM slaps WHERE guild=$1 GROUP BY offender ORDER BY "cnt!" DESC, offender"#,
+                guild
+            ))
+            .fetch(conn)
+            .map_err(query_failed("guild_slaps.offenders_sorted_by_slap_count", Some(self.0)))
+            .map(move |res| {
+                res.and_then(|record| {
+                    Ok((
+                        MemberSlapRecord(self.0, try_from_i64(record.offender)?),
+                        record.cnt,
+                    ))
+                })
+            })
+        }))
+    }
+
+    ///A stream over the members slapped since `since`, pardoned slaps included
+    pub fn active_members_with_slaps<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+        since: DateTime<Utc>,
+    ) -> impl Stream<Item = Result<MemberSlapRecord>> + 'a {
+        stream_or_error(try_to_i64(self.0).map(move |guild| {
+            traced!(query!(
+                "SELECT DISTINCT offender FROM slaps WHERE guild=$1 AND created_at >= $2",
+                guild,
+                since
+            ))
+            .fetch(conn)
+            .map_err(query_failed(
+                "guild_slaps.active_members_with_slaps",
+                Some(self.0),
+            ))
+            .map(move |res| {
+                res.and_then(|record| Ok(MemberSlapRecord(self.0, try_from_i64(record.offender)?)))
+            })
+        }))
     }
 
     ///Number of offending members in the guild
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
     pub async fn offender_count<'a, PgExec: Executor<'a, Database = Postgres>>(
         &self,
         conn: PgExec,
     ) -> Result<usize> {
-        Ok(query_scalar!(
+        Ok(traced!(query_scalar!(
             // "count!" is to force non-null -> see sqlx::query! docs
             r#"SELECT COUNT(DISTINCT offender) as "count!" FROM slaps WHERE guild=$1"#,
-            to_i64(self.0),
-        )
+            try_to_i64(self.0)?,
+        ))
+        .fetch_one(conn)
+        .timed("guild_slaps.offender_count", Some(self.0))
+        .await? as usize)
+    }
+
+    ///Number of members of the guild with at least one active slap
+    ///
+    /// Active slaps are neither pardoned nor expired, like for [`MemberSlapRecord::active_slaps`]. This is the
+    /// count to show in a moderation overview.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn active_offender_count<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        Ok(traced!(query_scalar!(
+            r#"SELECT COUNT(DISTINCT offender) as "count!" FROM slaps WHERE guild=$1 AND pardoned_at IS NULL AND (expires_at IS NULL OR expires_at > now())"#,
+            try_to_i64(self.0)?,
+        ))
+        .fetch_one(conn)
+        .timed("guild_slaps.active_offender_count", Some(self.0))
+        .await? as usize)
+    }
+
+    /// Number of slaps issued in the last 7 days, pardoned ones included
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn rolling_7day_count<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        self.rolling_count(conn, 7, "guild_slaps.rolling_7day_count")
+            .await
+    }
+
+    /// Number of slaps issued in the last 30 days, pardoned ones included
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn rolling_30day_count<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        self.rolling_count(conn, 30, "guild_slaps.rolling_30day_count")
+            .await
+    }
+
+    /// Average number of slaps issued per day over the last `days` days, pardoned ones included
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `days` is 0 or over [`i32::MAX`], in which case the query is
+    /// not made.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn slap_rate<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        days: u32,
+    ) -> Result<f64> {
+        let period = match i32::try_from(days) {
+            Ok(period) if period > 0 => period,
+            _ => {
+                return Err(AdapterError::InvalidArgument {
+                    name: "days",
+                    reason: "must be between 1 and i32::MAX",
+                })
+            }
+        };
+        Ok(self
+            .rolling_count(conn, period, "guild_slaps.slap_rate")
+            .await? as f64
+            / f64::from(days))
+    }
+
+    // `operation` is the public method the count is made for, queries are recorded under its name
+    async fn rolling_count<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        days: i32,
+        operation: &'static str,
+    ) -> Result<usize> {
+        Ok(traced!(query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM slaps WHERE guild=$1 AND created_at >= now() - make_interval(days => $2)"#,
+            try_to_i64(self.0)?,
+            days
+        ))
         .fetch_one(conn)
+        .timed(operation, Some(self.0))
         .await? as usize)
     }
+
+    /// Number of slaps issued in each `bucket` between `from` (included) and `to` (excluded), pardoned ones
+    /// included
+    ///
+    /// Each entry holds the start of its period and periods without slaps are counted as 0. The first period
+    /// starts before `from` unless `from` is aligned on a `bucket`, only the slaps issued since `from` are counted
+    /// though.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `to` isn't after `from`, in which case the query is not made.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn activity<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        bucket: ActivityBucket,
+    ) -> Result<Vec<(DateTime<Utc>, u64)>> {
+        if to <= from {
+            return Err(AdapterError::InvalidArgument {
+                name: "to",
+                reason: "must be after `from`",
+            });
+        }
+        Ok(traced!(query!(
+            r#"SELECT bucket AT TIME ZONE 'UTC' AS "start!", COUNT(slaps.sentence) AS "count!" FROM generate_series(date_trunc($3, $1::timestamptz AT TIME ZONE 'UTC'), $2::timestamptz AT TIME ZONE 'UTC', ('1 ' || $3)::interval) AS bucket LEFT JOIN slaps ON slaps.guild=$4 AND slaps.created_at >= $1 AND slaps.created_at < $2 AND date_trunc($3, slaps.created_at AT TIME ZONE 'UTC') = bucket WHERE bucket < $2::timestamptz AT TIME ZONE 'UTC' GROUP BY bucket ORDER BY bucket"#,
+            from,
+            to,
+            bucket.unit(),
+            try_to_i64(self.0)?
+        ))
+        .fetch_all(conn)
+        .timed("guild_slaps.activity", Some(self.0))
+        .await?
+        .into_iter()
+        // counts can't be negative
+        .map(|record| (record.start, record.count as u64))
+        .collect())
+    }
+
+    /// Slap statistics of the guild for `month` of `year`
+    ///
+    /// `month` starts at 1 for January. Months are delimited in UTC.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `month` isn't between 1 and 12 or if `year` is out of the
+    /// range supported by [`chrono`], in which case the query is not made.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn monthly_summary<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        year: i32,
+        month: u32,
+    ) -> Result<MonthlySlapSummary> {
+        if !(1..=12).contains(&month) {
+            return Err(AdapterError::InvalidArgument {
+                name: "month",
+                reason: "must be between 1 and 12",
+            });
+        }
+        let (next_year, next_month) = if month == 12 {
+            (year.checked_add(1), 1)
+        } else {
+            (Some(year), month + 1)
+        };
+        let first_day =
+            |year: i32, month: u32| Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single();
+        let (start, end) = match (
+            first_day(year, month),
+            next_year.and_then(|year| first_day(year, next_month)),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => {
+                return Err(AdapterError::InvalidArgument {
+                    name: "year",
+                    reason: "out of the supported range",
+                })
+            }
+        };
+        // an enforcer of 0 is `Enforcer::Automatic`
+        let record = traced!(query!(
+            r#"SELECT COUNT(*) as "total!", COUNT(*) FILTER (WHERE enforcer IS NULL) as "community!", COUNT(*) FILTER (WHERE enforcer <> 0) as "manager!", COUNT(DISTINCT offender) as "unique_offenders!" FROM slaps WHERE guild=$1 AND created_at >= $2 AND created_at < $3"#,
+            try_to_i64(self.0)?,
+            start,
+            end
+        ))
+        .fetch_one(conn)
+        .timed("guild_slaps.monthly_summary", Some(self.0))
+        .await?;
+        Ok(MonthlySlapSummary {
+            total: record.total as usize,
+            community: record.community as usize,
+            manager: record.manager as usize,
+            unique_offenders: record.unique_offenders as usize,
+        })
+    }
+
+    /// Number of slaps issued on each day of the week, pardoned ones included
+    ///
+    /// Follows PostgreSQL's `DOW` convention: index 0 is Sunday, 1 is Monday and so on up to 6 for Saturday.
+    /// Days are computed in UTC.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn slap_heatmap_by_weekday<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<[usize; 7]> {
+        let mut heatmap = [0; 7];
+        for record in traced!(query!(
+            r#"SELECT EXTRACT(DOW FROM created_at AT TIME ZONE 'UTC')::int4 as "weekday!", COUNT(*) as "count!" FROM slaps WHERE guild=$1 GROUP BY 1 ORDER BY 1"#,
+            try_to_i64(self.0)?,
+        ))
+        .fetch_all(conn)
+        .timed("guild_slaps.slap_heatmap_by_weekday", Some(self.0))
+        .await?
+        {
+            heatmap[record.weekday as usize] = record.count as usize;
+        }
+        Ok(heatmap)
+    }
+
+    /// Deletes the guild's slaps issued before `cutoff`
+    ///
+    /// Returns the number of deleted slaps. Slaps issued exactly at `cutoff` are kept.
+    ///
+    /// # Error
+    /// To avoid wiping a guild's record by mistake `cutoff` can't be in the future. If it is
+    /// [`SlapError::CutoffInFuture`] is returned and nothing is deleted. Use [`Utc::now`] to delete every slap.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn prune_older_than<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+        cutoff: DateTime<Utc>,
+    ) -> Result<u64> {
+        check_cutoff(cutoff)?;
+        Ok(traced!(query!(
+            "DELETE FROM slaps WHERE guild=$1 AND created_at < $2",
+            try_to_i64(self.0)?,
+            cutoff
+        ))
+        .execute(conn)
+        .timed("guild_slaps.prune_older_than", Some(self.0))
+        .await?
+        .rows_affected())
+    }
+
+    /// Deletes the guild's expired slaps
+    ///
+    /// Returns the number of deleted slaps. Slaps without expiry are never deleted, see
+    /// [`MemberSlapRecord::new_slap_expiring`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn), fields(guild = %self.0)))]
+    pub async fn expunge_expired_slaps<'a, PgExec: Executor<'a, Database = Postgres>>(
+        &self,
+        conn: PgExec,
+    ) -> Result<usize> {
+        Ok(traced!(query!(
+            "DELETE FROM slaps WHERE guild=$1 AND expires_at IS NOT NULL AND expires_at < now()",
+            try_to_i64(self.0)?
+        ))
+        .execute(conn)
+        .timed("guild_slaps.expunge_expired_slaps", Some(self.0))
+        .await?
+        .rows_affected() as usize)
+    }
+}
+
+/// JSON exports, requires the `net` feature
+#[cfg(feature = "net")]
+impl GuildSlapRecord {
+    /// Exports the slaps of the guild as a JSON array of [`SlapReport`]s, pardoned ones included
+    ///
+    /// Slaps are sorted by sentence.
+    pub async fn export_as_json<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> Result<String> {
+        let mut slaps: Vec<SlapReport> = self.slaps(conn).try_collect().await?;
+        slaps.sort_by_key(|slap| slap.sentence);
+        Ok(serde_json::to_string(&slaps).map_err(std::io::Error::from)?)
+    }
+
+    /// Streams the slaps of the guild as a JSON array of [`SlapReport`]s, pardoned ones included
+    ///
+    /// Unlike [`Self::export_as_json`] the slaps aren't held in memory: the opening bracket is emitted right away
+    /// and then one chunk per slap as rows arrive, oldest first. Concatenated, the chunks form the
+    /// array.
+    ///
+    /// # Error
+    /// If fetching a slap fails the error is emitted and the stream ends, so the array is never closed and the
+    /// client fails to parse the body instead of receiving a truncated array.
+    pub fn export_json<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> impl Stream<Item = Result<String>> + 'a {
+        let mut first = true;
+        let elements = self.slaps(conn).map(move |slap| -> Result<String> {
+            let json = serde_json::to_string(&slap?).map_err(std::io::Error::from)?;
+            let separator = if first { "" } else { "," };
+            first = false;
+            Ok(format!("{}{}", separator, json))
+        });
+        let chunks = tokio_stream::once(Ok("[".to_string()))
+            .chain(elements)
+            .chain(tokio_stream::once(Ok("]".to_string())));
+        futures::StreamExt::scan(chunks, false, |failed, chunk| {
+            if *failed {
+                return futures::future::ready(None);
+            }
+            *failed = chunk.is_err();
+            futures::future::ready(Some(chunk))
+        })
+    }
+
+    /// Restores slaps exported by [`Self::export_as_json`] into the guild, returns how many were inserted
+    ///
+    /// The archive doesn't hold the guild, so every slap is added to this one. Slaps whose sentence is already
+    /// recorded, in this guild or another, are skipped. Slaps keep their creation date. They are inserted in a
+    /// single transaction so either all of them are imported or none is.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::InvalidArgument`] if `json` isn't an array of [`SlapReport`]s or holds a manager
+    /// with the ID 0 and [`SlapError::ReasonTooLong`] if a reason is over [`MAX_REASON_LENGTH`] characters.
+    /// Nothing is inserted then.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, json), fields(guild = %self.0)))]
+    pub async fn import_from_json<'a, A: Acquire<'a, Database = Postgres>>(
+        &self,
+        conn: A,
+        json: &str,
+    ) -> Result<usize> {
+        let slaps: Vec<SlapReport> =
+            serde_json::from_str(json).map_err(|_| AdapterError::InvalidArgument {
+                name: "json",
+                reason: "not an array of slap reports",
+            })?;
+        let guild = try_to_i64(self.0)?;
+        let mut rows = Vec::with_capacity(slaps.len());
+        for slap in slaps {
+            if let Some(reason) = &slap.reason {
+                if reason.chars().count() > MAX_REASON_LENGTH {
+                    return Err(SlapError::ReasonTooLong.into());
+                }
+            }
+            let (pardoned_by, pardoned_at) = match slap.pardoned {
+                Some(PardonInfo { by, at }) => (Some(try_to_i64(by)?), Some(at)),
+                None => (None, None),
+            };
+            rows.push(RawSlap {
+                sentence: try_to_i64(slap.sentence)?,
+                offender: try_to_i64(slap.offender)?,
+                enforcer: enforcer_to_option(slap.enforcer)?
+                    .map(try_to_i64)
+                    .transpose()?,
+                reason: slap.reason,
+                pardoned_by,
+                pardoned_at,
+                expires_at: slap.expires_at,
+                notes: slap.notes,
+                created_at: slap.created_at,
+            });
+        }
+
+        let mut tx = conn
+            .begin()
+            .await
+            .map_err(query_failed("guild_slaps.import_from_json", Some(self.0)))?;
+        let mut inserted = 0;
+        for row in rows {
+            inserted += traced!(query!(
+                "INSERT INTO slaps(sentence, guild, offender, enforcer, reason, pardoned_by, pardoned_at, expires_at, notes, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) ON CONFLICT DO NOTHING",
+                row.sentence,
+                guild,
+                row.offender,
+                row.enforcer,
+                @redact row.reason,
+                row.pardoned_by,
+                row.pardoned_at,
+                row.expires_at,
+                @redact row.notes,
+                row.created_at
+            ))
+            .execute(&mut tx)
+            .timed("guild_slaps.import_from_json", Some(self.0))
+            .await?
+            .rows_affected();
+        }
+        tx.commit()
+            .await
+            .map_err(query_failed("guild_slaps.import_from_json", Some(self.0)))?;
+        Ok(inserted as usize)
+    }
+}
+
+/// CSV exports, requires the `csv` feature
+///
+/// Slaps are exported with the headers `sentence,offender,enforcer,enforcer_id,reason,created_at`, oldest first.
+/// The enforcer is written as `Community`, `Manager` or `Automatic`, `enforcer_id` holds the manager's ID and is
+/// left empty otherwise. `reason` is left empty when the default reason was used and `created_at` is in RFC 3339
+/// format. Fields holding commas, quotes or line breaks are quoted as per RFC 4180. Pardons aren't exported.
+#[cfg(feature = "csv")]
+impl GuildSlapRecord {
+    /// Exports the slaps of the guild as a CSV string
+    ///
+    /// See [`Self::write_csv_to`] to avoid holding the whole export in memory.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::IoError`] if the CSV isn't valid UTF-8.
+    pub async fn export_csv<'a, PgExec: Executor<'a, Database = Postgres> + 'a>(
+        &'a self,
+        conn: PgExec,
+    ) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.write_csv_to(conn, &mut buffer).await?;
+        String::from_utf8(buffer)
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error).into())
+    }
+
+    /// Writes the slaps of the guild as CSV to `writer`, one row at a time
+    ///
+    /// Each record is written with its own call to `write_all`, wrap `writer` in a [`tokio::io::BufWriter`] if
+    /// writes are costly.
+    ///
+    /// # Error
+    /// Returns [`AdapterError::IoError`] if writing to `writer` fails. Rows written before the failure are not
+    /// rolled back.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, conn, writer), fields(guild = %self.0)))]
+    pub async fn write_csv_to<
+        'a,
+        PgExec: Executor<'a, Database = Postgres> + 'a,
+        W: AsyncWrite + Unpin,
+    >(
+        &'a self,
+        conn: PgExec,
+        mut writer: W,
+    ) -> Result<()> {
+        writer
+            .write_all(&csv_record(&[
+                "sentence",
+                "offender",
+                "enforcer",
+                "enforcer_id",
+                "reason",
+                "created_at",
+            ])?)
+            .await?;
+        let slaps = self.slaps(conn);
+        tokio::pin!(slaps);
+        while let Some(slap) = TryStreamExt::try_next(&mut slaps).await? {
+            let (enforcer, enforcer_id) = match slap.enforcer {
+                Enforcer::Community => ("Community", String::new()),
+                Enforcer::Manager(user) => ("Manager", user.0.to_string()),
+                Enforcer::Automatic => ("Automatic", String::new()),
+            };
+            let line = csv_record(&[
+                slap.sentence.0.to_string(),
+                slap.offender.0.to_string(),
+                enforcer.to_string(),
+                enforcer_id,
+                slap.reason.unwrap_or_default(),
+                slap.created_at.to_rfc3339(),
+            ])?;
+            writer.write_all(&line).await?;
+        }
+        writer.flush().await?;
+        Ok(())
+    }
+}
+
+// the `csv` crate only writes synchronously so each record is encoded in memory first
+#[cfg(feature = "csv")]
+fn csv_record<F: AsRef<[u8]>>(fields: &[F]) -> Result<Vec<u8>> {
+    let mut csv = csv::Writer::from_writer(Vec::new());
+    csv.write_record(fields).map_err(std::io::Error::from)?;
+    Ok(csv.into_inner().map_err(|error| error.into_error())?)
+}
+
+fn check_cutoff(cutoff: DateTime<Utc>) -> Result<()> {
+    if cutoff > Utc::now() {
+        return Err(SlapError::CutoffInFuture(cutoff).into());
+    }
+    Ok(())
+}
+
+/// Deletes the slaps of every guild issued before `cutoff`
+///
+/// Works like [`GuildSlapRecord::prune_older_than`] but slaps are deleted by batches of [`PRUNE_BATCH_SIZE`]
+/// so that the table isn't locked for too long. As such if an error occurs some slaps may already
+/// have been deleted. Within a transaction the locks are held until it ends so batching doesn't help.
+///
+/// # Error
+/// Same as [`GuildSlapRecord::prune_older_than`].
+pub fn prune_all_guilds<'a, A: Acquire<'a, Database = Postgres> + Send + 'a>(
+    conn: A,
+    cutoff: DateTime<Utc>,
+) -> impl Future<Output = Result<u64>> + Send + 'a {
+    // see `GuildConfig` for why the work is done by an inner async fn
+    prune_all_guilds_inner(conn, cutoff)
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(skip(conn)))]
+async fn prune_all_guilds_inner<'a, A: Acquire<'a, Database = Postgres>>(
+    conn: A,
+    cutoff: DateTime<Utc>,
+) -> Result<u64> {
+    check_cutoff(cutoff)?;
+    let mut conn = conn
+        .acquire()
+        .await
+        .map_err(query_failed("slap.prune_all_guilds", None))?;
+    let mut total = 0;
+    loop {
+        let deleted = traced!(query!(
+            "DELETE FROM slaps WHERE sentence IN (SELECT sentence FROM slaps WHERE created_at < $1 LIMIT $2)",
+            cutoff,
+            PRUNE_BATCH_SIZE
+        ))
+        .execute(&mut *conn)
+        .timed("slap.prune_all_guilds", None)
+        .await?
+        .rows_affected();
+        total += deleted;
+        if deleted < PRUNE_BATCH_SIZE as u64 {
+            return Ok(total);
+        }
+    }
 }
 
 impl From<GuildId> for GuildSlapRecord {
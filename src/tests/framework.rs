@@ -1,61 +1,11 @@
-// `db_session`, and `teardown_db` is based on https://github.com/almetica/almetica/blob/9d9688d3d1ddddae2594ed18fe78ac6b5718d1e7/src/model.rs#L375
-// licensed under AGPL 3.0 by almetica
-
 pub mod guild_test_info {
-    use serenity::model::id::{ChannelId, GuildId, RoleId};
-    //beware the types are do not exactly represent those expected by the end user of lib.
-    //they are such because of const restrictions and because it doesn't affect test quality
-    pub const FIRST_ID: GuildId = GuildId(5844);
-    pub const FIRST_WELCOME_MESSAGE: Option<&str> = Some("hello");
-    pub const FIRST_GOODBYE_MESSAGE: Option<String> = None;
-    pub const FIRST_ADVERTISE: bool = true;
-    pub const FIRST_ADMIN_CHAN: Option<ChannelId> = Some(ChannelId(87904));
-    pub const FIRST_POLL_CHANS: [ChannelId; 3] =
-        [ChannelId(2323), ChannelId(664), ChannelId(1212054)];
-    pub const FIRST_PRIV_MANAGER: [RoleId; 3] = [RoleId(22522), RoleId(44943544), RoleId(4444444)];
-    pub const FIRST_PRIV_ADMIN: [RoleId; 2] = [RoleId(22522), RoleId(44943544)];
-    pub const FIRST_PRIV_EVENT: [RoleId; 1] = [RoleId(48201365)];
-    pub const SECOND_ID: GuildId = GuildId(8750);
-
-    pub const SECOND_WELCOME_MESSAGE: Option<String> = None;
-    pub const SECOND_GOODBYE_MESSAGE: Option<&str> = Some("goodbye");
-    pub const SECOND_ADVERTISE: bool = false;
-    pub const SECOND_ADMIN_CHAN: Option<ChannelId> = None;
-    pub const SECOND_POLL_CHANS: [ChannelId; 3] =
-        [ChannelId(5406), ChannelId(254102), ChannelId(5455)];
-    pub const SECOND_PRIV_MANAGER: [RoleId; 3] = [RoleId(843934), RoleId(3504), RoleId(084304)];
-    pub const SECOND_PRIV_ADMIN: [RoleId; 2] = [RoleId(843934), RoleId(3504)];
-    pub const SECOND_PRIV_EVENT: [RoleId; 1] = [RoleId(984762)];
+    pub use crate::testing::fixtures::guild::*;
     #[allow(dead_code)] //for some reason it's always marked as unused, even when that's not the case
     pub const TOO_LONG: &str = "toolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolongtoolong";
 }
 
 pub mod slap_test_info {
-    use crate::slap::Enforcer;
-    use serenity::model::id::{GuildId, MessageId, UserId};
-    pub const FIRST_SENTENCE: MessageId = MessageId(6841381385);
-    pub const FIRST_GUILD: GuildId = super::guild_test_info::FIRST_ID;
-    pub const FIRST_OFFENDER: UserId = UserId(87038540);
-    pub const FIRST_ENFORCER: Enforcer = Enforcer::Manager(UserId(684308));
-    pub const FIRST_REASON: Option<&str> = Some("just because");
-
-    pub const SECOND_SENTENCE: MessageId = MessageId(878404);
-    pub const SECOND_GUILD: GuildId = super::guild_test_info::FIRST_ID;
-    pub const SECOND_OFFENDER: UserId = FIRST_OFFENDER;
-    pub const SECOND_ENFORCER: Enforcer = Enforcer::Community;
-    pub const SECOND_REASON: Option<&str> = None;
-
-    pub const THIRD_SENTENCE: MessageId = MessageId(987698473);
-    pub const THIRD_GUILD: GuildId = super::guild_test_info::SECOND_ID;
-    pub const THIRD_OFFENDER: UserId = UserId(454);
-    pub const THIRD_ENFORCER: Enforcer = Enforcer::Manager(UserId(4543453));
-    pub const THIRD_REASON: Option<&str> = None;
-
-    pub const FOURTH_SENTENCE: MessageId = MessageId(356489);
-    pub const FOURTH_GUILD: GuildId = super::guild_test_info::FIRST_ID;
-    pub const FOURTH_OFFENDER: UserId = THIRD_OFFENDER;
-    pub const FOURTH_ENFORCER: Enforcer = Enforcer::Manager(UserId(4543453));
-    pub const FOURTH_REASON: Option<&str> = None;
+    pub use crate::testing::fixtures::slap::*;
 
     #[allow(unused_macros)]
     macro_rules! assemble_from_test {
@@ -67,6 +17,10 @@ pub mod slap_test_info {
                 offender: paste! {[<$row _OFFENDER>]},
                 enforcer: paste! {[<$row _ENFORCER>]},
                 reason: paste! {[<$row _REASON>]}.map(|some| some.to_string()),
+                pardoned: None,
+                expires_at: None,
+                notes: None,
+                created_at: crate::testing::fixtures::slap::created_at(),
             }
         }};
     }
@@ -74,182 +28,98 @@ pub mod slap_test_info {
     pub(crate) use assemble_from_test;
 }
 
-// WE don't use the `query!` macro because it only looks up the `DATABASE_URL` env var
-// when tests should rather use `TEST_DB_URL`
+// Each test gets its own database, see `crate::testing`. The database is dropped by `TestDb`'s `Drop` if the
+// test panics.
 #[macro_use]
 pub mod db_test_interface {
-    use std::env;
-    use std::panic;
-
-    use dotenv::dotenv;
-    use paste::paste;
-    use rand::{thread_rng, Rng};
-    use sqlx::{migrate, Connection, PgConnection, Result};
+    use crate::testing::TestDb;
+    use sqlx::Result;
     use tokio::runtime::Runtime;
 
+    // for tests that can't be async, such as those of the blocking API
+    #[allow(dead_code)]
     pub fn db_session<F>(test: F) -> Result<()>
     where
-        //we only pass the url instead of the connection because creating it requires async
-        F: FnOnce(&str, Runtime) -> Result<()> + panic::UnwindSafe,
+        F: FnOnce(&str) -> Result<()>,
     {
-        dotenv().ok();
-        let base_url = env::var("TEST_DB_URL").expect("TEST_DB_URL was not set");
-
-        // TODO: ugly! try to find a better way to do the following -> #[tokio::test] does the same
-        let this_runtime = Runtime::new().unwrap();
-        let db_name = this_runtime.block_on(async { db_setup(&base_url).await })?;
-
-        let result = panic::catch_unwind(|| {
-            let db_url = format!("{}/{}", base_url, db_name);
-            //TODO: find a way to already execute `test` in an async closure (unwind bound complains)
-            if let Err(e) = test(&db_url, Runtime::new().unwrap()) {
-                panic!("Error occured while executing test: {:?}", e)
+        let runtime = Runtime::new().unwrap();
+        let db = runtime
+            .block_on(TestDb::new())
+            .expect("could not set up the test database");
+        let result = test(db.url());
+        runtime
+            .block_on(db.close())
+            .expect("could not drop the test database");
+        result
+    }
+
+    #[macro_export]
+    macro_rules! db_test {
+        ($(#[$attr:meta])* async fn $name:ident $($tt:tt)*) => {
+            #[tokio::test]
+            $(#[$attr])*
+            async fn $name() -> Result<()> {
+                async fn inner $($tt)*
+                let db = $crate::testing::TestDb::new()
+                    .await
+                    .expect("could not set up the test database");
+                let result = inner(db.pool().clone()).await;
+                db.close().await.expect("could not drop the test database");
+                result
             }
-        });
+        }
+    }
 
-        this_runtime.block_on(async { teardown_db(&base_url, &db_name).await })?;
+    #[allow(unused_imports)]
+    pub(crate) use db_test;
+}
 
-        assert!(result.is_ok());
+// `tracing-subscriber` would do but this is all the tests need
+#[cfg(feature = "tracing")]
+pub mod span_recorder {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
 
-        Ok(())
-    }
+    /// Name and fields of a span, field values are formatted with `Debug`
+    pub type RecordedSpan = (String, Vec<(String, String)>);
 
-    //we create a db to only for one test
-    async fn db_setup(base_url: &str) -> Result<String> {
-        //not a truly random name but chances are slim that two identical names will be generated
-        let mut db_name = String::from("botanist_test_");
-        let random_id: u128 = thread_rng().gen();
-        db_name.push_str(random_id.to_string().as_str());
-
-        let mut default_conn = PgConnection::connect(&base_url).await?;
-        // TODO: investigave why using the `query!` macro would not compile
-        sqlx::query(&format!("CREATE DATABASE {}", db_name))
-            //Executor is only impl for &mut Connection
-            .execute(&mut default_conn)
-            .await?;
-
-        //we don't want to continue on the default DB
-        let db_url = format!("{}/{}", base_url, db_name);
-        let mut new_conn = PgConnection::connect(&db_url).await?;
-        apply_migrations(&mut new_conn).await?;
-        insert_dummy(new_conn).await?;
-
-        Ok(db_name)
-    }
+    /// Subscriber keeping every span created while it is the default
+    #[derive(Clone, Default)]
+    pub struct SpanRecorder(pub Arc<Mutex<Vec<RecordedSpan>>>);
 
-    ///we drop the db after testing
-    async fn teardown_db(base_url: &str, name: &str) -> Result<()> {
-        let mut conn = PgConnection::connect(&base_url).await?;
-
-        sqlx::query(&format!("DROP DATABASE {};", name))
-            .execute(&mut conn)
-            .await?;
-
-        // Drop all other connections to the database -> is this really necessary?
-        sqlx::query(
-            format!(
-                r#"SELECT pg_terminate_backend(pg_stat_activity.pid)
-                           FROM pg_stat_activity
-                           WHERE datname = '{}'
-                           AND pid <> pg_backend_pid();"#,
-                name
-            )
-            .as_ref(),
-        )
-        .execute(&mut conn)
-        .await?;
-        conn.close().await?;
-
-        Ok(())
-    }
+    struct FieldVisitor(Vec<(String, String)>);
 
-    /// we apply all the migrations from `migration` to our test DB
-    async fn apply_migrations(conn: &mut PgConnection) -> Result<()> {
-        migrate!("./migrations").run(conn).await?;
-        Ok(())
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0
+                .push((field.name().to_string(), format!("{:?}", value)));
+        }
     }
 
-    // TODO: find how to return a literal instead of a String
-    macro_rules! prepare_guild_row {
-        ($row:literal) => {{
-            use super::guild_test_info::*;
-            use crate::stringify_option;
-            format!("INSERT INTO guilds(id, welcome_message, goodbye_message, advertise, admin_chan, poll_chans, priv_admin, priv_manager, priv_event) VALUES ({}, {}, {}, {}, {}, array[{}, {}, {}], array[{}, {}], array[{}, {}, {}], array[{}])",
-            paste! {[<$row _ID>]},
-            paste!{stringify_option([<$row _WELCOME_MESSAGE>])},
-            paste!{stringify_option([<$row _GOODBYE_MESSAGE>])},
-            paste!{[<$row _ADVERTISE>]},
-            paste!{stringify_option([<$row _ADMIN_CHAN>])},
-            paste!{[<$row _POLL_CHANS>][0]},
-            paste!{[<$row _POLL_CHANS>][1]},
-            paste!{[<$row _POLL_CHANS>][2]},
-            paste!{[<$row _PRIV_ADMIN>][0]},
-            paste!{[<$row _PRIV_ADMIN>][1]},
-            paste!{[<$row _PRIV_MANAGER>][0]},
-            paste!{[<$row _PRIV_MANAGER>][1]},
-            paste!{[<$row _PRIV_MANAGER>][2]},
-            paste!{[<$row _PRIV_EVENT>][0]}
-        )
-        }};
-    }
+    impl Subscriber for SpanRecorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
 
-    macro_rules! prepare_slap_row {
-        ($row:literal) => {{
-            use super::slap_test_info::*;
-            use crate::{stringify_option, slap::enforcer_to_option};
-            format!("INSERT INTO slaps(sentence, guild, offender, enforcer, reason) VALUES ({}, {}, {}, {}, {})",
-            paste!{[<$row _SENTENCE>]},
-            paste!{[<$row _GUILD>]},
-            paste!{[<$row _OFFENDER>]},
-            paste!{stringify_option(enforcer_to_option([<$row _ENFORCER>]))},
-            paste!{stringify_option([<$row _REASON>])},
-    )
-        }};
-    }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut visitor = FieldVisitor(Vec::new());
+            span.record(&mut visitor);
+            let mut spans = self.0.lock().unwrap();
+            spans.push((span.metadata().name().to_string(), visitor.0));
+            Id::from_u64(spans.len() as u64)
+        }
 
-    /// inserts some dummy values into the dabase to allow tests to be relevant
-    async fn insert_dummy(mut conn: PgConnection) -> Result<()> {
-        //guild mock data
-        sqlx::query(&prepare_guild_row!("FIRST"))
-            .execute(&mut conn)
-            .await?;
-        sqlx::query(&prepare_guild_row!("SECOND"))
-            .execute(&mut conn)
-            .await?;
-
-        //slap mock data
-        sqlx::query(&prepare_slap_row!("FIRST"))
-            .execute(&mut conn)
-            .await?;
-        sqlx::query(&prepare_slap_row!("SECOND"))
-            .execute(&mut conn)
-            .await?;
-        sqlx::query(&prepare_slap_row!("THIRD"))
-            .execute(&mut conn)
-            .await?;
-        sqlx::query(&prepare_slap_row!("FOURTH"))
-            .execute(&mut conn)
-            .await?;
-
-        Ok(())
-    }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
 
-    #[macro_export]
-    macro_rules! db_test {
-        (async fn $name:ident $($tt:tt)*) => {
-            #[test]
-            fn $name() -> Result<()> {
-                async fn inner $($tt)*
-                db_session(|db_url, runtime| {
-                    runtime.block_on(async {
-                        let pool = sqlx::PgPool::connect(&db_url).await?;
-                        inner(pool).await
-                    })
-                })
-            }
-        }
-    }
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
 
-    #[allow(unused_imports)]
-    pub(crate) use db_test;
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
 }
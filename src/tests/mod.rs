@@ -1,3 +1,14 @@
 mod framework;
+#[cfg(feature = "blocking")]
+mod test_blocking;
+mod test_connection;
 mod test_guild;
+mod test_handle;
+mod test_lib;
+mod test_metrics;
+#[cfg(feature = "net")]
+mod test_net;
+mod test_prelude;
 mod test_slap;
+mod test_testing;
+mod test_utils;
@@ -0,0 +1,63 @@
+use super::framework::{
+    db_test_interface::db_session,
+    guild_test_info::{FIRST_ADVERTISE, FIRST_ID, FIRST_PRIV_EVENT},
+    slap_test_info::{FIRST_SENTENCE, FOURTH_SENTENCE, SECOND_SENTENCE},
+};
+use crate::blocking::BlockingPool;
+use crate::guild::Privilege;
+use crate::id::{MessageId, UserId};
+use crate::slap::Enforcer;
+use crate::AdapterError;
+use sqlx::Result;
+
+#[test]
+fn blocking_guild_config() -> Result<()> {
+    db_session(|db_url| {
+        let db = BlockingPool::connect(db_url).unwrap();
+        let config = db.guild(FIRST_ID).require_exists().unwrap();
+        assert_eq!(config.get_advertise().unwrap(), FIRST_ADVERTISE);
+        config.set_advertise(!FIRST_ADVERTISE).unwrap();
+        assert_eq!(config.get_advertise().unwrap(), !FIRST_ADVERTISE);
+        assert_eq!(
+            config.get_roles_with(Privilege::Event).unwrap(),
+            FIRST_PRIV_EVENT.to_vec()
+        );
+        assert!(!db.guild(4200.into()).exists().unwrap());
+        db.close().unwrap();
+        Ok(())
+    })
+}
+
+#[test]
+fn blocking_guild_slap_record() -> Result<()> {
+    db_session(|db_url| {
+        let db = BlockingPool::connect(db_url).unwrap();
+        let record = db.slaps(FIRST_ID);
+        let mut sentences: Vec<MessageId> = record
+            .slaps()
+            .unwrap()
+            .into_iter()
+            .map(|slap| slap.sentence)
+            .collect();
+        sentences.sort();
+        assert_eq!(
+            sentences,
+            vec![FOURTH_SENTENCE, SECOND_SENTENCE, FIRST_SENTENCE]
+        );
+        assert_eq!(record.offenders().unwrap().len(), 2);
+
+        record
+            .new_slap(MessageId(1234), UserId(5678), Enforcer::Community, None)
+            .unwrap();
+        assert_eq!(record.len().unwrap(), 4);
+        Ok(())
+    })
+}
+
+#[tokio::test]
+async fn blocking_in_async_context() {
+    match BlockingPool::connect("postgres://localhost/botanist") {
+        Err(AdapterError::BlockingInAsyncContext) => (),
+        other => panic!("expected BlockingInAsyncContext, got {:?}", other),
+    }
+}
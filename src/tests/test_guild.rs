@@ -1,20 +1,25 @@
-use super::framework::{
-    db_test_interface::{db_session, db_test},
-    guild_test_info::*,
-};
+use super::framework::{db_test_interface::db_test, guild_test_info::*};
+use crate::id::{ChannelId, GuildId, RoleId};
 use crate::{
-    guild::{GuildConfig, GuildConfigBuilder, GuildConfigError, Privilege},
+    guild::{
+        get_all_guild_ids, CompletionCriteria, EffectivePrivileges, GoodbyeMessage, GuildConfig,
+        GuildConfigBuilder, GuildConfigData, GuildConfigError, MessageType, MissingField,
+        ParsePrivilegeError, Privilege, PrivilegeDiff, PrivilegeSet, PrivilegeSnapshot,
+        WelcomeMessage, DEFAULT_MAX_ROLES_PER_PRIVILEGE,
+    },
     AdapterError,
 };
 use macro_rules_attribute::apply;
-use serenity::model::id::{ChannelId, GuildId, RoleId};
 use sqlx::{PgPool, Result};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use tokio_stream::StreamExt;
 
 #[apply(db_test!)]
 async fn test_new(pool: PgPool) -> Result<()> {
     let id = 123456789.into();
 
-    let mut builder = GuildConfigBuilder::new(id);
+    let mut builder = GuildConfigBuilder::new(id).unwrap();
     let welcome = "Hello dear people";
     let goodbye = "So long my friend";
     builder
@@ -49,6 +54,127 @@ async fn test_new(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_validate_builder(pool: PgPool) -> Result<()> {
+    let id = GuildId(123456789);
+    let mut builder = GuildConfigBuilder::new(id).unwrap();
+    builder.welcome_message("hello").unwrap();
+    GuildConfig::validate_builder(&pool, &builder)
+        .await
+        .unwrap();
+    //nothing was written
+    assert!(!GuildConfig::from(id).exists(&pool).await.unwrap());
+
+    match GuildConfig::validate_builder(&pool, &GuildConfigBuilder::new(FIRST_ID).unwrap()).await {
+        Err(AdapterError::GuildError(GuildConfigError::AlreadyExists { id, existing })) => {
+            assert_eq!(id, FIRST_ID);
+            assert_eq!(existing.0, FIRST_ID);
+        }
+        other => panic!("expected an existing guild, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_new_already_exists(pool: PgPool) -> Result<()> {
+    match GuildConfig::new(&pool, GuildConfigBuilder::new(FIRST_ID).unwrap()).await {
+        Err(AdapterError::GuildError(GuildConfigError::AlreadyExists { id, existing })) => {
+            assert_eq!(id, FIRST_ID);
+            assert_eq!(existing.0, FIRST_ID);
+        }
+        _ => panic!(),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_new_zero_id() {
+    match GuildConfigBuilder::new(GuildId(0)) {
+        Err(AdapterError::GuildError(GuildConfigError::InvalidId(GuildId(0)))) => (),
+        _ => panic!(),
+    }
+}
+
+fn fields(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[apply(db_test!)]
+async fn test_new_from_fields(pool: PgPool) -> Result<()> {
+    let builder = GuildConfigBuilder::try_from(fields(&[
+        ("id", "123456789"),
+        ("welcome_message", "Hello dear people"),
+        ("advertise", "false"),
+        ("slap_threshold", "4"),
+    ]))
+    .unwrap();
+    let guild_config = GuildConfig::new(&pool, builder).await.unwrap();
+    assert_eq!(guild_config.0, GuildId(123456789));
+    assert_eq!(
+        guild_config.get_welcome_message(&pool).await.unwrap(),
+        Some("Hello dear people".to_string())
+    );
+    assert_eq!(guild_config.get_goodbye_message(&pool).await.unwrap(), None);
+    assert!(!guild_config.get_advertise(&pool).await.unwrap());
+    assert_eq!(
+        guild_config.get_slap_threshold(&pool).await.unwrap(),
+        Some(4)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_builder_from_invalid_fields() {
+    match GuildConfigBuilder::try_from(fields(&[("id", "5844"), ("prefix", "!")])) {
+        Err(AdapterError::GuildError(GuildConfigError::UnknownField(field))) => {
+            assert_eq!(field, "prefix")
+        }
+        _ => panic!(),
+    }
+    match GuildConfigBuilder::try_from(fields(&[("advertise", "true")])) {
+        Err(AdapterError::InvalidArgument { name: "id", .. }) => (),
+        _ => panic!(),
+    }
+    match GuildConfigBuilder::try_from(fields(&[("id", "5844"), ("advertise", "yes")])) {
+        Err(AdapterError::InvalidArgument {
+            name: "advertise", ..
+        }) => (),
+        _ => panic!(),
+    }
+    match GuildConfigBuilder::try_from(fields(&[("id", "5844"), ("max_roles_per_privilege", "0")]))
+    {
+        Err(AdapterError::InvalidArgument {
+            name: "max_roles_per_privilege",
+            ..
+        }) => (),
+        _ => panic!(),
+    }
+    match GuildConfigBuilder::try_from(fields(&[("id", "0")])) {
+        Err(AdapterError::GuildError(GuildConfigError::InvalidId(GuildId(0)))) => (),
+        _ => panic!(),
+    }
+    match GuildConfigBuilder::try_from(fields(&[("id", "5844"), ("goodbye_message", TOO_LONG)])) {
+        Err(AdapterError::GuildError(GuildConfigError::MessageTooLong { field })) => {
+            assert_eq!(field, "goodbye_message")
+        }
+        _ => panic!(),
+    }
+}
+
+#[test]
+fn test_try_new() {
+    assert_eq!(GuildConfig::try_new(FIRST_ID).unwrap().0, FIRST_ID);
+    match GuildConfig::try_new(GuildId(0)) {
+        Err(AdapterError::GuildError(GuildConfigError::InvalidId(GuildId(0)))) => (),
+        other => panic!("expected an invalid ID, got {:?}", other),
+    }
+    // `From` doesn't check the ID
+    assert_eq!(GuildConfig::from(GuildId(0)).0, GuildId(0));
+}
+
 #[apply(db_test!)]
 async fn test_exists(pool: PgPool) -> Result<()> {
     assert!(GuildConfig::from(FIRST_ID).exists(&pool).await.unwrap());
@@ -59,6 +185,268 @@ async fn test_exists(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_require_exists(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID)
+        .require_exists(&pool)
+        .await
+        .unwrap();
+    assert_eq!(guild_conf.0, FIRST_ID);
+    match GuildConfig::from(GuildId(572634589))
+        .require_exists(&pool)
+        .await
+    {
+        Err(AdapterError::GuildNotFound(GuildId(572634589))) => (),
+        _ => panic!(),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_exists_batch(pool: PgPool) -> Result<()> {
+    let missing = GuildId(572634589);
+    let found = GuildConfig::exists_batch(&pool, &[FIRST_ID, missing, SECOND_ID])
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 3);
+    assert!(found[&FIRST_ID]);
+    assert!(found[&SECOND_ID]);
+    assert!(!found[&missing]);
+    assert!(GuildConfig::exists_batch(&pool, &[])
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_get_many(pool: PgPool) -> Result<()> {
+    let missing = GuildId(572634589);
+    let configs = GuildConfig::get_many(&pool, &[SECOND_ID, missing, FIRST_ID, SECOND_ID])
+        .await
+        .unwrap();
+    assert_eq!(
+        configs,
+        vec![
+            GuildConfigData {
+                id: FIRST_ID,
+                welcome_message: FIRST_WELCOME_MESSAGE.map(str::to_string),
+                goodbye_message: FIRST_GOODBYE_MESSAGE,
+                advertise: FIRST_ADVERTISE,
+                admin_chan: FIRST_ADMIN_CHAN,
+                poll_chans: Some(FIRST_POLL_CHANS.to_vec()),
+                privileges: PrivilegeSnapshot {
+                    admin: FIRST_PRIV_ADMIN.to_vec(),
+                    manager: FIRST_PRIV_MANAGER.to_vec(),
+                    event: FIRST_PRIV_EVENT.to_vec(),
+                },
+                default_slap_expiry_days: None,
+                max_roles_per_privilege: DEFAULT_MAX_ROLES_PER_PRIVILEGE,
+                slap_threshold: None,
+            },
+            GuildConfigData {
+                id: SECOND_ID,
+                welcome_message: SECOND_WELCOME_MESSAGE,
+                goodbye_message: SECOND_GOODBYE_MESSAGE.map(str::to_string),
+                advertise: SECOND_ADVERTISE,
+                admin_chan: SECOND_ADMIN_CHAN,
+                poll_chans: Some(SECOND_POLL_CHANS.to_vec()),
+                privileges: PrivilegeSnapshot {
+                    admin: SECOND_PRIV_ADMIN.to_vec(),
+                    manager: SECOND_PRIV_MANAGER.to_vec(),
+                    event: SECOND_PRIV_EVENT.to_vec(),
+                },
+                default_slap_expiry_days: None,
+                max_roles_per_privilege: DEFAULT_MAX_ROLES_PER_PRIVILEGE,
+                slap_threshold: None,
+            },
+        ]
+    );
+    assert!(GuildConfig::get_many(&pool, &[missing])
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(GuildConfig::get_many(&pool, &[]).await.unwrap().is_empty());
+    Ok(())
+}
+
+// the threshold set from fields survives an export, a JSON round trip and an import
+#[apply(db_test!)]
+async fn test_slap_threshold_round_trip(pool: PgPool) -> Result<()> {
+    let builder =
+        GuildConfigBuilder::try_from(fields(&[("id", "123456789"), ("slap_threshold", "7")]))
+            .unwrap();
+    GuildConfig::new(&pool, builder).await.unwrap();
+    let exported = GuildConfig::get_many(&pool, &[GuildId(123456789)])
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(exported.slap_threshold, Some(7));
+    #[cfg(feature = "net")]
+    let exported = {
+        let json = serde_json::to_value(&exported).unwrap();
+        assert_eq!(json["slap_threshold"], 7);
+        serde_json::from_value::<GuildConfigData>(json).unwrap()
+    };
+    GuildConfig::from(FIRST_ID)
+        .import_from_snapshot(&pool, &exported)
+        .await
+        .unwrap();
+    assert_eq!(
+        GuildConfig::from(FIRST_ID)
+            .get_slap_threshold(&pool)
+            .await
+            .unwrap(),
+        Some(7)
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_import_from_snapshot(pool: PgPool) -> Result<()> {
+    let mut snapshot = GuildConfig::get_many(&pool, &[FIRST_ID])
+        .await
+        .unwrap()
+        .remove(0);
+    snapshot.default_slap_expiry_days = Some(30);
+    snapshot.max_roles_per_privilege = 5;
+    snapshot.slap_threshold = Some(3);
+    GuildConfig::from(SECOND_ID)
+        .import_from_snapshot(&pool, &snapshot)
+        .await
+        .unwrap();
+    let restored = GuildConfig::get_many(&pool, &[SECOND_ID])
+        .await
+        .unwrap()
+        .remove(0);
+    assert_eq!(
+        restored,
+        GuildConfigData {
+            id: SECOND_ID,
+            ..snapshot.clone()
+        }
+    );
+
+    match GuildConfig::from(GuildId(572634589))
+        .import_from_snapshot(&pool, &snapshot)
+        .await
+    {
+        Err(AdapterError::GuildNotFound(GuildId(572634589))) => (),
+        other => panic!("expected an unknown guild, got {:?}", other),
+    }
+
+    let mut invalid = snapshot.clone();
+    invalid.goodbye_message = Some("a".repeat(2001));
+    match GuildConfig::from(SECOND_ID)
+        .import_from_snapshot(&pool, &invalid)
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::MessageTooLong { field })) => {
+            assert_eq!(field, "goodbye_message")
+        }
+        other => panic!("expected a message too long, got {:?}", other),
+    }
+    let mut invalid = snapshot.clone();
+    invalid.max_roles_per_privilege = 2;
+    match GuildConfig::from(SECOND_ID)
+        .import_from_snapshot(&pool, &invalid)
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Manager,
+            max: 2,
+        })) => (),
+        other => panic!("expected too many roles, got {:?}", other),
+    }
+    let mut invalid = snapshot.clone();
+    invalid.slap_threshold = Some(0);
+    match GuildConfig::from(SECOND_ID)
+        .import_from_snapshot(&pool, &invalid)
+        .await
+    {
+        Err(AdapterError::InvalidArgument {
+            name: "slap_threshold",
+            ..
+        }) => (),
+        other => panic!("expected an invalid `slap_threshold`, got {:?}", other),
+    }
+    //nothing was written by the failed imports
+    assert_eq!(
+        GuildConfig::get_many(&pool, &[SECOND_ID]).await.unwrap(),
+        vec![restored]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_getters_unknown_guild(pool: PgPool) -> Result<()> {
+    let id = GuildId(572634589);
+    let guild_conf = GuildConfig::from(id);
+    let role = FIRST_PRIV_ADMIN[0];
+    macro_rules! assert_not_found {
+        ($call:expr) => {
+            match $call.await {
+                Err(AdapterError::GuildNotFound(missing)) => assert_eq!(missing, id),
+                other => panic!(
+                    "{} should fail with GuildNotFound, got {:?}",
+                    stringify!($call),
+                    other
+                ),
+            }
+        };
+    }
+    assert_not_found!(guild_conf.get_welcome_message(&pool));
+    assert_not_found!(guild_conf.get_goodbye_message(&pool));
+    assert_not_found!(guild_conf.get_advertise(&pool));
+    assert_not_found!(guild_conf.get_admin_chan(&pool));
+    assert_not_found!(guild_conf.get_default_slap_expiry_days(&pool));
+    assert_not_found!(guild_conf.get_slap_threshold(&pool));
+    assert_not_found!(guild_conf.get_max_roles_per_privilege(&pool));
+    assert_not_found!(guild_conf.get_roles_with(&pool, Privilege::Admin));
+    assert_not_found!(guild_conf.get_privilege_snapshot(&pool));
+    assert_not_found!(guild_conf.get_full_privilege_audit(&pool));
+    assert_not_found!(guild_conf.diff_privileges(&pool, &PrivilegeSnapshot::default()));
+    assert_not_found!(guild_conf.roles_with_any_privilege(&pool));
+    assert_not_found!(guild_conf.roles_matching_privilege_set(&pool, PrivilegeSet::default()));
+    assert_not_found!(guild_conf.privilege_count(&pool, Privilege::Event));
+    assert_not_found!(guild_conf.have_privilege(&pool, &[role], Privilege::Manager));
+    assert_not_found!(guild_conf.any_has_privilege(&pool, &[role], Privilege::Manager));
+    assert_not_found!(guild_conf.has_privilege(&pool, role, Privilege::Manager));
+    assert_not_found!(guild_conf.has_privileges(&pool, role, &[Privilege::Event]));
+    assert_not_found!(guild_conf.privilege_set_for(&pool, role));
+    assert_not_found!(guild_conf.get_privileges_for(&pool, role));
+    assert_not_found!(guild_conf.highest_privilege_of(&pool, &[role]));
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_get_all_guild_ids(pool: PgPool) -> Result<()> {
+    let mut expected = vec![FIRST_ID, SECOND_ID];
+    expected.sort();
+    assert_eq!(get_all_guild_ids(&pool).await.unwrap(), expected);
+    Ok(())
+}
+
+#[cfg(feature = "tracing")]
+#[apply(db_test!)]
+async fn test_get_advertise_span(pool: PgPool) -> Result<()> {
+    use super::framework::span_recorder::SpanRecorder;
+    let recorder = SpanRecorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    GuildConfig::from(FIRST_ID)
+        .get_advertise(&pool)
+        .await
+        .unwrap();
+    let guild_field = ("guild".to_string(), FIRST_ID.to_string());
+    assert!(recorder
+        .0
+        .lock()
+        .unwrap()
+        .iter()
+        .any(|(name, fields)| name == "get_advertise" && fields.contains(&guild_field)));
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_some_get_welcome_message(pool: PgPool) -> Result<()> {
     assert_eq!(
@@ -99,6 +487,25 @@ async fn test_set_welcome_message(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_set_message_with_quote(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    let message = "it's nice to have you here";
+    g_config
+        .set_goodbye_message(&pool, Some(message))
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config
+            .get_goodbye_message(&pool)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(message)
+    );
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_too_long_set_welcome_message(pool: PgPool) -> Result<()> {
     let g_config = GuildConfig::from(FIRST_ID);
@@ -108,6 +515,92 @@ async fn test_too_long_set_welcome_message(pool: PgPool) -> Result<()> {
     };
 }
 
+#[apply(db_test!)]
+async fn test_message_raw(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    g_config
+        .set_message_raw(&pool, MessageType::Goodbye, Some("raw goodbye"))
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config
+            .get_message_raw(&pool, MessageType::Goodbye)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("raw goodbye")
+    );
+    assert_eq!(
+        g_config
+            .get_goodbye_message(&pool)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("raw goodbye")
+    );
+    assert_eq!(
+        g_config
+            .get_message_raw(&pool, MessageType::Welcome)
+            .await
+            .unwrap(),
+        g_config.get_welcome_message(&pool).await.unwrap()
+    );
+
+    match g_config
+        .set_message_raw(&pool, MessageType::Welcome, Some(TOO_LONG))
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::MessageTooLong { field })) => {
+            assert_eq!(field, "welcome_message")
+        }
+        other => panic!("expected a message too long error, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_message_newtypes() {
+    assert_eq!(WelcomeMessage::new("hello").unwrap().as_str(), "hello");
+    match GoodbyeMessage::new(TOO_LONG) {
+        Err(AdapterError::GuildError(GuildConfigError::MessageTooLong { field })) => {
+            assert_eq!(field, "goodbye_message")
+        }
+        other => panic!("expected a message too long error, got {:?}", other),
+    }
+}
+
+#[apply(db_test!)]
+async fn test_set_message_text_kinds(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    let welcome = WelcomeMessage::new("checked welcome").unwrap();
+    g_config.set_welcome_message(&pool, &welcome).await.unwrap();
+    assert_eq!(
+        g_config
+            .get_welcome_message(&pool)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some(welcome.as_str())
+    );
+
+    g_config
+        .set_goodbye_message(&pool, "plain goodbye")
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config
+            .get_goodbye_message(&pool)
+            .await
+            .unwrap()
+            .as_deref(),
+        Some("plain goodbye")
+    );
+
+    g_config.set_welcome_message(&pool, None).await.unwrap();
+    assert_eq!(g_config.get_welcome_message(&pool).await.unwrap(), None);
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_get_advertise(pool: PgPool) -> Result<()> {
     assert_eq!(
@@ -124,7 +617,99 @@ async fn test_get_advertise(pool: PgPool) -> Result<()> {
 async fn test_set_advertise(pool: PgPool) -> Result<()> {
     let g_config = GuildConfig::from(FIRST_ID);
     g_config.set_advertise(&pool, false).await.unwrap();
-    assert_eq!(g_config.get_advertise(&pool).await.unwrap(), false);
+    assert!(!g_config.get_advertise(&pool).await.unwrap());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_default_slap_expiry_days(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    assert_eq!(
+        g_config.get_default_slap_expiry_days(&pool).await.unwrap(),
+        None
+    );
+    g_config
+        .set_default_slap_expiry_days(&pool, Some(30))
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config.get_default_slap_expiry_days(&pool).await.unwrap(),
+        Some(30)
+    );
+    for days in [0, u32::MAX] {
+        match g_config
+            .set_default_slap_expiry_days(&pool, Some(days))
+            .await
+        {
+            Err(AdapterError::InvalidArgument { name: "days", .. }) => (),
+            other => panic!("expected an invalid `days`, got {:?}", other),
+        }
+    }
+    g_config
+        .set_default_slap_expiry_days(&pool, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config.get_default_slap_expiry_days(&pool).await.unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_slap_threshold(pool: PgPool) -> Result<()> {
+    for id in [FIRST_ID, SECOND_ID] {
+        assert_eq!(
+            GuildConfig::from(id)
+                .get_slap_threshold(&pool)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+    let g_config = GuildConfig::from(FIRST_ID);
+    g_config.set_slap_threshold(&pool, Some(3)).await.unwrap();
+    assert_eq!(g_config.get_slap_threshold(&pool).await.unwrap(), Some(3));
+    for threshold in [0, u32::MAX] {
+        match g_config.set_slap_threshold(&pool, Some(threshold)).await {
+            Err(AdapterError::InvalidArgument {
+                name: "threshold", ..
+            }) => (),
+            other => panic!("expected an invalid `threshold`, got {:?}", other),
+        }
+    }
+    assert_eq!(g_config.get_slap_threshold(&pool).await.unwrap(), Some(3));
+    g_config.set_slap_threshold(&pool, None).await.unwrap();
+    assert_eq!(g_config.get_slap_threshold(&pool).await.unwrap(), None);
+    assert_eq!(
+        GuildConfig::from(SECOND_ID)
+            .get_slap_threshold(&pool)
+            .await
+            .unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_new_with_slap_threshold(pool: PgPool) -> Result<()> {
+    let mut builder = GuildConfigBuilder::new(GuildId(123456789)).unwrap();
+    builder.slap_threshold(5).unwrap();
+    let guild_config = GuildConfig::new(&pool, builder).await.unwrap();
+    assert_eq!(
+        guild_config.get_slap_threshold(&pool).await.unwrap(),
+        Some(5)
+    );
+    match GuildConfigBuilder::new(GuildId(123456789))
+        .unwrap()
+        .slap_threshold(0)
+    {
+        Err(AdapterError::InvalidArgument {
+            name: "slap_threshold",
+            ..
+        }) => (),
+        other => panic!("expected an invalid `slap_threshold`, got {:?}", other),
+    }
     Ok(())
 }
 
@@ -180,6 +765,195 @@ async fn test_get_roles_with(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_max_roles_per_privilege(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    assert_eq!(
+        g_config.get_max_roles_per_privilege(&pool).await.unwrap(),
+        DEFAULT_MAX_ROLES_PER_PRIVILEGE
+    );
+    g_config
+        .set_max_roles_per_privilege(&pool, FIRST_PRIV_MANAGER.len() as u32)
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config.get_max_roles_per_privilege(&pool).await.unwrap(),
+        FIRST_PRIV_MANAGER.len() as u32
+    );
+
+    // the constraint catches what the crate doesn't check itself
+    let too_low = FIRST_PRIV_MANAGER.len() as u32 - 1;
+    match g_config.set_max_roles_per_privilege(&pool, too_low).await {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Manager,
+            max,
+        })) => assert_eq!(max, too_low),
+        other => panic!("expected TooManyRoles, got {:?}", other),
+    }
+    match g_config.set_max_roles_per_privilege(&pool, 0).await {
+        Err(AdapterError::InvalidArgument { name: "max", .. }) => (),
+        other => panic!("expected InvalidArgument, got {:?}", other),
+    }
+    assert_eq!(
+        g_config.get_max_roles_per_privilege(&pool).await.unwrap(),
+        FIRST_PRIV_MANAGER.len() as u32
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_grant_privilege_over_cap(pool: PgPool) -> Result<()> {
+    let mut builder = GuildConfigBuilder::new(GuildId(123456789)).unwrap();
+    builder.max_roles_per_privilege(2).unwrap();
+    let g_config = GuildConfig::new(&pool, builder).await.unwrap();
+    for role in [RoleId(1), RoleId(2)] {
+        g_config
+            .grant_privilege(&pool, role, Privilege::Manager)
+            .await
+            .unwrap();
+    }
+    match g_config
+        .grant_privilege(&pool, RoleId(3), Privilege::Manager)
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Manager,
+            max: 2,
+        })) => (),
+        other => panic!("expected TooManyRoles, got {:?}", other),
+    }
+    // admin implies manager, which is full
+    match g_config
+        .grant_privilege(&pool, RoleId(3), Privilege::Admin)
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Manager,
+            max: 2,
+        })) => (),
+        other => panic!("expected TooManyRoles, got {:?}", other),
+    }
+    assert_eq!(
+        g_config.get_privilege_snapshot(&pool).await.unwrap(),
+        PrivilegeSnapshot {
+            admin: vec![],
+            manager: vec![RoleId(1), RoleId(2)],
+            event: vec![],
+        }
+    );
+
+    match g_config
+        .set_all_privileges(&pool, &[], &[], &[RoleId(4), RoleId(5), RoleId(6)])
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Event,
+            max: 2,
+        })) => (),
+        other => panic!("expected TooManyRoles, got {:?}", other),
+    }
+    assert_eq!(
+        g_config
+            .get_roles_with(&pool, Privilege::Manager)
+            .await
+            .unwrap(),
+        vec![RoleId(1), RoleId(2)]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_roles_with_any_privilege(pool: PgPool) -> Result<()> {
+    let mut roles = FIRST_PRIV_MANAGER.to_vec();
+    roles.extend_from_slice(&FIRST_PRIV_EVENT);
+    roles.sort();
+    assert_eq!(
+        GuildConfig::from(FIRST_ID)
+            .roles_with_any_privilege(&pool)
+            .await
+            .unwrap(),
+        roles
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_roles_matching_privilege_set(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    let sorted = |roles: &[RoleId]| {
+        let mut roles = roles.to_vec();
+        roles.sort();
+        roles
+    };
+    let admin_event: PrivilegeSet = vec![Privilege::Admin, Privilege::Event]
+        .into_iter()
+        .collect();
+    let cases = [
+        (
+            PrivilegeSet::default().insert(Privilege::Admin),
+            sorted(&FIRST_PRIV_ADMIN),
+        ),
+        (
+            PrivilegeSet::default().insert(Privilege::Manager),
+            sorted(&FIRST_PRIV_MANAGER),
+        ),
+        (admin_event, vec![]),
+    ];
+    for (required, roles) in cases {
+        assert_eq!(
+            g_config
+                .roles_matching_privilege_set(&pool, required)
+                .await
+                .unwrap(),
+            roles
+        );
+    }
+    assert_eq!(
+        g_config
+            .roles_matching_privilege_set(&pool, PrivilegeSet::default())
+            .await
+            .unwrap(),
+        g_config.roles_with_any_privilege(&pool).await.unwrap()
+    );
+
+    g_config
+        .grant_privilege(&pool, FIRST_PRIV_ADMIN[1], Privilege::Event)
+        .await
+        .unwrap();
+    assert_eq!(
+        g_config
+            .roles_matching_privilege_set(&pool, admin_event)
+            .await
+            .unwrap(),
+        vec![FIRST_PRIV_ADMIN[1]]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_privilege_count(pool: PgPool) -> Result<()> {
+    let g_config = GuildConfig::from(FIRST_ID);
+    assert_eq!(
+        g_config
+            .privilege_count(&pool, Privilege::Admin)
+            .await
+            .unwrap(),
+        FIRST_PRIV_ADMIN.len()
+    );
+
+    let empty = GuildConfig::new(&pool, GuildConfigBuilder::new(GuildId(123456789)).unwrap())
+        .await
+        .unwrap();
+    assert_eq!(
+        empty
+            .privilege_count(&pool, Privilege::Event)
+            .await
+            .unwrap(),
+        0
+    );
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_grant_admin_privilege(pool: PgPool) -> Result<()> {
     let guild_conf = GuildConfig::from(FIRST_ID);
@@ -215,6 +989,24 @@ async fn test_grant_any_privilege(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_grant_privilege_large_id(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let role = RoleId(i64::MAX as u64 - 1);
+    guild_conf
+        .grant_privilege(&pool, role, Privilege::Event)
+        .await
+        .unwrap();
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Event)
+            .await
+            .unwrap(),
+        [FIRST_PRIV_EVENT[0], role]
+    );
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_deny_admin_privilege(pool: PgPool) -> Result<()> {
     let guild_conf = GuildConfig::from(FIRST_ID);
@@ -229,6 +1021,254 @@ async fn test_deny_admin_privilege(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_transfer_admin_privilege(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let role = RoleId(1234567);
+    guild_conf
+        .transfer_privilege(&pool, FIRST_PRIV_ADMIN[0], role, Privilege::Admin)
+        .await
+        .unwrap();
+    assert!(guild_conf
+        .has_privileges(&pool, role, &[Privilege::Admin, Privilege::Manager])
+        .await
+        .unwrap());
+    assert!(guild_conf
+        .get_privileges_for(&pool, FIRST_PRIV_ADMIN[0])
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_transfer_privilege_to_holder(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    guild_conf
+        .transfer_privilege(
+            &pool,
+            FIRST_PRIV_MANAGER[2],
+            FIRST_PRIV_MANAGER[0],
+            Privilege::Manager,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Manager)
+            .await
+            .unwrap(),
+        FIRST_PRIV_MANAGER[..2]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_set_all_privileges(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let admin = [RoleId(1234567)];
+    let manager = [RoleId(1234567), RoleId(7654321)];
+    guild_conf
+        .set_all_privileges(&pool, &admin, &manager, &[])
+        .await
+        .unwrap();
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Admin)
+            .await
+            .unwrap(),
+        admin
+    );
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Manager)
+            .await
+            .unwrap(),
+        manager
+    );
+    assert!(guild_conf
+        .get_roles_with(&pool, Privilege::Event)
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_set_all_privileges_invariant(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let role = RoleId(1234567);
+    match guild_conf
+        .set_all_privileges(&pool, &[role], &FIRST_PRIV_MANAGER, &[])
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::PrivilegeInvariantViolation {
+            role: violating,
+        })) => assert_eq!(violating, role),
+        _ => panic!(),
+    };
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Admin)
+            .await
+            .unwrap(),
+        FIRST_PRIV_ADMIN
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_diff_privileges(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let current = guild_conf.get_privilege_snapshot(&pool).await.unwrap();
+    assert_eq!(
+        current,
+        PrivilegeSnapshot {
+            admin: FIRST_PRIV_ADMIN.to_vec(),
+            manager: FIRST_PRIV_MANAGER.to_vec(),
+            event: FIRST_PRIV_EVENT.to_vec(),
+        }
+    );
+    assert!(guild_conf
+        .diff_privileges(&pool, &current)
+        .await
+        .unwrap()
+        .is_empty());
+
+    let role = RoleId(1234567);
+    let new_snapshot = PrivilegeSnapshot {
+        event: vec![role],
+        ..current
+    };
+    let diff = guild_conf
+        .diff_privileges(&pool, &new_snapshot)
+        .await
+        .unwrap();
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[&Privilege::Event], [role]);
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[&Privilege::Event], FIRST_PRIV_EVENT);
+    Ok(())
+}
+
+#[test]
+fn test_privilege_snapshot_display() {
+    let snapshot = PrivilegeSnapshot {
+        admin: vec![RoleId(1)],
+        manager: vec![RoleId(1), RoleId(2)],
+        event: vec![],
+    };
+    assert_eq!(
+        snapshot.to_string(),
+        "Admin: [1]\nManager: [1, 2]\nEvent: []"
+    );
+}
+
+#[apply(db_test!)]
+async fn test_get_full_privilege_audit(pool: PgPool) -> Result<()> {
+    assert_eq!(
+        GuildConfig::from(SECOND_ID)
+            .get_full_privilege_audit(&pool)
+            .await
+            .unwrap(),
+        "Admin: [843934, 3504]\nManager: [843934, 3504, 84304]\nEvent: [984762]"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_privilege_diff_between() {
+    let old = PrivilegeSnapshot {
+        admin: vec![RoleId(1)],
+        manager: vec![RoleId(1), RoleId(2)],
+        event: vec![],
+    };
+    let new = PrivilegeSnapshot {
+        admin: vec![],
+        manager: vec![RoleId(2), RoleId(1), RoleId(3)],
+        event: vec![],
+    };
+    let diff = PrivilegeDiff::between(&old, &new);
+    assert_eq!(diff.added.get(&Privilege::Manager), Some(&vec![RoleId(3)]));
+    assert_eq!(diff.removed.get(&Privilege::Admin), Some(&vec![RoleId(1)]));
+    assert_eq!((diff.added.len(), diff.removed.len()), (1, 1));
+    assert!(PrivilegeDiff::between(&new, &new).is_empty());
+}
+
+#[apply(db_test!)]
+async fn test_transfer_missing_privilege(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    match guild_conf
+        .transfer_privilege(&pool, RoleId(1234567), RoleId(7654321), Privilege::Event)
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::RoleNoPrivilege { .. })) => (),
+        _ => panic!(),
+    };
+    assert_eq!(
+        guild_conf
+            .get_roles_with(&pool, Privilege::Event)
+            .await
+            .unwrap(),
+        FIRST_PRIV_EVENT
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_copy_privileges_from(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let role = RoleId(1234567);
+    guild_conf
+        .grant_privilege(&pool, role, Privilege::Event)
+        .await
+        .unwrap();
+    guild_conf
+        .copy_privileges_from(&pool, FIRST_PRIV_ADMIN[0], role)
+        .await
+        .unwrap();
+    assert_eq!(
+        guild_conf.get_privileges_for(&pool, role).await.unwrap(),
+        vec![Privilege::Admin, Privilege::Manager, Privilege::Event]
+    );
+    //event privilege was not granted twice
+    assert_eq!(
+        guild_conf
+            .privilege_count(&pool, Privilege::Event)
+            .await
+            .unwrap(),
+        FIRST_PRIV_EVENT.len() + 1
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_copy_privileges_from_at_cap(pool: PgPool) -> Result<()> {
+    let guild_conf = GuildConfig::from(FIRST_ID);
+    let max = FIRST_PRIV_MANAGER.len() as u32;
+    guild_conf
+        .set_max_roles_per_privilege(&pool, max)
+        .await
+        .unwrap();
+    match guild_conf
+        .copy_privileges_from(&pool, FIRST_PRIV_MANAGER[2], RoleId(1234567))
+        .await
+    {
+        Err(AdapterError::GuildError(GuildConfigError::TooManyRoles {
+            privilege: Privilege::Manager,
+            max: error_max,
+        })) => assert_eq!(error_max, max),
+        other => panic!("expected TooManyRoles, got {:?}", other),
+    }
+    // nothing was copied
+    assert!(guild_conf
+        .get_privileges_for(&pool, RoleId(1234567))
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_have_privilege(pool: PgPool) -> Result<()> {
     let guild_config = GuildConfig::from(FIRST_ID);
@@ -243,6 +1283,25 @@ async fn test_have_privilege(pool: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn test_any_has_privilege(pool: PgPool) -> Result<()> {
+    let guild_config = GuildConfig::from(FIRST_ID);
+    let member_roles = [RoleId(1234567), FIRST_PRIV_EVENT[0], FIRST_PRIV_ADMIN[1]];
+    assert!(guild_config
+        .any_has_privilege(&pool, &member_roles, Privilege::Admin)
+        .await
+        .unwrap());
+    assert!(!guild_config
+        .any_has_privilege(&pool, &member_roles[..2], Privilege::Manager)
+        .await
+        .unwrap());
+    assert!(!guild_config
+        .any_has_privilege(&pool, &[], Privilege::Event)
+        .await
+        .unwrap());
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn test_has_privilege(pool: PgPool) -> Result<()> {
     let guild_config = GuildConfig::from(FIRST_ID);
@@ -279,3 +1338,203 @@ async fn test_get_privileges_for(pool: PgPool) -> Result<()> {
     );
     Ok(())
 }
+
+#[apply(db_test!)]
+async fn test_privilege_set_for(pool: PgPool) -> Result<()> {
+    let guild_config = GuildConfig::from(FIRST_ID);
+    let cases = [
+        (
+            FIRST_PRIV_ADMIN[0],
+            vec![Privilege::Manager, Privilege::Admin],
+        ),
+        (FIRST_PRIV_MANAGER[2], vec![Privilege::Manager]),
+        (FIRST_PRIV_EVENT[0], vec![Privilege::Event]),
+        (RoleId(1234567), vec![]),
+    ];
+    for (role, privileges) in cases {
+        let set = guild_config.privilege_set_for(&pool, role).await.unwrap();
+        assert_eq!(set.iter().collect::<Vec<_>>(), privileges);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_privilege_display_round_trip() {
+    for privilege in Privilege::ALL {
+        let name = privilege.to_string();
+        assert_eq!(name.parse::<Privilege>(), Ok(privilege));
+        assert_eq!(name.to_uppercase().parse::<Privilege>(), Ok(privilege));
+        //database identifiers are not user-facing names
+        assert_ne!(name, privilege.as_ref());
+        assert!(privilege.as_ref().parse::<Privilege>().is_err());
+    }
+    let names = Privilege::ALL
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    assert_eq!(names, vec!["admin", "manager", "event"]);
+}
+
+#[test]
+fn test_privilege_from_str() {
+    let cases = [
+        ("Administrator", Privilege::Admin),
+        ("mod", Privilege::Manager),
+        ("MODERATOR", Privilege::Manager),
+        ("events", Privilege::Event),
+    ];
+    for (name, privilege) in cases {
+        assert_eq!(name.parse::<Privilege>(), Ok(privilege));
+    }
+    for name in ["", "owner", " admin", "priv_manager"] {
+        assert_eq!(
+            name.parse::<Privilege>(),
+            Err(ParsePrivilegeError(name.to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_privilege_implies() {
+    use Privilege::*;
+    let table = [
+        (Admin, Admin, true),
+        (Admin, Manager, true),
+        (Admin, Event, false),
+        (Manager, Admin, false),
+        (Manager, Manager, true),
+        (Manager, Event, false),
+        (Event, Admin, false),
+        (Event, Manager, false),
+        (Event, Event, true),
+    ];
+    for (privilege, other, implied) in table {
+        assert_eq!(
+            privilege.implies(other),
+            implied,
+            "{:?} implies {:?}",
+            privilege,
+            other
+        );
+    }
+    assert_eq!(Admin.implied().collect::<Vec<_>>(), vec![Admin, Manager]);
+    assert_eq!(Event.implied().collect::<Vec<_>>(), vec![Event]);
+    assert!(Admin > Manager);
+    assert!(Manager < Admin);
+    assert!(Manager <= Manager);
+    assert_eq!(Admin.partial_cmp(&Event), None);
+    assert_eq!(Event.partial_cmp(&Manager), None);
+}
+
+#[test]
+fn test_privilege_set() {
+    let set = PrivilegeSet::default();
+    assert!(set.is_empty());
+    let set = set.insert(Privilege::Event).insert(Privilege::Admin);
+    assert!(!set.is_empty());
+    assert!(set.contains(Privilege::Admin));
+    assert!(set.contains(Privilege::Event));
+    assert!(!set.contains(Privilege::Manager));
+    assert_eq!(set.insert(Privilege::Admin), set);
+    assert_eq!(
+        set.iter().collect::<Vec<_>>(),
+        vec![Privilege::Admin, Privilege::Event]
+    );
+    let set = set.remove(Privilege::Admin).remove(Privilege::Manager);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![Privilege::Event]);
+    assert!(set.remove(Privilege::Event).is_empty());
+    assert_eq!(
+        vec![Privilege::Manager, Privilege::Event]
+            .into_iter()
+            .collect::<PrivilegeSet>(),
+        PrivilegeSet::default()
+            .insert(Privilege::Event)
+            .insert(Privilege::Manager)
+    );
+}
+
+#[apply(db_test!)]
+async fn test_incomplete(pool: PgPool) -> Result<()> {
+    let incomplete = |criteria| {
+        GuildConfig::incomplete(&pool, criteria)
+            .map(|res| res.map(|(config, missing)| (config.0, missing)).unwrap())
+            .collect::<Vec<_>>()
+    };
+    let admin_chan = CompletionCriteria::default().insert(MissingField::AdminChan);
+    assert_eq!(
+        incomplete(admin_chan).await,
+        vec![(SECOND_ID, vec![MissingField::AdminChan])]
+    );
+    //both fixture guilds have privileges configured
+    assert!(incomplete(
+        admin_chan
+            .remove(MissingField::AdminChan)
+            .insert(MissingField::AnyPrivilege)
+    )
+    .await
+    .is_empty());
+    assert_eq!(
+        incomplete(CompletionCriteria::all()).await,
+        vec![
+            (FIRST_ID, vec![MissingField::GoodbyeMessage]),
+            (
+                SECOND_ID,
+                vec![MissingField::AdminChan, MissingField::WelcomeMessage]
+            ),
+        ]
+    );
+    assert!(incomplete(CompletionCriteria::default()).await.is_empty());
+
+    let id = GuildId(123456789);
+    GuildConfig::new(&pool, GuildConfigBuilder::new(id).unwrap())
+        .await
+        .unwrap();
+    let privilege = CompletionCriteria::default().insert(MissingField::AnyPrivilege);
+    assert_eq!(
+        incomplete(privilege).await,
+        vec![(id, vec![MissingField::AnyPrivilege])]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_highest_privilege_of(pool: PgPool) -> Result<()> {
+    let guild_config = GuildConfig::from(FIRST_ID);
+    let cases = [
+        (vec![FIRST_PRIV_ADMIN[0]], Some(Privilege::Admin)),
+        (vec![FIRST_PRIV_MANAGER[2]], Some(Privilege::Manager)),
+        (vec![FIRST_PRIV_EVENT[0]], Some(Privilege::Event)),
+        (vec![RoleId(1234567)], None),
+        (vec![], None),
+    ];
+    for (roles, highest) in cases {
+        assert_eq!(
+            guild_config
+                .highest_privilege_of(&pool, &roles)
+                .await
+                .unwrap()
+                .highest(),
+            highest
+        );
+    }
+    //event and manager are held through different roles, neither is lost
+    assert_eq!(
+        guild_config
+            .highest_privilege_of(
+                &pool,
+                &[FIRST_PRIV_EVENT[0], FIRST_PRIV_MANAGER[2], RoleId(1234567)]
+            )
+            .await
+            .unwrap(),
+        EffectivePrivileges {
+            admin: false,
+            manager: true,
+            event: true,
+        }
+    );
+    assert_eq!(
+        guild_config.highest_privilege_of(&pool, &[]).await.unwrap(),
+        EffectivePrivileges::default()
+    );
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use super::framework::{
+    db_test_interface::db_test,
+    guild_test_info::FIRST_ID,
+    slap_test_info::{FIRST_OFFENDER, FIRST_SENTENCE, SECOND_SENTENCE},
+};
+use crate::guild::GuildConfigBuilder;
+use crate::handle::BotanistDb;
+use crate::id::{GuildId, MessageId, UserId};
+use crate::slap::Enforcer;
+use macro_rules_attribute::apply;
+use sqlx::{PgPool, Result};
+use tokio_stream::StreamExt;
+
+#[apply(db_test!)]
+async fn handle_full_flow(pool: PgPool) -> Result<()> {
+    let db = BotanistDb::from(pool);
+    let guild_id = GuildId(6543);
+    let offender = UserId(4321);
+
+    let guild = db
+        .create_guild(GuildConfigBuilder::new(guild_id).unwrap())
+        .await
+        .unwrap();
+    assert!(guild.exists().await.unwrap());
+    guild.set_welcome_message(Some("welcome!")).await.unwrap();
+    assert_eq!(
+        guild.get_welcome_message().await.unwrap(),
+        Some("welcome!".to_string())
+    );
+
+    let report = db
+        .slaps(guild_id)
+        .new_slap(MessageId(1234), offender, Enforcer::Community, None)
+        .await
+        .unwrap();
+    assert_eq!(report.offender, offender);
+    assert_eq!(db.slaps(guild_id).len().await.unwrap(), 1);
+
+    let member = db.member_slaps(guild_id, offender);
+    member
+        .new_slap(MessageId(1235), Enforcer::Automatic, None)
+        .await
+        .unwrap();
+    assert_eq!(member.len().await.unwrap(), 2);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn handle_streams(pool: PgPool) -> Result<()> {
+    let db = BotanistDb::new(pool);
+    let member = db.member_slaps(FIRST_ID, FIRST_OFFENDER);
+    let mut sentences: Vec<MessageId> = member
+        .slaps()
+        .map(|slap| slap.unwrap().sentence)
+        .collect()
+        .await;
+    sentences.sort();
+    assert_eq!(sentences, vec![SECOND_SENTENCE, FIRST_SENTENCE]);
+
+    let offenders: Vec<_> = db.slaps(FIRST_ID).offenders().collect().await;
+    assert_eq!(offenders.len(), 2);
+    for offender in offenders {
+        let offender = offender.unwrap();
+        assert_eq!(offender.guild(), FIRST_ID);
+        assert!(offender.len().await.unwrap() > 0);
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn handle_close(pool: PgPool) -> Result<()> {
+    let db = BotanistDb::from(pool);
+    assert!(db.guild(FIRST_ID).exists().await.unwrap());
+    db.close(std::time::Duration::from_secs(5)).await.unwrap();
+    assert!(db.pool().is_closed());
+    assert!(db.guild(FIRST_ID).exists().await.is_err());
+    Ok(())
+}
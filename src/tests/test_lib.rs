@@ -0,0 +1,306 @@
+use super::framework::db_test_interface::db_test;
+use crate::guild::{GuildConfig, GuildConfigBuilder, GuildConfigError, Privilege};
+use crate::id::{GuildId, RoleId};
+use crate::slap::{prune_all_guilds, GuildSlapRecord};
+use crate::{
+    assert_schema_compatible, check_migrations, env_var, establish_connection_with,
+    pending_migrations, run_migrations, schema_version, shutdown, with_transaction, AdapterError,
+    EXPECTED_SCHEMA_VERSION, MIGRATOR,
+};
+use chrono::Utc;
+use macro_rules_attribute::apply;
+use sqlx::{PgPool, Result};
+use std::env::VarError;
+use std::error::Error;
+use std::time::Duration;
+
+#[apply(db_test!)]
+async fn test_run_migrations(pool: PgPool) -> Result<()> {
+    //the test DB is already migrated so this must be a no-op
+    run_migrations(&pool).await.unwrap();
+    assert!(check_migrations(&pool).await?);
+    assert_eq!(pending_migrations(&pool).await.unwrap(), 0);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_check_missing_migration(pool: PgPool) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version=(SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(&pool)
+    .await?;
+    assert!(!check_migrations(&pool).await?);
+    assert_eq!(pending_migrations(&pool).await.unwrap(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_expected_schema_version() {
+    let latest = MIGRATOR.iter().map(|migration| migration.version).max();
+    assert_eq!(
+        latest,
+        Some(EXPECTED_SCHEMA_VERSION),
+        "EXPECTED_SCHEMA_VERSION must match the embedded migrations"
+    );
+}
+
+#[apply(db_test!)]
+async fn test_schema_compatible(pool: PgPool) -> Result<()> {
+    assert_eq!(
+        schema_version(&pool).await.unwrap(),
+        EXPECTED_SCHEMA_VERSION
+    );
+    assert_schema_compatible(&pool).await.unwrap();
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_schema_behind(pool: PgPool) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version=(SELECT MAX(version) FROM _sqlx_migrations)",
+    )
+    .execute(&pool)
+    .await?;
+    let previous = MIGRATOR
+        .iter()
+        .map(|migration| migration.version)
+        .filter(|version| *version < EXPECTED_SCHEMA_VERSION)
+        .max()
+        .unwrap();
+    assert_eq!(schema_version(&pool).await.unwrap(), previous);
+    match assert_schema_compatible(&pool).await {
+        Err(AdapterError::SchemaMismatch { expected, found }) => {
+            assert_eq!(expected, EXPECTED_SCHEMA_VERSION);
+            assert_eq!(found, previous);
+        }
+        other => panic!("expected a schema mismatch, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[test]
+fn test_missing_env_var() {
+    match env_var("BOTANIST_SURELY_UNSET_VARIABLE") {
+        Err(AdapterError::EnvError {
+            var,
+            source: VarError::NotPresent,
+        }) => assert_eq!(var, "BOTANIST_SURELY_UNSET_VARIABLE"),
+        other => panic!("expected a missing variable error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_connect_malformed_url() {
+    match establish_connection_with("definitely not a url").await {
+        Err(AdapterError::SqlxError(_)) => (),
+        other => panic!("expected a connection error, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_sqlx_error_source() {
+    let error = establish_connection_with("definitely not a url")
+        .await
+        .unwrap_err();
+    let source = error.source().expect("the sqlx error should be the source");
+    assert!(source.downcast_ref::<sqlx::Error>().is_some());
+    //sqlx keeps the URL parsing error as its own source
+    assert!(source.source().is_some());
+}
+
+#[test]
+fn test_guild_error_source() {
+    let error = AdapterError::from(GuildConfigError::MessageTooLong {
+        field: "welcome_message".to_string(),
+    });
+    let source = error
+        .source()
+        .expect("the guild error should be the source");
+    assert!(matches!(
+        source.downcast_ref::<GuildConfigError>(),
+        Some(GuildConfigError::MessageTooLong { .. })
+    ));
+}
+
+#[apply(db_test!)]
+async fn test_transaction_commits(pool: PgPool) -> Result<()> {
+    let id = GuildId(123456789);
+    let role = RoleId(1);
+    //every method taking several queries must accept the transaction
+    let effective = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            let config = GuildConfig::new(&mut *tx, GuildConfigBuilder::new(id)?).await?;
+            config
+                .grant_privilege(&mut *tx, role, Privilege::Admin)
+                .await?;
+            config
+                .grant_privilege(&mut *tx, role, Privilege::Event)
+                .await?;
+            config
+                .deny_privilege(&mut *tx, role, Privilege::Event)
+                .await?;
+            assert!(
+                config
+                    .has_privileges(&mut *tx, role, &[Privilege::Admin, Privilege::Manager])
+                    .await?
+            );
+            assert!(
+                config
+                    .have_privilege(&mut *tx, &[role], Privilege::Manager)
+                    .await?
+            );
+            assert_eq!(
+                config.get_privileges_for(&mut *tx, role).await?,
+                vec![Privilege::Admin, Privilege::Manager]
+            );
+            prune_all_guilds(&mut *tx, Utc::now()).await?;
+            config.highest_privilege_of(&mut *tx, &[role]).await
+        })
+    })
+    .await
+    .unwrap();
+    assert_eq!(effective.highest(), Some(Privilege::Admin));
+    let config = GuildConfig::from(id);
+    assert!(config.exists(&pool).await.unwrap());
+    assert!(config
+        .has_privilege(&pool, role, Privilege::Admin)
+        .await
+        .unwrap());
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(5844))
+            .len(&pool)
+            .await
+            .unwrap(),
+        0
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_transaction_rolls_back(pool: PgPool) -> Result<()> {
+    let id = GuildId(123456789);
+    let result = with_transaction(&pool, |tx| {
+        Box::pin(async move {
+            let config = GuildConfig::new(&mut *tx, GuildConfigBuilder::new(id)?).await?;
+            config
+                .grant_privilege(&mut *tx, RoleId(1), Privilege::Admin)
+                .await?;
+            //the role doesn't have the privilege so this fails
+            config
+                .deny_privilege(&mut *tx, RoleId(2), Privilege::Event)
+                .await
+        })
+    })
+    .await;
+    assert!(matches!(
+        result,
+        Err(AdapterError::GuildError(
+            GuildConfigError::RoleNoPrivilege { .. }
+        ))
+    ));
+    assert!(!GuildConfig::from(id).exists(&pool).await.unwrap());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_shutdown_timeout(pool: PgPool) -> Result<()> {
+    let conn = pool.acquire().await?;
+    match shutdown(pool.clone(), Duration::from_millis(100)).await {
+        Err(AdapterError::ShutdownTimedOut(timeout)) => {
+            assert_eq!(timeout, Duration::from_millis(100))
+        }
+        other => panic!("expected the shutdown to time out, got {:?}", other),
+    }
+    //the pool was closed even though the deadline was hit
+    assert!(pool.is_closed());
+    assert!(pool.acquire().await.is_err());
+    drop(conn);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_shutdown(pool: PgPool) -> Result<()> {
+    GuildConfig::from(GuildId(5844))
+        .get_advertise(&pool)
+        .await
+        .unwrap();
+    shutdown(pool.clone(), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert!(pool.is_closed());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_query_error_context(pool: PgPool) -> Result<()> {
+    pool.close().await;
+    let error = GuildConfig::from(GuildId(5844))
+        .set_welcome_message(&pool, Some("hello"))
+        .await
+        .unwrap_err();
+    let display = error.to_string();
+    assert!(display.contains("guild.set_welcome_message"), "{}", display);
+    assert!(display.contains("5844"), "{}", display);
+    assert!(matches!(
+        error,
+        AdapterError::Query {
+            operation: "guild.set_welcome_message",
+            guild: Some(GuildId(5844)),
+            source: sqlx::Error::PoolClosed,
+        }
+    ));
+    assert!(matches!(error.sqlx_error(), Some(sqlx::Error::PoolClosed)));
+    assert!(error
+        .source()
+        .unwrap()
+        .downcast_ref::<sqlx::Error>()
+        .is_some());
+
+    let error = prune_all_guilds(&pool, Utc::now()).await.unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "could not execute query `slap.prune_all_guilds`"
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_decode_error_context(pool: PgPool) -> Result<()> {
+    sqlx::query("ALTER TABLE guilds ALTER COLUMN welcome_message TYPE integer USING 42")
+        .execute(&pool)
+        .await?;
+    sqlx::query(
+        "ALTER TABLE guilds ALTER COLUMN priv_manager TYPE text[] USING priv_manager::text[]",
+    )
+    .execute(&pool)
+    .await?;
+    let config = GuildConfig::from(GuildId(5844));
+    assert!(matches!(
+        config.get_welcome_message(&pool).await.unwrap_err(),
+        AdapterError::Query {
+            operation: "guild.get_welcome_message",
+            guild: Some(GuildId(5844)),
+            source: sqlx::Error::ColumnDecode { .. },
+        }
+    ));
+    assert!(matches!(
+        config
+            .get_roles_with(&pool, Privilege::Manager)
+            .await
+            .unwrap_err(),
+        AdapterError::Query {
+            operation: "guild.get_roles_with",
+            guild: Some(GuildId(5844)),
+            source: sqlx::Error::ColumnDecode { .. },
+        }
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_sqlx_error_from() {
+    let error = AdapterError::from(sqlx::Error::RowNotFound);
+    assert!(matches!(error, AdapterError::SqlxError(_)));
+    assert_eq!(error.to_string(), "could not execute query");
+}
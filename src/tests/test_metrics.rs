@@ -0,0 +1,28 @@
+use super::framework::{db_test_interface::db_test, guild_test_info::FIRST_ID};
+use crate::guild::GuildConfig;
+use crate::id::GuildId;
+use crate::metrics::metrics;
+use macro_rules_attribute::apply;
+use sqlx::{PgPool, Result};
+
+#[apply(db_test!)]
+async fn test_query_counters(pool: PgPool) -> Result<()> {
+    //other tests run concurrently and share the counters so they can only be checked for a lower bound
+    let before = metrics(&pool);
+    let config = GuildConfig::from(FIRST_ID);
+    config.get_advertise(&pool).await.unwrap();
+    config.get_welcome_message(&pool).await.unwrap();
+    config.get_admin_chan(&pool).await.unwrap();
+    let after = metrics(&pool);
+    assert!(after.queries_executed >= before.queries_executed + 3);
+
+    assert!(GuildConfig::from(GuildId(1))
+        .get_advertise(&pool)
+        .await
+        .is_err());
+    let errored = metrics(&pool);
+    assert!(errored.queries_executed > after.queries_executed);
+    assert!(errored.queries_errored > after.queries_errored);
+    assert!(errored.idle <= errored.size as usize);
+    Ok(())
+}
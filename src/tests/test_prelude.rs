@@ -0,0 +1,39 @@
+use super::framework::db_test_interface::db_test;
+use crate::prelude::*;
+use macro_rules_attribute::apply;
+use sqlx::Result;
+
+//only the prelude is used so that a missing re-export fails to compile
+async fn use_prelude(pool: &PgPool) -> std::result::Result<(), AdapterError> {
+    let _connect = establish_connection;
+    let _try_connect = try_establish_connection;
+    let _connect_with = establish_connection_with;
+    let _connect_config = establish_connection_config;
+    let _connect_retrying = establish_connection_retrying;
+    let _shutdown = shutdown;
+
+    let guild = GuildConfig::new(pool, GuildConfigBuilder::new(6543.into())?).await?;
+    let role = 9876.into();
+    guild
+        .grant_privilege(pool, role, Privilege::Manager)
+        .await?;
+    assert!(guild.has_privilege(pool, role, Privilege::Manager).await?);
+
+    let guild_record = GuildSlapRecord::from(guild.0);
+    let report = guild_record
+        .new_slap(pool, 1234.into(), 4321.into(), Enforcer::Community, None)
+        .await?;
+    let member_record = MemberSlapRecord::from((guild.0, report.offender));
+    assert_eq!(member_record.len(pool).await?, 1);
+    assert_eq!(SlapReport::get(pool, report.sentence).await?, Some(report));
+
+    let db = BotanistDb::from(pool.clone());
+    assert_eq!(db.member_slaps(guild.0, 4321.into()).len().await?, 1);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn test_prelude(pool: PgPool) -> Result<()> {
+    use_prelude(&pool).await.unwrap();
+    Ok(())
+}
@@ -1,11 +1,8 @@
-use super::framework::{
-    db_test_interface::{db_session, db_test},
-    guild_test_info::FIRST_ID,
-    slap_test_info::*,
-};
-use crate::slap::*;
+use super::framework::{db_test_interface::db_test, guild_test_info::FIRST_ID, slap_test_info::*};
+use crate::id::{ChannelId, GuildId, MessageId, UserId};
+use crate::{guild::GuildConfig, slap::*, AdapterError};
+use chrono::{Duration, NaiveDate, TimeZone, Utc};
 use macro_rules_attribute::apply;
-use serenity::model::id::MessageId;
 use sqlx::{PgPool, Result};
 use tokio_stream::StreamExt;
 
@@ -40,6 +37,238 @@ async fn msr_slaps(conn: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn msr_slaps_guild_filter(conn: PgPool) -> Result<()> {
+    // the fixtures' third and fourth slaps have the same offender but are in different guilds
+    assert_eq!(THIRD_OFFENDER, FOURTH_OFFENDER);
+    assert_ne!(THIRD_GUILD, FOURTH_GUILD);
+    for (guild, expected) in [
+        (THIRD_GUILD, assemble_from_test!("THIRD")),
+        (FOURTH_GUILD, assemble_from_test!("FOURTH")),
+    ] {
+        assert_eq!(
+            MemberSlapRecord::from((guild, THIRD_OFFENDER))
+                .slaps(&conn)
+                .map(|res| res.unwrap())
+                .collect::<Vec<SlapReport>>()
+                .await,
+            vec![expected]
+        );
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn sr_require_get(conn: PgPool) -> Result<()> {
+    assert_eq!(
+        SlapReport::require_get(&conn, FIRST_SENTENCE)
+            .await
+            .unwrap(),
+        assemble_from_test!("FIRST")
+    );
+    match SlapReport::require_get(&conn, MessageId(1234567)).await {
+        Err(AdapterError::SlapNotFound(MessageId(1234567))) => (),
+        other => panic!("expected a missing slap error, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn sr_pardon_unknown(conn: PgPool) -> Result<()> {
+    let sentence = MessageId(1234567);
+    let mut report = SlapReport {
+        sentence,
+        offender: FIRST_OFFENDER,
+        enforcer: Enforcer::Community,
+        reason: None,
+        pardoned: None,
+        expires_at: None,
+        notes: None,
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    match report.pardon(&conn, UserId(9876)).await {
+        Err(AdapterError::SlapNotFound(missing)) => assert_eq!(missing, sentence),
+        other => panic!("expected a missing slap error, got {:?}", other),
+    }
+    assert_eq!(report.pardoned, None);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn sr_pardon(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let mut report = SlapReport::get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap()
+        .unwrap();
+    report.pardon(&conn, UserId(9876)).await.unwrap();
+    assert_eq!(report.pardoned.as_ref().unwrap().by, UserId(9876));
+    assert_eq!(
+        Some(&report),
+        SlapReport::get(&conn, FIRST_SENTENCE)
+            .await
+            .unwrap()
+            .as_ref()
+    );
+
+    assert_eq!(record.len(&conn).await.unwrap(), 1);
+    assert_eq!(record.len_including_pardoned(&conn).await.unwrap(), 2);
+    assert_eq!(
+        record
+            .slaps(&conn)
+            .map(|res| res.unwrap())
+            .collect::<Vec<SlapReport>>()
+            .await,
+        vec![assemble_from_test!("SECOND")]
+    );
+    assert!(record
+        .slaps_including_pardoned(&conn)
+        .map(|res| res.unwrap())
+        .collect::<Vec<SlapReport>>()
+        .await
+        .contains(&report));
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn sr_set_notes(conn: PgPool) -> Result<()> {
+    let mut report = SlapReport::require_get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap();
+    assert_eq!(report.notes, None);
+    //no length limit, unlike reasons
+    let notes = "reviewed with senior mod ".repeat(200);
+    report.set_notes(&conn, Some(&notes)).await.unwrap();
+    assert_eq!(report.notes.as_deref(), Some(notes.as_str()));
+    assert_eq!(
+        SlapReport::require_get(&conn, FIRST_SENTENCE)
+            .await
+            .unwrap(),
+        report
+    );
+    //the reason is left untouched
+    assert_eq!(report.reason.as_deref(), FIRST_REASON);
+
+    report.set_notes(&conn, None).await.unwrap();
+    assert_eq!(
+        SlapReport::require_get(&conn, FIRST_SENTENCE)
+            .await
+            .unwrap()
+            .notes,
+        None
+    );
+
+    let mut unknown = SlapReport {
+        sentence: MessageId(1234567),
+        offender: FIRST_OFFENDER,
+        enforcer: Enforcer::Community,
+        reason: None,
+        pardoned: None,
+        expires_at: None,
+        notes: None,
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    match unknown.set_notes(&conn, Some("lost")).await {
+        Err(AdapterError::SlapNotFound(missing)) => assert_eq!(missing, unknown.sentence),
+        other => panic!("expected a missing slap error, got {:?}", other),
+    }
+    assert_eq!(unknown.notes, None);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_recent(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    assert_eq!(
+        record.recent(&conn, 1).await.unwrap(),
+        vec![assemble_from_test!("FIRST")]
+    );
+    assert_eq!(
+        record.recent(&conn, 5).await.unwrap(),
+        vec![assemble_from_test!("FIRST"), assemble_from_test!("SECOND")]
+    );
+    assert!(record.recent(&conn, 0).await.unwrap().is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_latest(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    //FIRST has a higher sentence than SECOND
+    assert_eq!(
+        record.latest(&conn).await.unwrap(),
+        Some(assemble_from_test!("FIRST"))
+    );
+    assert_eq!(
+        MemberSlapRecord::from((FIRST_ID, UserId(1)))
+            .latest(&conn)
+            .await
+            .unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_oldest(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    //SECOND has a lower sentence than FIRST
+    assert_eq!(
+        record.oldest(&conn).await.unwrap(),
+        Some(assemble_from_test!("SECOND"))
+    );
+    assert_eq!(
+        MemberSlapRecord::from((FIRST_ID, UserId(1)))
+            .oldest(&conn)
+            .await
+            .unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_last_n_slaps(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let sentences = |slaps: Vec<SlapReport>| -> Vec<MessageId> {
+        slaps.into_iter().map(|slap| slap.sentence).collect()
+    };
+    assert_eq!(
+        sentences(record.last_n_slaps(&conn, 1).await.unwrap()),
+        vec![FIRST_SENTENCE]
+    );
+    //ordered by creation date rather than by sentence
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at='2022-01-01T00:00:00Z' WHERE sentence={}",
+        SECOND_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    assert_eq!(
+        sentences(record.last_n_slaps(&conn, 5).await.unwrap()),
+        vec![SECOND_SENTENCE, FIRST_SENTENCE]
+    );
+    //pardoned slaps are left out
+    SlapReport::require_get(&conn, SECOND_SENTENCE)
+        .await
+        .unwrap()
+        .pardon(&conn, UserId(42))
+        .await
+        .unwrap();
+    assert_eq!(
+        sentences(record.last_n_slaps(&conn, 5).await.unwrap()),
+        vec![FIRST_SENTENCE]
+    );
+    for n in [0, -1] {
+        match record.last_n_slaps(&conn, n).await {
+            Err(AdapterError::InvalidArgument { name: "n", .. }) => (),
+            other => panic!("expected an invalid n, got {:?}", other),
+        }
+    }
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn msr_new_slap(conn: PgPool) -> Result<()> {
     let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
@@ -56,13 +285,285 @@ async fn msr_new_slap(conn: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn gsr_new_slap_enforcers(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let enforcers = [
+        Enforcer::Community,
+        Enforcer::Manager(UserId(684308)),
+        Enforcer::Automatic,
+    ];
+    for (i, enforcer) in enforcers.iter().enumerate() {
+        let sentence = MessageId(5864 + i as u64);
+        let report = record
+            .new_slap(&conn, sentence, FIRST_OFFENDER, *enforcer, None)
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(report),
+            SlapReport::get(&conn, sentence).await.unwrap()
+        );
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn new_slap_manager_zero(conn: PgPool) -> Result<()> {
+    // would be read back as `Enforcer::Automatic`
+    match GuildSlapRecord::from(FIRST_ID)
+        .new_slap(
+            &conn,
+            MessageId(5864),
+            FIRST_OFFENDER,
+            Enforcer::Manager(UserId(0)),
+            None,
+        )
+        .await
+    {
+        Err(AdapterError::InvalidArgument { name, .. }) => assert_eq!(name, "enforcer"),
+        other => panic!("expected an invalid argument, got {:?}", other),
+    }
+    assert_eq!(None, SlapReport::get(&conn, MessageId(5864)).await.unwrap());
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn enforcer_serde() {
+    for enforcer in [
+        Enforcer::Community,
+        Enforcer::Manager(UserId(684308)),
+        Enforcer::Automatic,
+    ] {
+        let json = serde_json::to_string(&enforcer).unwrap();
+        assert_eq!(serde_json::from_str::<Enforcer>(&json).unwrap(), enforcer);
+    }
+}
+
+#[test]
+fn enforcer_accessors() {
+    let manager = Enforcer::Manager(UserId(684308));
+    assert_eq!(manager.manager_id(), Some(UserId(684308)));
+    assert!(manager.is_manager());
+    assert!(!manager.is_community());
+    assert_eq!(Enforcer::Community.manager_id(), None);
+    assert!(Enforcer::Community.is_community());
+    assert!(!Enforcer::Community.is_manager());
+    assert_eq!(Enforcer::Automatic.manager_id(), None);
+    assert!(!Enforcer::Automatic.is_community());
+    assert!(!Enforcer::Automatic.is_manager());
+}
+
+#[apply(db_test!)]
+async fn sr_enforcer_shorthands(conn: PgPool) -> Result<()> {
+    let first = SlapReport::require_get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap();
+    assert!(first.is_manager_issued());
+    assert!(!first.is_community_issued());
+    assert_eq!(first.manager_id(), Some(UserId(684308)));
+    let second = SlapReport::require_get(&conn, SECOND_SENTENCE)
+        .await
+        .unwrap();
+    assert!(second.is_community_issued());
+    assert!(!second.is_manager_issued());
+    assert_eq!(second.manager_id(), None);
+    Ok(())
+}
+
+#[test]
+fn sr_embed_fields_manager() {
+    let report = SlapReport {
+        sentence: MessageId(6841381385),
+        offender: UserId(87038540),
+        enforcer: Enforcer::Manager(UserId(684308)),
+        reason: Some("just because".to_string()),
+        pardoned: Some(PardonInfo {
+            by: UserId(4543453),
+            at: Utc.with_ymd_and_hms(2021, 1, 2, 0, 0, 0).unwrap(),
+        }),
+        expires_at: Some(Utc.with_ymd_and_hms(2021, 2, 1, 0, 0, 0).unwrap()),
+        notes: Some("repeat offender".to_string()),
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    assert_eq!(
+        report.to_embed_fields(),
+        vec![
+            ("Offender".to_string(), "<@87038540>".to_string(), true),
+            ("Enforcer".to_string(), "<@684308>".to_string(), true),
+            ("Reason".to_string(), "just because".to_string(), false),
+            ("Pardoned by".to_string(), "<@4543453>".to_string(), true),
+            ("Expires".to_string(), "<t:1612137600:f>".to_string(), true),
+        ]
+    );
+    assert_eq!(
+        report.jump_link(GuildId(5844), ChannelId(87904)),
+        "https://discord.com/channels/5844/87904/6841381385"
+    );
+}
+
+#[test]
+fn sr_embed_fields_community() {
+    let report = SlapReport {
+        sentence: MessageId(878404),
+        offender: UserId(87038540),
+        enforcer: Enforcer::Community,
+        reason: None,
+        pardoned: None,
+        expires_at: None,
+        notes: None,
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    assert_eq!(
+        report.to_embed_fields(),
+        vec![
+            ("Offender".to_string(), "<@87038540>".to_string(), true),
+            ("Enforcer".to_string(), "Community".to_string(), true),
+            ("Reason".to_string(), DEFAULT_REASON.to_string(), false),
+        ]
+    );
+}
+
+#[test]
+fn sr_embed_fields_long_reason() {
+    let report = SlapReport {
+        sentence: MessageId(878404),
+        offender: UserId(87038540),
+        enforcer: Enforcer::Automatic,
+        reason: Some("é".repeat(MAX_REASON_LENGTH)),
+        pardoned: None,
+        expires_at: None,
+        notes: None,
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    let (_, reason, _) = &report.to_embed_fields()[2];
+    assert_eq!(reason.chars().count(), EMBED_FIELD_VALUE_LIMIT);
+    assert_eq!(
+        *reason,
+        format!("{}…", "é".repeat(EMBED_FIELD_VALUE_LIMIT - 1))
+    );
+
+    let exact = "a".repeat(EMBED_FIELD_VALUE_LIMIT);
+    let report = SlapReport {
+        reason: Some(exact.clone()),
+        ..report
+    };
+    assert_eq!(report.to_embed_fields()[2].1, exact);
+}
+
+#[cfg(feature = "net")]
+#[test]
+fn slap_report_serde() {
+    let report = SlapReport {
+        sentence: MessageId(6841381385),
+        offender: UserId(87038540),
+        enforcer: Enforcer::Manager(UserId(684308)),
+        reason: None,
+        pardoned: Some(PardonInfo {
+            by: UserId(u64::MAX),
+            at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+        }),
+        expires_at: None,
+        notes: None,
+        created_at: Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap(),
+    };
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["sentence"], "6841381385");
+    assert_eq!(json["offender"], "87038540");
+    assert_eq!(json["enforcer"], serde_json::json!({"Manager": "684308"}));
+    assert_eq!(json["pardoned"]["by"], "18446744073709551615");
+    assert_eq!(json["created_at"], "2021-01-01T00:00:00Z");
+    assert_eq!(serde_json::from_value::<SlapReport>(json).unwrap(), report);
+}
+
+#[apply(db_test!)]
+async fn new_slap_community_reason(conn: PgPool) -> Result<()> {
+    let reason = "that's what they said";
+    let member_report = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER))
+        .new_slap(&conn, MessageId(5864), Enforcer::Community, Some(reason))
+        .await
+        .unwrap();
+    let guild_report = GuildSlapRecord::from(FIRST_ID)
+        .new_slap(
+            &conn,
+            MessageId(5865),
+            FIRST_OFFENDER,
+            Enforcer::Community,
+            Some(reason),
+        )
+        .await
+        .unwrap();
+    for report in [member_report, guild_report] {
+        assert_eq!(report.reason.as_deref(), Some(reason));
+        let sentence = report.sentence;
+        assert_eq!(
+            Some(report),
+            SlapReport::get(&conn, sentence).await.unwrap()
+        );
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn new_slap_reason_types(conn: PgPool) -> Result<()> {
+    let member = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let guild = GuildSlapRecord::from(FIRST_ID);
+    let owned = String::from("owned reason");
+    let reasons = [Some("borrowed reason"), Some(owned.as_str()), None];
+    for (i, reason) in reasons.iter().enumerate() {
+        let sentence = MessageId(5864 + 2 * i as u64);
+        let member_report = member
+            .new_slap(&conn, sentence, Enforcer::Community, *reason)
+            .await
+            .unwrap();
+        let guild_report = guild
+            .new_slap(
+                &conn,
+                MessageId(sentence.0 + 1),
+                FIRST_OFFENDER,
+                Enforcer::Community,
+                *reason,
+            )
+            .await
+            .unwrap();
+        assert_eq!(member_report.reason.as_deref(), *reason);
+        assert_eq!(guild_report.reason, member_report.reason);
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn new_slap_reason_too_long(conn: PgPool) -> Result<()> {
+    let reason = "a".repeat(MAX_REASON_LENGTH + 1);
+    let member_res = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER))
+        .new_slap(&conn, MessageId(5864), Enforcer::Community, Some(&reason))
+        .await;
+    let guild_res = GuildSlapRecord::from(FIRST_ID)
+        .new_slap(
+            &conn,
+            MessageId(5865),
+            FIRST_OFFENDER,
+            Enforcer::Automatic,
+            Some(&reason),
+        )
+        .await;
+    for res in [member_res, guild_res] {
+        match res {
+            Err(AdapterError::SlapError(SlapError::ReasonTooLong)) => (),
+            _ => panic!(),
+        }
+    }
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn gsr_slaps(conn: PgPool) -> Result<()> {
     let record = GuildSlapRecord::from(FIRST_ID);
+    // the fixtures share a creation date so they are ordered by sentence
     let reports = vec![
-        assemble_from_test!("FIRST"),
-        assemble_from_test!("SECOND"),
         assemble_from_test!("FOURTH"),
+        assemble_from_test!("SECOND"),
+        assemble_from_test!("FIRST"),
     ];
     assert_eq!(
         record
@@ -75,6 +576,16 @@ async fn gsr_slaps(conn: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn gsr_recent(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    assert_eq!(
+        record.recent(&conn, 2).await.unwrap(),
+        vec![assemble_from_test!("FIRST"), assemble_from_test!("SECOND")]
+    );
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn gsr_members(conn: PgPool) -> Result<()> {
     let record = GuildSlapRecord::from(FIRST_ID);
@@ -89,6 +600,74 @@ async fn gsr_members(conn: PgPool) -> Result<()> {
     Ok(())
 }
 
+#[apply(db_test!)]
+async fn gsr_collect_offenders(conn: PgPool) -> Result<()> {
+    let mut members = GuildSlapRecord::from(FIRST_ID)
+        .collect_offenders(&conn)
+        .await
+        .unwrap();
+    members.sort_by_key(|member| member.1);
+    assert_eq!(
+        members,
+        vec![
+            MemberSlapRecord::from((FIRST_ID, FOURTH_OFFENDER)),
+            MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER))
+        ]
+    );
+    assert!(GuildSlapRecord::from(GuildId(1))
+        .collect_offenders(&conn)
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_offenders_sorted_by_slap_count(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let offenders = record
+        .offenders_sorted_by_slap_count(&conn)
+        .map(|res| res.unwrap())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(
+        offenders,
+        vec![
+            (MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER)), 2),
+            (MemberSlapRecord::from((FIRST_ID, FOURTH_OFFENDER)), 1),
+        ]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_active_members_with_slaps(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let since = |year| {
+        record
+            .active_members_with_slaps(&conn, Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0).unwrap())
+            .map(|res| res.unwrap())
+            .collect::<Vec<MemberSlapRecord>>()
+    };
+    let mut members = since(2020).await;
+    members.sort_by_key(|member| member.1);
+    assert_eq!(
+        members,
+        vec![
+            MemberSlapRecord::from((FIRST_ID, FOURTH_OFFENDER)),
+            MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER)),
+        ]
+    );
+    //FIRST_OFFENDER has two slaps but is listed once
+    assert_eq!(
+        since(2021).await,
+        vec![MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER))]
+    );
+    assert!(since(2022).await.is_empty());
+    Ok(())
+}
+
 #[apply(db_test!)]
 async fn gsr_member_count(conn: PgPool) -> Result<()> {
     let record = GuildSlapRecord::from(FIRST_ID);
@@ -96,3 +675,848 @@ async fn gsr_member_count(conn: PgPool) -> Result<()> {
     assert_eq!(count, 2);
     Ok(())
 }
+
+#[apply(db_test!)]
+async fn gsr_active_offender_count(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    assert_eq!(record.active_offender_count(&conn).await.unwrap(), 2);
+
+    //FOURTH_OFFENDER only has one slap in this guild
+    sqlx::query(&format!(
+        "UPDATE slaps SET expires_at=now() - INTERVAL '1 second' WHERE sentence={}",
+        FOURTH_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    assert_eq!(record.active_offender_count(&conn).await.unwrap(), 1);
+
+    let mut first = SlapReport::require_get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap();
+    first.pardon(&conn, UserId(42)).await.unwrap();
+    assert_eq!(record.active_offender_count(&conn).await.unwrap(), 1);
+    let mut second = SlapReport::require_get(&conn, SECOND_SENTENCE)
+        .await
+        .unwrap();
+    second.pardon(&conn, UserId(42)).await.unwrap();
+    assert_eq!(record.active_offender_count(&conn).await.unwrap(), 0);
+    //expired and pardoned slaps still count there
+    assert_eq!(record.offender_count(&conn).await.unwrap(), 2);
+    Ok(())
+}
+
+async fn set_creation_dates(conn: &PgPool) -> Result<()> {
+    sqlx::query("UPDATE slaps SET created_at='2020-01-01T00:00:00Z'")
+        .execute(conn)
+        .await?;
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at='2021-01-01T00:00:00Z' WHERE sentence={}",
+        FIRST_SENTENCE
+    ))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_pardon_all_community_slaps(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    assert_eq!(record.pardon_all_community_slaps(&conn).await.unwrap(), 1);
+    // the manager's slap is kept
+    assert_eq!(
+        record
+            .slaps(&conn)
+            .map(|res| res.unwrap())
+            .collect::<Vec<SlapReport>>()
+            .await,
+        vec![assemble_from_test!("FIRST")]
+    );
+    assert_eq!(record.pardon_all_community_slaps(&conn).await.unwrap(), 0);
+    // other members are left alone
+    assert_eq!(GuildSlapRecord::from(FIRST_ID).len(&conn).await.unwrap(), 2);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_slap_summary(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    assert_eq!(
+        record.slap_summary(&conn).await.unwrap(),
+        MemberSlapSummary {
+            total: 2,
+            community: 1,
+            manager: 1
+        }
+    );
+
+    GuildSlapRecord::from(FIRST_ID)
+        .new_slap(
+            &conn,
+            MessageId(5864),
+            FIRST_OFFENDER,
+            Enforcer::Automatic,
+            None,
+        )
+        .await
+        .unwrap();
+    SlapReport::require_get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap()
+        .pardon(&conn, UserId(42))
+        .await
+        .unwrap();
+    assert_eq!(
+        record.slap_summary(&conn).await.unwrap(),
+        MemberSlapSummary {
+            total: 2,
+            community: 1,
+            manager: 0
+        }
+    );
+
+    assert_eq!(
+        MemberSlapRecord::from((GuildId(1), FIRST_OFFENDER))
+            .slap_summary(&conn)
+            .await
+            .unwrap(),
+        MemberSlapSummary::default()
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_threshold_exceeded(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    // no threshold by default
+    assert_eq!(record.threshold_exceeded(&conn, None).await.unwrap(), None);
+
+    let guild = GuildConfig::from(FIRST_ID);
+    for (threshold, exceeded) in [(3, false), (2, true), (1, true)] {
+        guild
+            .set_slap_threshold(&conn, Some(threshold))
+            .await
+            .unwrap();
+        assert_eq!(
+            record.threshold_exceeded(&conn, None).await.unwrap(),
+            Some(ThresholdStatus {
+                count: 2,
+                threshold,
+                exceeded
+            })
+        );
+    }
+
+    set_creation_dates(&conn).await?;
+    guild.set_slap_threshold(&conn, Some(2)).await.unwrap();
+    assert_eq!(
+        record
+            .threshold_exceeded(
+                &conn,
+                Some(Utc.with_ymd_and_hms(2020, 6, 1, 0, 0, 0).unwrap())
+            )
+            .await
+            .unwrap(),
+        Some(ThresholdStatus {
+            count: 1,
+            threshold: 2,
+            exceeded: false
+        })
+    );
+
+    match MemberSlapRecord::from((GuildId(1), FIRST_OFFENDER))
+        .threshold_exceeded(&conn, None)
+        .await
+    {
+        Err(AdapterError::GuildNotFound(GuildId(1))) => (),
+        other => panic!("expected a missing guild, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_rolling_counts(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    sqlx::query("UPDATE slaps SET created_at=now()")
+        .execute(&conn)
+        .await?;
+    assert_eq!(record.rolling_7day_count(&conn).await.unwrap(), 3);
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at=now() - INTERVAL '10 days' WHERE sentence={}",
+        FIRST_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at=now() - INTERVAL '40 days' WHERE sentence={}",
+        SECOND_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    assert_eq!(record.rolling_7day_count(&conn).await.unwrap(), 1);
+    assert_eq!(record.rolling_30day_count(&conn).await.unwrap(), 2);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_slap_rate(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    sqlx::query("UPDATE slaps SET created_at=now()")
+        .execute(&conn)
+        .await?;
+    assert_eq!(record.slap_rate(&conn, 3).await.unwrap(), 1.0);
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at=now() - INTERVAL '10 days' WHERE sentence={}",
+        FIRST_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    assert_eq!(record.slap_rate(&conn, 7).await.unwrap(), 2.0 / 7.0);
+    assert_eq!(record.slap_rate(&conn, 14).await.unwrap(), 3.0 / 14.0);
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .slap_rate(&conn, 7)
+            .await
+            .unwrap(),
+        0.0
+    );
+    for days in [0, u32::MAX] {
+        match record.slap_rate(&conn, days).await {
+            Err(AdapterError::InvalidArgument { name: "days", .. }) => (),
+            other => panic!("expected invalid days, got {:?}", other),
+        }
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_monthly_summary(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let record = GuildSlapRecord::from(FIRST_ID);
+    assert_eq!(
+        record.monthly_summary(&conn, 2020, 1).await.unwrap(),
+        MonthlySlapSummary {
+            total: 2,
+            community: 1,
+            manager: 1,
+            unique_offenders: 2,
+        }
+    );
+    assert_eq!(
+        record.monthly_summary(&conn, 2021, 1).await.unwrap(),
+        MonthlySlapSummary {
+            total: 1,
+            community: 0,
+            manager: 1,
+            unique_offenders: 1,
+        }
+    );
+    //december must include its last day
+    assert_eq!(
+        record.monthly_summary(&conn, 2019, 12).await.unwrap(),
+        MonthlySlapSummary::default()
+    );
+    for month in [0, 13] {
+        match record.monthly_summary(&conn, 2020, month).await {
+            Err(AdapterError::InvalidArgument { name: "month", .. }) => (),
+            other => panic!("expected an invalid month, got {:?}", other),
+        }
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_activity(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at='2020-01-03T12:00:00Z' WHERE sentence={}",
+        SECOND_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let date = |y, m, d| Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap();
+    assert_eq!(
+        record
+            .activity(
+                &conn,
+                date(2020, 1, 1),
+                date(2020, 1, 4),
+                ActivityBucket::Day
+            )
+            .await
+            .unwrap(),
+        vec![
+            (date(2020, 1, 1), 1),
+            (date(2020, 1, 2), 0),
+            (date(2020, 1, 3), 1)
+        ]
+    );
+    //2020-01-01 is a wednesday
+    assert_eq!(
+        record
+            .activity(
+                &conn,
+                date(2020, 1, 1),
+                date(2020, 1, 10),
+                ActivityBucket::Week
+            )
+            .await
+            .unwrap(),
+        vec![(date(2019, 12, 30), 2), (date(2020, 1, 6), 0)]
+    );
+    assert_eq!(
+        record
+            .activity(
+                &conn,
+                date(2019, 12, 15),
+                date(2020, 3, 1),
+                ActivityBucket::Month
+            )
+            .await
+            .unwrap(),
+        vec![
+            (date(2019, 12, 1), 0),
+            (date(2020, 1, 1), 2),
+            (date(2020, 2, 1), 0)
+        ]
+    );
+    //slaps before `from` are left out of the first period
+    assert_eq!(
+        record
+            .activity(
+                &conn,
+                Utc.with_ymd_and_hms(2020, 1, 1, 6, 0, 0).unwrap(),
+                date(2020, 1, 2),
+                ActivityBucket::Day
+            )
+            .await
+            .unwrap(),
+        vec![(date(2020, 1, 1), 0)]
+    );
+    match record
+        .activity(
+            &conn,
+            date(2020, 1, 1),
+            date(2020, 1, 1),
+            ActivityBucket::Day,
+        )
+        .await
+    {
+        Err(AdapterError::InvalidArgument { name: "to", .. }) => (),
+        other => panic!("expected an invalid range, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_latest(conn: PgPool) -> Result<()> {
+    assert_eq!(
+        GuildSlapRecord::from(FIRST_ID).latest(&conn).await.unwrap(),
+        Some(assemble_from_test!("FIRST"))
+    );
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .latest(&conn)
+            .await
+            .unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_oldest(conn: PgPool) -> Result<()> {
+    assert_eq!(
+        GuildSlapRecord::from(FIRST_ID).oldest(&conn).await.unwrap(),
+        Some(assemble_from_test!("FOURTH"))
+    );
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .oldest(&conn)
+            .await
+            .unwrap(),
+        None
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_slaps_on_date(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    //late in the day in UTC, already the next day further east
+    sqlx::query(&format!(
+        "UPDATE slaps SET created_at='2020-01-01T23:30:00Z' WHERE sentence={}",
+        SECOND_SENTENCE
+    ))
+    .execute(&conn)
+    .await?;
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let sentences =
+        |slaps: Vec<SlapReport>| slaps.into_iter().map(|s| s.sentence).collect::<Vec<_>>();
+    assert_eq!(
+        sentences(
+            record
+                .slaps_on_date(&conn, NaiveDate::from_ymd_opt(2020, 1, 1).unwrap())
+                .await
+                .unwrap()
+        ),
+        vec![FOURTH_SENTENCE, SECOND_SENTENCE]
+    );
+    assert_eq!(
+        sentences(
+            record
+                .slaps_on_date(&conn, NaiveDate::from_ymd_opt(2021, 1, 1).unwrap())
+                .await
+                .unwrap()
+        ),
+        vec![FIRST_SENTENCE]
+    );
+    assert!(record
+        .slaps_on_date(&conn, NaiveDate::from_ymd_opt(2020, 1, 2).unwrap())
+        .await
+        .unwrap()
+        .is_empty());
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_heatmap_by_weekday(conn: PgPool) -> Result<()> {
+    //2020-01-01 was a wednesday and 2021-01-01 a friday
+    set_creation_dates(&conn).await?;
+    assert_eq!(
+        GuildSlapRecord::from(FIRST_ID)
+            .slap_heatmap_by_weekday(&conn)
+            .await
+            .unwrap(),
+        [0, 0, 0, 2, 0, 1, 0]
+    );
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .slap_heatmap_by_weekday(&conn)
+            .await
+            .unwrap(),
+        [0; 7]
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_new_slap_expiring(conn: PgPool) -> Result<()> {
+    let record = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let sentence = MessageId(1234567);
+    let report = record
+        .new_slap_expiring(
+            &conn,
+            sentence,
+            Enforcer::Community,
+            None,
+            Duration::days(1),
+        )
+        .await
+        .unwrap();
+    let expires_at = report.expires_at.unwrap();
+    assert!(expires_at > Utc::now() + Duration::hours(23));
+    assert!(expires_at < Utc::now() + Duration::hours(25));
+    assert_eq!(
+        SlapReport::get(&conn, sentence).await.unwrap().unwrap(),
+        report
+    );
+    match record
+        .new_slap_expiring(
+            &conn,
+            MessageId(7654321),
+            Enforcer::Community,
+            None,
+            Duration::zero(),
+        )
+        .await
+    {
+        Err(AdapterError::InvalidArgument {
+            name: "duration", ..
+        }) => (),
+        other => panic!("expected an invalid duration, got {:?}", other),
+    }
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_expunge_expired_slaps(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let member = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let sentence = MessageId(1234567);
+    member
+        .new_slap_expiring(
+            &conn,
+            sentence,
+            Enforcer::Community,
+            None,
+            Duration::days(1),
+        )
+        .await
+        .unwrap();
+    //not expired yet
+    assert_eq!(record.expunge_expired_slaps(&conn).await.unwrap(), 0);
+    sqlx::query(&format!(
+        "UPDATE slaps SET expires_at=now() - INTERVAL '1 second' WHERE sentence={}",
+        sentence
+    ))
+    .execute(&conn)
+    .await?;
+    //other guilds' expired slaps are left untouched
+    assert_eq!(
+        GuildSlapRecord::from(THIRD_GUILD)
+            .expunge_expired_slaps(&conn)
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(record.expunge_expired_slaps(&conn).await.unwrap(), 1);
+    assert!(SlapReport::get(&conn, sentence).await.unwrap().is_none());
+    //slaps without expiry are kept
+    assert_eq!(record.len(&conn).await.unwrap(), 3);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn new_slap_default_expiry(conn: PgPool) -> Result<()> {
+    let member = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let report = member
+        .new_slap(&conn, MessageId(1234567), Enforcer::Community, None)
+        .await
+        .unwrap();
+    assert_eq!(report.expires_at, None);
+
+    GuildConfig::from(FIRST_ID)
+        .set_default_slap_expiry_days(&conn, Some(7))
+        .await
+        .unwrap();
+    let report = member
+        .new_slap(&conn, MessageId(7654321), Enforcer::Community, None)
+        .await
+        .unwrap();
+    let expires_at = report.expires_at.unwrap();
+    assert!(expires_at > Utc::now() + Duration::days(7) - Duration::hours(1));
+    assert!(expires_at < Utc::now() + Duration::days(7) + Duration::hours(1));
+    let report = GuildSlapRecord::from(FIRST_ID)
+        .new_slap(
+            &conn,
+            MessageId(7654322),
+            UserId(42),
+            Enforcer::Community,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(report.expires_at.is_some());
+
+    //an explicit expiry takes precedence
+    let report = member
+        .new_slap_expiring(
+            &conn,
+            MessageId(7654323),
+            Enforcer::Community,
+            None,
+            Duration::hours(1),
+        )
+        .await
+        .unwrap();
+    assert!(report.expires_at.unwrap() < Utc::now() + Duration::hours(2));
+
+    //other guilds are unaffected
+    let report = MemberSlapRecord::from((THIRD_GUILD, THIRD_OFFENDER))
+        .new_slap(&conn, MessageId(7654324), Enforcer::Community, None)
+        .await
+        .unwrap();
+    assert_eq!(report.expires_at, None);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn msr_active_slaps(conn: PgPool) -> Result<()> {
+    let member = MemberSlapRecord::from((FIRST_ID, FIRST_OFFENDER));
+    let pending = MessageId(1234567);
+    let expired = MessageId(7654321);
+    for sentence in [pending, expired] {
+        member
+            .new_slap_expiring(
+                &conn,
+                sentence,
+                Enforcer::Community,
+                None,
+                Duration::days(1),
+            )
+            .await
+            .unwrap();
+    }
+    sqlx::query(&format!(
+        "UPDATE slaps SET expires_at=now() - INTERVAL '1 second' WHERE sentence={}",
+        expired
+    ))
+    .execute(&conn)
+    .await?;
+
+    let mut active: Vec<MessageId> = member
+        .active_slaps(&conn)
+        .map(|slap| slap.unwrap().sentence)
+        .collect()
+        .await;
+    active.sort();
+    assert_eq!(active, vec![SECOND_SENTENCE, pending, FIRST_SENTENCE]);
+    assert_eq!(member.active_len(&conn).await.unwrap(), 3);
+    //expired slaps are still there until they are expunged
+    assert_eq!(member.len(&conn).await.unwrap(), 4);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_prune_older_than(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let record = GuildSlapRecord::from(FIRST_ID);
+    let before_all = Utc.with_ymd_and_hms(2019, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(record.prune_older_than(&conn, before_all).await.unwrap(), 0);
+
+    //slaps issued at the cutoff are kept
+    let cutoff = Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(record.prune_older_than(&conn, cutoff).await.unwrap(), 2);
+    assert_eq!(record.len(&conn).await.unwrap(), 1);
+    assert!(SlapReport::get(&conn, FIRST_SENTENCE)
+        .await
+        .unwrap()
+        .is_some());
+    //other guilds are left untouched
+    assert_eq!(
+        GuildSlapRecord::from(THIRD_GUILD).len(&conn).await.unwrap(),
+        1
+    );
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn gsr_prune_future_cutoff(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(FIRST_ID);
+    match record
+        .prune_older_than(&conn, Utc::now() + Duration::days(1))
+        .await
+    {
+        Err(AdapterError::SlapError(SlapError::CutoffInFuture(_))) => (),
+        _ => panic!(),
+    };
+    assert_eq!(record.len(&conn).await.unwrap(), 3);
+    Ok(())
+}
+
+#[apply(db_test!)]
+async fn prune_all(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let cutoff = Utc.with_ymd_and_hms(2021, 1, 1, 0, 0, 0).unwrap();
+    assert_eq!(prune_all_guilds(&conn, cutoff).await.unwrap(), 3);
+    assert_eq!(prune_all_guilds(&conn, Utc::now()).await.unwrap(), 1);
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+#[apply(db_test!)]
+async fn gsr_export_as_json(conn: PgPool) -> Result<()> {
+    let json = GuildSlapRecord::from(FIRST_ID)
+        .export_as_json(&conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::from_str::<Vec<SlapReport>>(&json).unwrap(),
+        vec![
+            assemble_from_test!("FOURTH"),
+            assemble_from_test!("SECOND"),
+            assemble_from_test!("FIRST")
+        ]
+    );
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .export_as_json(&conn)
+            .await
+            .unwrap(),
+        "[]"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+#[apply(db_test!)]
+async fn gsr_export_json(conn: PgPool) -> Result<()> {
+    let json = GuildSlapRecord::from(FIRST_ID)
+        .export_json(&conn)
+        .map(|chunk| chunk.unwrap())
+        .collect::<String>()
+        .await;
+    let mut reports = serde_json::from_str::<Vec<SlapReport>>(&json).unwrap();
+    reports.sort_by_key(|report| report.sentence);
+    assert_eq!(
+        reports,
+        vec![
+            assemble_from_test!("FOURTH"),
+            assemble_from_test!("SECOND"),
+            assemble_from_test!("FIRST")
+        ]
+    );
+    assert_eq!(
+        GuildSlapRecord::from(GuildId(1))
+            .export_json(&conn)
+            .map(|chunk| chunk.unwrap())
+            .collect::<String>()
+            .await,
+        "[]"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+#[apply(db_test!)]
+async fn gsr_import_from_json(conn: PgPool) -> Result<()> {
+    let json = GuildSlapRecord::from(FIRST_ID)
+        .export_as_json(&conn)
+        .await
+        .unwrap();
+    let record = GuildSlapRecord::from(GuildId(1));
+    // every sentence is already recorded in the first guild
+    assert_eq!(record.import_from_json(&conn, &json).await.unwrap(), 0);
+    assert_eq!(record.len(&conn).await.unwrap(), 0);
+
+    GuildSlapRecord::from(FIRST_ID)
+        .prune_older_than(&conn, Utc::now())
+        .await
+        .unwrap();
+    assert_eq!(record.import_from_json(&conn, &json).await.unwrap(), 3);
+    assert_eq!(
+        record.export_as_json(&conn).await.unwrap(),
+        json,
+        "the slaps should be restored as they were"
+    );
+    assert_eq!(record.import_from_json(&conn, &json).await.unwrap(), 0);
+
+    match record.import_from_json(&conn, "{}").await {
+        Err(AdapterError::InvalidArgument { name: "json", .. }) => (),
+        other => panic!("expected an invalid `json`, got {:?}", other),
+    }
+    let mut too_long = assemble_from_test!("FIRST");
+    too_long.sentence = MessageId(42);
+    too_long.reason = Some("a".repeat(MAX_REASON_LENGTH + 1));
+    let json = serde_json::to_string(&[assemble_from_test!("SECOND"), too_long]).unwrap();
+    match record.import_from_json(&conn, &json).await {
+        Err(AdapterError::SlapError(SlapError::ReasonTooLong)) => (),
+        other => panic!("expected a reason too long, got {:?}", other),
+    }
+    assert_eq!(record.len(&conn).await.unwrap(), 3);
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+#[apply(db_test!)]
+async fn gsr_import_keeps_creation_date(conn: PgPool) -> Result<()> {
+    let issued = Utc.with_ymd_and_hms(2019, 3, 14, 15, 9, 26).unwrap();
+    let old = SlapReport {
+        sentence: MessageId(42),
+        created_at: issued,
+        ..assemble_from_test!("FIRST")
+    };
+    let json = serde_json::to_string(&[old]).unwrap();
+    let record = GuildSlapRecord::from(GuildId(1));
+    assert_eq!(record.import_from_json(&conn, &json).await.unwrap(), 1);
+    assert_eq!(
+        SlapReport::require_get(&conn, MessageId(42))
+            .await
+            .unwrap()
+            .created_at,
+        issued
+    );
+    assert_eq!(record.export_as_json(&conn).await.unwrap(), json);
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[apply(db_test!)]
+async fn gsr_export_csv(conn: PgPool) -> Result<()> {
+    set_creation_dates(&conn).await?;
+    let csv = GuildSlapRecord::from(FIRST_ID)
+        .export_csv(&conn)
+        .await
+        .unwrap();
+    assert_eq!(
+        csv,
+        "sentence,offender,enforcer,enforcer_id,reason,created_at\n\
+         356489,454,Manager,4543453,,2020-01-01T00:00:00+00:00\n\
+         878404,87038540,Community,,,2020-01-01T00:00:00+00:00\n\
+         6841381385,87038540,Manager,684308,just because,2021-01-01T00:00:00+00:00\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[apply(db_test!)]
+async fn gsr_export_csv_escaping(conn: PgPool) -> Result<()> {
+    let record = GuildSlapRecord::from(GuildId(1));
+    for (sentence, reason) in [
+        (1, "spam, twice"),
+        (2, r#"said "hi""#),
+        (3, "line\nbreak"),
+        (4, "plain"),
+    ] {
+        record
+            .new_slap(
+                &conn,
+                MessageId(sentence),
+                UserId(10),
+                Enforcer::Automatic,
+                Some(reason),
+            )
+            .await
+            .unwrap();
+    }
+    sqlx::query("UPDATE slaps SET created_at='2020-01-01T00:00:00Z' WHERE guild=1")
+        .execute(&conn)
+        .await?;
+    assert_eq!(
+        record.export_csv(&conn).await.unwrap(),
+        "sentence,offender,enforcer,enforcer_id,reason,created_at\n\
+         1,10,Automatic,,\"spam, twice\",2020-01-01T00:00:00+00:00\n\
+         2,10,Automatic,,\"said \"\"hi\"\"\",2020-01-01T00:00:00+00:00\n\
+         3,10,Automatic,,\"line\nbreak\",2020-01-01T00:00:00+00:00\n\
+         4,10,Automatic,,plain,2020-01-01T00:00:00+00:00\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "csv")]
+#[apply(db_test!)]
+async fn gsr_write_csv_to_failing_writer(conn: PgPool) -> Result<()> {
+    struct Broken;
+    impl tokio::io::AsyncWrite for Broken {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            _: &[u8],
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            std::task::Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()))
+        }
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<std::io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+    match GuildSlapRecord::from(FIRST_ID)
+        .write_csv_to(&conn, Broken)
+        .await
+    {
+        Err(AdapterError::IoError(error)) => {
+            assert_eq!(error.kind(), std::io::ErrorKind::BrokenPipe)
+        }
+        other => panic!("expected an I/O error, got {:?}", other),
+    }
+    Ok(())
+}
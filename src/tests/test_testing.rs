@@ -0,0 +1,123 @@
+use super::framework::db_test_interface::db_test;
+use crate::guild::{get_all_guild_ids, GuildConfig};
+use crate::id::{GuildId, MessageId, UserId};
+use crate::slap::GuildSlapRecord;
+use crate::testing::fixtures::{self, GuildRow, SlapRow};
+use crate::testing::{purge_test_databases, Fixtures, TestDb, TEST_DB_URL_VAR};
+use macro_rules_attribute::apply;
+use sqlx::{Connection, PgConnection, PgPool, Result};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[tokio::test]
+async fn test_db_close() {
+    let db = TestDb::new().await.unwrap();
+    let url = db.url().to_string();
+    let mut ids = get_all_guild_ids(db.pool()).await.unwrap();
+    ids.sort();
+    assert_eq!(
+        ids,
+        vec![fixtures::guild::FIRST_ID, fixtures::guild::SECOND_ID]
+    );
+    db.close().await.unwrap();
+    assert!(PgConnection::connect(&url).await.is_err());
+}
+
+#[tokio::test]
+async fn test_db_drop() {
+    let db = TestDb::empty().await.unwrap();
+    let url = db.url().to_string();
+    assert!(get_all_guild_ids(db.pool()).await.unwrap().is_empty());
+    drop(db);
+    assert!(PgConnection::connect(&url).await.is_err());
+}
+
+#[tokio::test]
+async fn test_db_empty_fixtures() {
+    let db = TestDb::with_fixtures(Fixtures::Empty).await.unwrap();
+    assert!(get_all_guild_ids(db.pool()).await.unwrap().is_empty());
+    let slaps = GuildSlapRecord(fixtures::guild::FIRST_ID);
+    assert_eq!(slaps.len(db.pool()).await.unwrap(), 0);
+    db.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_db_custom_fixtures() {
+    const GUILD: GuildId = GuildId(4200);
+    let db = TestDb::with_fixtures(Fixtures::custom(|conn| {
+        Box::pin(async move {
+            fixtures::insert_guild(conn, &GuildRow::new(GUILD)).await?;
+            for i in 1..=1000 {
+                let slap = SlapRow::new(MessageId(i), GUILD, UserId(i % 10 + 1));
+                fixtures::insert_slap(conn, &slap).await?;
+            }
+            Ok(())
+        })
+    }))
+    .await
+    .unwrap();
+
+    assert_eq!(get_all_guild_ids(db.pool()).await.unwrap(), vec![GUILD]);
+    assert!(GuildConfig(GUILD).get_advertise(db.pool()).await.unwrap());
+    let slaps = GuildSlapRecord(GUILD);
+    assert_eq!(slaps.len(db.pool()).await.unwrap(), 1000);
+    assert_eq!(slaps.offender_count(db.pool()).await.unwrap(), 10);
+    db.close().await.unwrap();
+}
+
+async fn database_exists(conn: &mut PgConnection, name: &str) -> bool {
+    sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)")
+        .bind(name)
+        .fetch_one(conn)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_purge_test_databases() {
+    let fresh = TestDb::empty().await.unwrap();
+    let fresh_name = fresh.url().rsplit('/').next().unwrap().to_string();
+    let base_url = std::env::var(TEST_DB_URL_VAR).unwrap();
+    let mut conn = PgConnection::connect(&base_url).await.unwrap();
+
+    // named like a database of a run killed two days ago
+    let created = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+    let orphan = format!(
+        "botanist_test_0_{}_0",
+        created.duration_since(UNIX_EPOCH).unwrap().as_nanos()
+    );
+    sqlx::query(&format!("CREATE DATABASE {}", orphan))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+    let purged = purge_test_databases(&base_url, Duration::from_secs(60 * 60))
+        .await
+        .unwrap();
+    assert!(!purged.contains(&fresh_name));
+    assert!(!database_exists(&mut conn, &orphan).await);
+    assert!(database_exists(&mut conn, &fresh_name).await);
+    conn.close().await.unwrap();
+    fresh.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_db_dropped_on_panic() {
+    let (sender, receiver) = mpsc::channel();
+    let test = tokio::spawn(async move {
+        let db = TestDb::new().await.unwrap();
+        sender.send(db.url().to_string()).unwrap();
+        panic!("deliberate panic");
+    })
+    .await;
+    assert!(test.unwrap_err().is_panic());
+    let url = receiver.recv().unwrap();
+    assert!(PgConnection::connect(&url).await.is_err());
+}
+
+// no `botanist_test_*` database should be left once it failed
+#[apply(db_test!)]
+#[ignore = "panics on purpose, run it to check that its database is still dropped"]
+async fn db_test_panicking(_pool: PgPool) -> Result<()> {
+    panic!("deliberate panic")
+}
@@ -0,0 +1,44 @@
+use crate::id::UserId;
+use crate::{
+    utils::{try_from_i64, try_to_i64},
+    AdapterError,
+};
+
+#[test]
+fn test_round_trip() {
+    let id = UserId(87038540);
+    assert_eq!(try_to_i64(id).unwrap(), 87038540);
+    assert_eq!(try_from_i64::<UserId>(try_to_i64(id).unwrap()).unwrap(), id);
+}
+
+#[test]
+fn test_try_from_negative() {
+    assert!(matches!(
+        try_from_i64::<UserId>(-1),
+        Err(AdapterError::IdOutOfRange { value: -1 })
+    ));
+    assert_eq!(
+        try_from_i64::<UserId>(i64::MAX).unwrap(),
+        UserId(i64::MAX as u64)
+    );
+}
+
+#[test]
+fn test_try_to_out_of_range() {
+    assert_eq!(try_to_i64(UserId(i64::MAX as u64)).unwrap(), i64::MAX);
+    match try_to_i64(UserId(i64::MAX as u64 + 1)) {
+        Err(AdapterError::IdOutOfRange { value }) => assert_eq!(value, i64::MAX as i128 + 1),
+        other => panic!("expected an out of range error, got {:?}", other),
+    }
+    assert_eq!(try_to_i64(UserId(0)).unwrap(), 0);
+}
+
+#[cfg(feature = "utils")]
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_helpers() {
+    use crate::utils::{from_i64, to_i64};
+    let id = UserId(87038540);
+    assert_eq!(from_i64::<UserId>(to_i64(id)), id);
+    assert!(std::panic::catch_unwind(|| from_i64::<UserId>(-1)).is_err());
+}
@@ -0,0 +1,51 @@
+//! Conversions between discord IDs and their database representation
+//!
+//! Discord IDs (snowflakes) are `u64` but postgres has no unsigned integers so they are stored as `bigint`
+//! (`i64`). Snowflakes only use their 63 lower bits until 2084 so every genuine ID fits in both types. Values
+//! that don't, such as a forged ID or a row edited by hand, are reported with [`AdapterError::IdOutOfRange`]
+//! rather than panicking.
+//!
+//! This module is only public with the `utils` feature. It is meant for crates that extend [`crate`] with their
+//! own queries.
+
+use crate::AdapterError;
+use std::convert::TryFrom;
+
+/// Converts a `bigint` from the database into an ID
+///
+/// # Errors
+/// Returns [`AdapterError::IdOutOfRange`] if `int` is negative.
+pub fn try_from_i64<I: From<u64>>(int: i64) -> Result<I, AdapterError> {
+    u64::try_from(int)
+        .map(I::from)
+        .map_err(|_| AdapterError::IdOutOfRange { value: int.into() })
+}
+
+/// Converts an ID into a `bigint` for the database
+///
+/// # Errors
+/// Returns [`AdapterError::IdOutOfRange`] if `id` is over [`i64::MAX`].
+pub fn try_to_i64<I: Into<u64>>(id: I) -> Result<i64, AdapterError> {
+    let id = id.into();
+    i64::try_from(id).map_err(|_| AdapterError::IdOutOfRange { value: id.into() })
+}
+
+/// Converts a `bigint` from the database into an ID
+///
+/// # Panic
+/// Panics if `int` is negative.
+#[cfg(feature = "utils")]
+#[deprecated(note = "panics on negative values, use `try_from_i64` instead")]
+pub fn from_i64<I: From<u64>>(int: i64) -> I {
+    try_from_i64(int).expect("IDs can't be negative")
+}
+
+/// Converts an ID into a `bigint` for the database
+///
+/// # Panic
+/// Panics if `id` doesn't fit in an `i64`.
+#[cfg(feature = "utils")]
+#[deprecated(note = "panics on IDs over `i64::MAX`, use `try_to_i64` instead")]
+pub fn to_i64<I: Into<u64>>(id: I) -> i64 {
+    try_to_i64(id).expect("IDs must fit in an i64")
+}